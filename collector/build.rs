@@ -1,3 +1,15 @@
+use std::process::Command;
+
 fn main() {
     lalrpop::process_root().unwrap();
+
+    // Recorded in the metadata of result sets.
+    let hash = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=COLLECTOR_GIT_HASH={}", hash);
 }
@@ -0,0 +1,181 @@
+use std::{
+    fs::{self, create_dir_all, read_dir, File},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::toolchain::Bencher;
+
+use super::{benchmark::Benchamrk, scenario::Scenario};
+
+pub const INDEX_FILE: &str = "index.json";
+
+/// A profiler output moved out of the temporary benchmark dir.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Artifact {
+    pub iteration: usize,
+    pub scenario: Scenario,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
+    pub tool: String,
+    /// File name, relative to the directory of the index.
+    pub file: String,
+}
+
+/// The artifacts of one benchmark and profile, stored in
+/// `out_dir/<benchmark>/<profile>/` next to an `index.json` listing them.
+pub struct ArtifactIndex {
+    pub dir: PathBuf,
+    pub artifacts: Vec<Artifact>,
+}
+
+impl ArtifactIndex {
+    pub fn new(dir: PathBuf) -> Self {
+        ArtifactIndex {
+            dir,
+            artifacts: vec![],
+        }
+    }
+
+    /// Move the artifacts `bencher` left in `cwd` into the index dir.
+    /// Processors building several packages prefix the artifact with the
+    /// package name, e.g. `foo_cgout`.
+    pub fn collect(
+        &mut self,
+        cwd: &Path,
+        bencher: Bencher,
+        iteration: usize,
+        scenario: Scenario,
+        patch: Option<&str>,
+    ) -> anyhow::Result<()> {
+        for src in find_artifacts(cwd, bencher)? {
+            create_dir_all(&self.dir)
+                .with_context(|| format!("failed to create {:?}", self.dir))?;
+
+            let src_name = src.file_name().unwrap().to_string_lossy();
+            let file = match patch {
+                Some(patch) => format!("{:02}_{}_{}_{}", iteration, scenario, patch, src_name),
+                None => format!("{:02}_{}_{}", iteration, scenario, src_name),
+            };
+            Benchamrk::copy(&src, &self.dir.join(&file))?;
+            remove(&src)?;
+
+            self.artifacts.push(Artifact {
+                iteration,
+                scenario,
+                patch: patch.map(String::from),
+                tool: format!("{:?}", bencher),
+                file,
+            });
+        }
+        Ok(())
+    }
+
+    /// Remove the artifacts of a build that is not recorded, so they are not
+    /// picked up by a later build in the same dir.
+    pub fn discard(cwd: &Path, bencher: Bencher) -> anyhow::Result<()> {
+        for src in find_artifacts(cwd, bencher)? {
+            remove(&src)?;
+        }
+        Ok(())
+    }
+
+    pub fn write(&self) -> anyhow::Result<()> {
+        if self.artifacts.is_empty() {
+            return Ok(());
+        }
+        let path = self.dir.join(INDEX_FILE);
+        serde_json::to_writer_pretty(
+            File::create(&path).with_context(|| format!("failed to create {:?}", path))?,
+            &self.artifacts,
+        )
+        .with_context(|| format!("failed to write {:?}", path))?;
+        Ok(())
+    }
+}
+
+fn find_artifacts(cwd: &Path, bencher: Bencher) -> anyhow::Result<Vec<PathBuf>> {
+    let artifact = match bencher.artifact() {
+        Some(artifact) => artifact,
+        None => return Ok(vec![]),
+    };
+    let suffix = format!("_{}", artifact);
+
+    let mut paths = vec![];
+    for entry in read_dir(cwd)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name == artifact || name.ends_with(&suffix) {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn remove(path: &Path) -> anyhow::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+    .with_context(|| format!("failed to remove {:?}", path))
+}
+
+#[cfg(test)]
+mod test_artifact {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use crate::{benchmark::scenario::Scenario, toolchain::Bencher};
+
+    use super::{Artifact, ArtifactIndex, INDEX_FILE};
+
+    /// Test for ArtifactIndex
+    ///
+    /// Step1. Leave a `cgout` and a package prefixed `foo_cgout` in a fake
+    /// build dir, together with a file that is not an artifact.
+    ///
+    /// Step2. Collect them and verify they are moved and listed in the index.
+    #[test]
+    fn test_collect_artifacts() {
+        let cwd = TempDir::new().unwrap();
+        let out_dir = TempDir::new().unwrap();
+        fs::write(cwd.path().join("cgout"), "events: Ir").unwrap();
+        fs::write(cwd.path().join("foo_cgout"), "events: Ir").unwrap();
+        fs::write(cwd.path().join("clgout"), "events: Ir").unwrap();
+
+        let mut index = ArtifactIndex::new(out_dir.path().join("helloworld").join("debug"));
+        index
+            .collect(cwd.path(), Bencher::Cachegrind, 1, Scenario::Full, None)
+            .unwrap();
+        index.write().unwrap();
+
+        assert_eq!(
+            index.artifacts.iter().map(|a| &a.file).collect::<Vec<_>>(),
+            vec!["01_full_cgout", "01_full_foo_cgout"]
+        );
+        assert!(!cwd.path().join("cgout").exists());
+        assert!(cwd.path().join("clgout").exists());
+        assert!(index.dir.join("01_full_foo_cgout").is_file());
+
+        let written: Vec<Artifact> =
+            serde_json::from_str(&fs::read_to_string(index.dir.join(INDEX_FILE)).unwrap()).unwrap();
+        assert_eq!(written, index.artifacts);
+        assert_eq!(written[0].tool, "Cachegrind");
+    }
+
+    #[test]
+    fn test_discard_artifacts() {
+        let cwd = TempDir::new().unwrap();
+        fs::create_dir(cwd.path().join("Zsp")).unwrap();
+        fs::write(cwd.path().join("Zsp").join("foo-1.mm_profdata"), "").unwrap();
+
+        ArtifactIndex::discard(cwd.path(), Bencher::SelfProfile).unwrap();
+        assert!(!cwd.path().join("Zsp").exists());
+    }
+}
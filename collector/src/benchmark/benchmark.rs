@@ -1,8 +1,9 @@
 use std::{
-    collections::HashMap,
-    fs::{create_dir_all, read_dir, File},
+    collections::{BTreeMap, HashMap},
+    fs::{create_dir_all, read_dir},
     mem::ManuallyDrop,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow::{bail, Context, Ok};
@@ -12,23 +13,40 @@ use tempfile::TempDir;
 use crate::{
     compile_time::{
         cargo_package_process::CargoPackageProcess, cargo_single_process::CargoSingleProcess,
-        CompileTimeProcessor,
+        CompileTimeProcessor, CompileTimeRun,
     },
+    execute::{events::EventSet, Stats},
     runtime::{
         cargo_bench_process::CargoBenchProcess, cargo_example_process::CargoExampleProcess,
         cargo_package_process::CargoPackageTestProcess, cargo_test_process::CargoTestProcess,
-        measure::RuntimeProcess, Runtime,
+        measure::RuntimeProcess, Runtime, TestExecutables,
+    },
+    statistics::{
+        compile_time_stat::{CompileTimeBenchResult, CompileTimeResult},
+        stability::StopRule,
     },
-    statistics::compile_time_stat::{CompileTimeBenchResult, CompileTimeResult},
     toolchain::{Compiler, PerfTool},
+    utils::{command::is_timeout, rng::SplitMix64},
 };
 
-use super::{profile::Profile, scenario::Scenario};
+use super::{
+    artifact::ArtifactIndex,
+    build_config::BuildConfig,
+    journal::Journal,
+    patch::Patch,
+    profile::Profile,
+    scenario::Scenario,
+    suit::{load_config, RawConfig, CONFIG_FILE},
+};
 
 fn default_runs() -> usize {
     3
 }
 
+fn default_weight() -> f64 {
+    1.0
+}
+
 pub struct Benchamrk {
     pub name: String,
     pub path: PathBuf,
@@ -36,46 +54,198 @@ pub struct Benchamrk {
 }
 
 impl Benchamrk {
+    /// Load a single benchmark, without suite defaults. Use
+    /// `BenchmarkSuit::load` to discover a whole suite.
     pub fn new(name: String, path: PathBuf) -> anyhow::Result<Self> {
-        let config_path = path.join("perf-config.json");
-        let config: BenchmarkConfig = if config_path.exists() {
-            serde_json::from_reader(
-                File::open(&config_path)
-                    .with_context(|| format!("failed to open {:?}", config_path))?,
-            )
-            .with_context(|| format!("failed to parse {:?}", config_path))?
-        } else {
+        if !path.join(CONFIG_FILE).exists() {
             bail!("missing a perf-config.json file for `{}`", name);
-        };
-        Ok(Benchamrk { name, path, config })
+        }
+        match load_config(&path, &RawConfig::new()) {
+            core::result::Result::Ok(config) => Ok(Benchamrk { name, path, config }),
+            Err(errors) => bail!(
+                "invalid config for `{}`:\n{}",
+                name,
+                errors
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+        }
+    }
+
+    /// Timeout of each build or run, `default` if the config does not set one.
+    pub fn timeout(&self, default: Option<u64>) -> Option<Duration> {
+        self.config
+            .timeout_secs
+            .or(default)
+            .map(Duration::from_secs)
+    }
+
+    /// Number of warm-up runs, `default` if the config does not set one.
+    pub fn warmup(&self, default: usize) -> usize {
+        self.config.warmup.unwrap_or(default)
+    }
+
+    /// The build config named `name`, the build without a config for `None`.
+    /// `None` if the benchmark has no such config.
+    pub fn build_config(&self, name: Option<&str>) -> Option<BuildConfig> {
+        match name {
+            Some(name) => self.config.build_configs.get(name).cloned(),
+            None => Some(BuildConfig::default()),
+        }
+    }
+
+    /// Result of an iteration whose build was killed after the timeout.
+    fn timed_out_result(
+        &self,
+        iteration: usize,
+        profile: Profile,
+        scenario: Scenario,
+    ) -> CompileTimeResult {
+        CompileTimeResult::new(
+            self.name.clone(),
+            iteration,
+            profile,
+            scenario,
+            Stats::timed_out(),
+        )
     }
 
+    /// Results of every build of `scenarios` left out because an earlier
+    /// build of the iteration was killed after the timeout, one for each
+    /// patch of `Scenario::IncrPatched`.
+    fn timed_out_results<'s>(
+        &self,
+        iteration: usize,
+        profile: Profile,
+        scenarios: impl Iterator<Item = &'s Scenario>,
+    ) -> anyhow::Result<Vec<CompileTimeResult>> {
+        let mut results = vec![];
+        for scenario in scenarios {
+            if *scenario == Scenario::IncrPatched {
+                for patch in self.patches()? {
+                    results.push(
+                        self.timed_out_result(iteration, profile, *scenario)
+                            .with_patch(patch.name),
+                    );
+                }
+            } else {
+                results.push(self.timed_out_result(iteration, profile, *scenario));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Whether the journal holds every result of `iteration`. Iterations
+    /// resumed with some results missing are measured again as a whole, as
+    /// the incremental builds depend on each other.
+    fn is_measured(
+        &self,
+        journal: &Journal,
+        profile: Profile,
+        build_config: Option<&str>,
+        scenarios: &[Scenario],
+        iteration: usize,
+    ) -> bool {
+        // Results are recorded under the file stem of the patch, see `Patch`.
+        let patches = self
+            .config
+            .patches
+            .iter()
+            .map(|p| Path::new(p).file_stem().and_then(|s| s.to_str()));
+        scenarios
+            .iter()
+            // Rustdoc does not support incremental compilation
+            .filter(|s| profile != Profile::Doc || !s.is_increment())
+            .all(|scenario| match scenario {
+                Scenario::IncrPatched => patches.clone().all(|patch| {
+                    journal.is_done(
+                        &self.name,
+                        profile,
+                        build_config,
+                        *scenario,
+                        patch,
+                        iteration,
+                    )
+                }),
+                _ => journal.is_done(
+                    &self.name,
+                    profile,
+                    build_config,
+                    *scenario,
+                    None,
+                    iteration,
+                ),
+            })
+    }
+
+    /// Patches listed in `perf-config.json`, resolved against the `patches/`
+    /// directory of the benchmark.
+    pub fn patches(&self) -> anyhow::Result<Vec<Patch>> {
+        self.config
+            .patches
+            .iter()
+            .map(|p| Patch::new(self.path.join("patches").join(p)))
+            .collect()
+    }
+
+    /// Measure the benchmark with the compiler of every run in `runs`. With
+    /// several runs, their builds are interleaved: every iteration is
+    /// measured with each compiler in an order shuffled by `rng`, so that
+    /// drifts of the machine spread over all of them. The benchmark is built
+    /// with the build config named `build_config`, see `Benchamrk::build_config`.
+    /// Returns the results of each run.
     pub fn measure_compile_time(
         &self,
         perf_tool: &PerfTool,
-        event_filter_file: &PathBuf,
+        events: &EventSet,
         profiles: &[Profile],
+        build_config: Option<&str>,
         scenarios: &[Scenario],
-        compiler: Compiler<'_>,
+        runs: &mut [CompileTimeRun<'_>],
         iterations: Option<usize>,
-        flame_graph_result_path: &Path,
         flamegraph_flag: i32,
-        out_dir: &Path,
-    ) -> anyhow::Result<CompileTimeBenchResult> {
+        timeout: Option<u64>,
+        stop_rule: Option<&StopRule>,
+        warmup: usize,
+        rng: &mut SplitMix64,
+    ) -> anyhow::Result<Vec<CompileTimeBenchResult>> {
         if self.config.disabled {
             println!("Skipping {}: disabled", self.name);
             bail!("disabled benchmark");
         }
 
         let iterations = iterations.unwrap_or(self.config.runs);
+        let timeout = self.timeout(timeout);
+        let warmup = self.warmup(warmup);
 
-        let mut bench_result = CompileTimeBenchResult::new(self.name.clone(), iterations);
+        let mut bench_results: Vec<CompileTimeBenchResult> = runs
+            .iter()
+            .map(|_| CompileTimeBenchResult::new(self.name.clone(), iterations))
+            .collect();
 
         if profiles.is_empty() {
             println!("Skipping {}: no profiles selected", self.name);
-            return Ok(bench_result);
+            return Ok(bench_results);
+        }
+        let Some(config) = self.build_config(build_config) else {
+            println!(
+                "Skipping {}: no build config `{}`",
+                self.name,
+                build_config.unwrap_or_default()
+            );
+            return Ok(bench_results);
+        };
+        if runs.iter().all(|run| {
+            profiles.iter().all(|profile| {
+                (1..=iterations)
+                    .all(|i| self.is_measured(&run.journal, *profile, build_config, scenarios, i))
+            })
+        }) {
+            println!("Skipping {}: already measured", self.name);
+            return Ok(bench_results);
         }
-
         println!("Preparing {}...", self.name);
 
         let profile_dirs = profiles
@@ -83,43 +253,106 @@ impl Benchamrk {
             .map(|profile| Ok((*profile, self.make_temp_dir(&self.path)?)))
             .collect::<anyhow::Result<Vec<_>>>()?;
 
+        let bencher = perf_tool.get_bencher();
+        // The build config is named, the toolchain only when there is more
+        // than one.
+        let interleaved = runs.len() > 1;
+        let tag = |run: &CompileTimeRun<'_>| {
+            let names: Vec<&str> = build_config
+                .into_iter()
+                .chain(interleaved.then_some(run.id.as_str()))
+                .collect();
+            if names.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", names.join(", "))
+            }
+        };
+        // Artifacts of a build config are kept apart from the default build.
+        let artifact_dir = |out_dir: &Path| match build_config {
+            Some(config) => out_dir.join(&self.name).join(config),
+            None => out_dir.join(&self.name),
+        };
+        // A toolchain failing to build the benchmark is left out of the
+        // later builds, the other toolchains keep on measuring it.
+        let mut failed = vec![false; runs.len()];
         for (profile, dir) in profile_dirs {
-            let mut result_map = HashMap::new();
-            scenarios.iter().for_each(|scenario| {
-                result_map.insert(scenario.clone(), vec![]);
-            });
+            let mut states: Vec<(ArtifactIndex, HashMap<Scenario, Vec<CompileTimeResult>>)> = runs
+                .iter()
+                .map(|run| {
+                    let artifacts =
+                        ArtifactIndex::new(artifact_dir(&run.out_dir).join(profile.to_string()));
+                    let result_map = scenarios.iter().map(|s| (*s, vec![])).collect();
+                    (artifacts, result_map)
+                })
+                .collect();
+            let mut stable = vec![false; runs.len()];
 
-            for i in 0..iterations {
+            let measure = |run: &mut CompileTimeRun<'_>,
+                           artifacts: &mut ArtifactIndex,
+                           result_map: &mut HashMap<Scenario, Vec<CompileTimeResult>>,
+                           i: usize|
+             -> anyhow::Result<()> {
+                // Warm-up runs are numbered apart from the measured iterations.
+                let is_warmup = i < warmup;
+                let (iteration, count) = if is_warmup {
+                    (i + 1, warmup)
+                } else {
+                    (i - warmup + 1, iterations)
+                };
                 println!(
-                    "running '{}' {:?} + {:?} iteration {}/{}",
+                    "running '{}'{} {:?} + {:?} {} {}/{}",
                     self.name,
+                    tag(run),
                     profile,
                     scenarios,
-                    i + 1,
-                    iterations
+                    if is_warmup { "warm-up" } else { "iteration" },
+                    iteration,
+                    count
                 );
                 let timing_dir = ManuallyDrop::new(self.make_temp_dir(dir.path())?);
+                // Warm-up runs leave no profiler output behind.
+                let mut collect_artifacts =
+                    |cwd: &Path, scenario: Scenario, patch: Option<&str>| {
+                        if is_warmup {
+                            ArtifactIndex::discard(cwd, bencher)
+                        } else {
+                            artifacts.collect(cwd, bencher, iteration, scenario, patch)
+                        }
+                    };
 
                 let cwd = timing_dir.path();
 
                 if scenarios.contains(&Scenario::Full) {
-                    let mut cargo_process = self.make_cargo_process(compiler, cwd, profile);
-                    cargo_process.gen_pkg()?;
-                    let stats = cargo_process
-                        .run_rustc(perf_tool, event_filter_file, true)
-                        .with_context(|| format!("Fail to run rust_c for {}", self.name));
+                    let mut cargo_process =
+                        self.make_cargo_process(run.compiler, cwd, profile, &config, timeout);
+                    // A dependency build running into the timeout counts as
+                    // a timed-out iteration, other failures abort the benchmark.
+                    let stats = match cargo_process.gen_pkg() {
+                        Err(e) if !is_timeout(&e) => return Err(e),
+                        gen => gen.and_then(|_| cargo_process.run_rustc(perf_tool, events, true)),
+                    }
+                    .with_context(|| format!("Fail to run rust_c for {}", self.name));
 
                     match stats {
                         Result::Ok(stats) => {
-                            if let Some(stats) = stats {
+                            collect_artifacts(cwd, Scenario::Full, None)?;
+                            if let Some((stats, self_profile)) = stats {
                                 let result_vec = result_map.get_mut(&Scenario::Full).unwrap();
-                                result_vec.push(CompileTimeResult::new(
-                                    self.name.clone(),
-                                    i + 1,
-                                    profile,
-                                    Scenario::Full,
-                                    stats,
-                                ));
+                                run.journal.record(
+                                    iterations,
+                                    result_vec,
+                                    CompileTimeResult::new(
+                                        self.name.clone(),
+                                        iteration,
+                                        profile,
+                                        Scenario::Full,
+                                        stats,
+                                    )
+                                    .with_self_profile(self_profile)
+                                    .with_warmup(is_warmup)
+                                    .with_build_config(build_config),
+                                )?;
                             }
                         }
                         Err(s) => {
@@ -127,9 +360,25 @@ impl Benchamrk {
                                 "Fail to bench {} with Profile {:?}: {:?}. Skip.",
                                 self.name, profile, s
                             );
+                            if is_timeout(&s) {
+                                // The later builds of the iteration are not run.
+                                let skipped = scenarios.iter().filter(|s| {
+                                    // Rustdoc does not support incremental compilation
+                                    profile != Profile::Doc || !s.is_increment()
+                                });
+                                for result in self.timed_out_results(iteration, profile, skipped)? {
+                                    run.journal.record(
+                                        iterations,
+                                        result_map.get_mut(&result.scenario).unwrap(),
+                                        result
+                                            .with_warmup(is_warmup)
+                                            .with_build_config(build_config),
+                                    )?;
+                                }
+                            }
                             drop(cargo_process);
                             drop(ManuallyDrop::into_inner(timing_dir));
-                            continue;
+                            return Ok(());
                         }
                     }
                 }
@@ -138,23 +387,39 @@ impl Benchamrk {
                     // An incremental  from scratch (slowest incremental case).
                     // This is required for any subsequent incremental builds.
                     if scenarios.iter().any(|s| s.is_increment()) {
-                        let mut process = self.make_cargo_process(compiler, cwd, profile);
+                        let mut process =
+                            self.make_cargo_process(run.compiler, cwd, profile, &config, timeout);
                         process.increment(true);
                         let stats = process
-                            .run_rustc(perf_tool, event_filter_file, true)
+                            .run_rustc(perf_tool, events, true)
                             .with_context(|| format!("Fail to run rust_c for {}", self.name));
 
                         match stats {
                             Result::Ok(stats) => {
-                                if let Some(stats) = stats {
-                                    let result_vec = result_map.get_mut(&Scenario::Full).unwrap();
-                                    result_vec.push(CompileTimeResult::new(
-                                        self.name.clone(),
-                                        i + 1,
-                                        profile,
-                                        Scenario::Full,
-                                        stats,
-                                    ));
+                                // This build is always run as the base of the other
+                                // incremental scenarios, but only recorded on request.
+                                if scenarios.contains(&Scenario::IncrFull) {
+                                    collect_artifacts(cwd, Scenario::IncrFull, None)?;
+                                } else {
+                                    ArtifactIndex::discard(cwd, bencher)?;
+                                }
+                                if let (Some((stats, self_profile)), Some(result_vec)) =
+                                    (stats, result_map.get_mut(&Scenario::IncrFull))
+                                {
+                                    run.journal.record(
+                                        iterations,
+                                        result_vec,
+                                        CompileTimeResult::new(
+                                            self.name.clone(),
+                                            iteration,
+                                            profile,
+                                            Scenario::IncrFull,
+                                            stats,
+                                        )
+                                        .with_self_profile(self_profile)
+                                        .with_warmup(is_warmup)
+                                        .with_build_config(build_config),
+                                    )?;
                                 }
                             }
                             Err(s) => {
@@ -162,91 +427,268 @@ impl Benchamrk {
                                     "Fail to bench {} with Profile {:?}: {:?}. Skip.",
                                     self.name, profile, s
                                 );
+                                if is_timeout(&s) {
+                                    // The other incremental builds depend on this one.
+                                    let skipped = scenarios.iter().filter(|s| s.is_increment());
+                                    for result in
+                                        self.timed_out_results(iteration, profile, skipped)?
+                                    {
+                                        run.journal.record(
+                                            iterations,
+                                            result_map.get_mut(&result.scenario).unwrap(),
+                                            result
+                                                .with_warmup(is_warmup)
+                                                .with_build_config(build_config),
+                                        )?;
+                                    }
+                                }
                                 drop(process);
                                 drop(ManuallyDrop::into_inner(timing_dir));
-                                continue;
+                                return Ok(());
                             }
                         }
                     }
 
                     // An incremental build with no changes (fastest incremental case).
                     if scenarios.contains(&Scenario::IncrUnchanged) {
-                        let mut process = self.make_cargo_process(compiler, cwd, profile);
+                        let mut process =
+                            self.make_cargo_process(run.compiler, cwd, profile, &config, timeout);
                         process.increment(true);
-                        let stats = process.run_rustc(perf_tool, event_filter_file, true)?;
-                        let result_vec = result_map.get_mut(&Scenario::Full).unwrap();
+                        let result_vec = result_map.get_mut(&Scenario::IncrUnchanged).unwrap();
+                        match process.run_rustc(perf_tool, events, true) {
+                            Result::Ok(stats) => {
+                                collect_artifacts(cwd, Scenario::IncrUnchanged, None)?;
+                                if let Some((stats, self_profile)) = stats {
+                                    run.journal.record(
+                                        iterations,
+                                        result_vec,
+                                        CompileTimeResult::new(
+                                            self.name.clone(),
+                                            iteration,
+                                            profile,
+                                            Scenario::IncrUnchanged,
+                                            stats,
+                                        )
+                                        .with_self_profile(self_profile)
+                                        .with_warmup(is_warmup)
+                                        .with_build_config(build_config),
+                                    )?;
+                                }
+                            }
+                            Err(s) if is_timeout(&s) => {
+                                eprintln!(
+                                    "Fail to bench {} with Profile {:?}: {:?}. Skip.",
+                                    self.name, profile, s
+                                );
+                                ArtifactIndex::discard(cwd, bencher)?;
+                                run.journal.record(
+                                    iterations,
+                                    result_vec,
+                                    self.timed_out_result(
+                                        iteration,
+                                        profile,
+                                        Scenario::IncrUnchanged,
+                                    )
+                                    .with_warmup(is_warmup)
+                                    .with_build_config(build_config),
+                                )?;
+                            }
+                            Err(s) => return Err(s),
+                        }
+                    }
 
-                        if let Some(stats) = stats {
-                            result_vec.push(CompileTimeResult::new(
-                                self.name.clone(),
-                                i + 1,
+                    // Incremental builds with some changes (realistic incremental
+                    // case). Patches are applied cumulatively on top of the
+                    // incremental state left by the builds above.
+                    if scenarios.contains(&Scenario::IncrPatched) {
+                        for patch in self.patches()? {
+                            patch.apply(cwd)?;
+
+                            let mut process = self.make_cargo_process(
+                                run.compiler,
+                                cwd,
                                 profile,
-                                Scenario::Full,
-                                stats,
-                            ));
+                                &config,
+                                timeout,
+                            );
+                            process.increment(true);
+                            let result_vec = result_map.get_mut(&Scenario::IncrPatched).unwrap();
+                            match process.run_rustc(perf_tool, events, true) {
+                                Result::Ok(stats) => {
+                                    collect_artifacts(
+                                        cwd,
+                                        Scenario::IncrPatched,
+                                        Some(&patch.name),
+                                    )?;
+                                    if let Some((stats, self_profile)) = stats {
+                                        run.journal.record(
+                                            iterations,
+                                            result_vec,
+                                            CompileTimeResult::new(
+                                                self.name.clone(),
+                                                iteration,
+                                                profile,
+                                                Scenario::IncrPatched,
+                                                stats,
+                                            )
+                                            .with_self_profile(self_profile)
+                                            .with_patch(patch.name)
+                                            .with_warmup(is_warmup)
+                                            .with_build_config(build_config),
+                                        )?;
+                                    }
+                                }
+                                Err(s) if is_timeout(&s) => {
+                                    eprintln!(
+                                        "Fail to bench {} with Profile {:?}: {:?}. Skip.",
+                                        self.name, profile, s
+                                    );
+                                    ArtifactIndex::discard(cwd, bencher)?;
+                                    run.journal.record(
+                                        iterations,
+                                        result_vec,
+                                        self.timed_out_result(
+                                            iteration,
+                                            profile,
+                                            Scenario::IncrPatched,
+                                        )
+                                        .with_patch(patch.name)
+                                        .with_warmup(is_warmup)
+                                        .with_build_config(build_config),
+                                    )?;
+                                }
+                                Err(s) => return Err(s),
+                            }
                         }
                     }
 
                     // If BenchTool is PerfRecord, we need to move perf.data
-                    // out of the temp dir.
-                    match perf_tool.get_bencher() {
-                        crate::toolchain::Bencher::PerfStat => (),
-                        crate::toolchain::Bencher::PerfRecord => {
-                            let cwd = timing_dir.path();
-                            let dst_dir = out_dir.join(self.name.clone());
-
-                            let _ = create_dir_all(dst_dir.clone());
-
-                            // Find file whoose name contains "perf.analyze" in directory cwd,
-                            // and copy it into directory dst_dir.
-                            for entry in read_dir(cwd)? {
-                                let entry = entry?;
-                                if entry.file_name().to_str().unwrap().contains("perf.data") {
-                                    let src_path = PathBuf::from(entry.file_name());
-                                    let dst_path = dst_dir.join(format!(
-                                        "{}_{:02}_{}",
-                                        profile,
-                                        i,
-                                        src_path.as_os_str().to_str().unwrap()
-                                    ));
-                                    if let Err(err) = std::fs::copy(cwd.join(src_path), &dst_path) {
-                                        eprintln!("Failed to copy 'perf.data' file: {}", err);
-                                    }
+                    // out of the temp dir. Warm-up runs leave no profiler
+                    // output behind.
+                    if bencher == crate::toolchain::Bencher::PerfRecord && !is_warmup {
+                        let cwd = timing_dir.path();
+                        let dst_dir = artifact_dir(&run.out_dir);
+
+                        let _ = create_dir_all(dst_dir.clone());
+
+                        // Find file whoose name contains "perf.analyze" in directory cwd,
+                        // and copy it into directory dst_dir.
+                        for entry in read_dir(cwd)? {
+                            let entry = entry?;
+                            if entry.file_name().to_str().unwrap().contains("perf.data") {
+                                let src_path = PathBuf::from(entry.file_name());
+                                let dst_path = dst_dir.join(format!(
+                                    "{}_{:02}_{}",
+                                    profile,
+                                    iteration,
+                                    src_path.as_os_str().to_str().unwrap()
+                                ));
+                                if let Err(err) = std::fs::copy(cwd.join(src_path), &dst_path) {
+                                    eprintln!("Failed to copy 'perf.data' file: {}", err);
                                 }
                             }
                         }
                     }
                 }
                 drop(ManuallyDrop::into_inner(timing_dir));
+                Ok(())
+            };
+
+            for i in 0..warmup + iterations {
+                let mut order: Vec<usize> = (0..runs.len()).collect();
+                rng.shuffle(&mut order);
+                for r in order {
+                    let run = &mut runs[r];
+                    if failed[r] {
+                        continue;
+                    }
+                    if i >= warmup {
+                        let iteration = i - warmup + 1;
+                        if stable[r] {
+                            continue;
+                        }
+                        if stop_rule.is_some_and(|rule| {
+                            run.journal
+                                .is_stable(&self.name, profile, build_config, rule)
+                        }) {
+                            println!(
+                                "'{}'{} {:?} is stable after {} iterations",
+                                self.name,
+                                tag(run),
+                                profile,
+                                iteration - 1
+                            );
+                            stable[r] = true;
+                            continue;
+                        }
+                        if self.is_measured(
+                            &run.journal,
+                            profile,
+                            build_config,
+                            scenarios,
+                            iteration,
+                        ) {
+                            continue;
+                        }
+                    }
+                    let (artifacts, result_map) = &mut states[r];
+                    match measure(run, artifacts, result_map, i) {
+                        Err(e) if interleaved => {
+                            eprintln!(
+                                "Fail to bench {} with {}: {:?}. Skip.",
+                                self.name, run.id, e
+                            );
+                            failed[r] = true;
+                        }
+                        result => result?,
+                    }
+                }
+                if stable.iter().zip(&failed).all(|(s, f)| *s || *f) {
+                    break;
+                }
+            }
+            if failed.iter().all(|f| *f) {
+                bail!("every toolchain failed to bench {}", self.name);
             }
 
-            result_map.iter().for_each(|(_scenario, results)| {
-                results.iter().for_each(|result| {
-                    bench_result.add_result(result.clone());
+            for (r, ((run, (artifacts, result_map)), bench_result)) in runs
+                .iter()
+                .zip(states)
+                .zip(bench_results.iter_mut())
+                .enumerate()
+            {
+                artifacts.write()?;
+
+                result_map.iter().for_each(|(_scenario, results)| {
+                    results.iter().for_each(|result| {
+                        bench_result.add_result(result.clone());
+                    });
                 });
-            });
 
-            //draw flamegraph with Full Scenario
-            if flamegraph_flag > 0 {
-                println!(
-                    "drawing flamegraph for '{}' {:?} + {:?}",
-                    self.name,
-                    profile,
-                    Scenario::Full
-                );
-                let timing_dir = ManuallyDrop::new(self.make_temp_dir(dir.path())?);
-                let cwd = timing_dir.path();
+                //draw flamegraph with Full Scenario
+                if flamegraph_flag > 0 && !failed[r] {
+                    println!(
+                        "drawing flamegraph for '{}'{} {:?} + {:?}",
+                        self.name,
+                        tag(run),
+                        profile,
+                        Scenario::Full
+                    );
+                    let timing_dir = ManuallyDrop::new(self.make_temp_dir(dir.path())?);
+                    let cwd = timing_dir.path();
 
-                let cargo_process = self.make_cargo_process(compiler, cwd, profile);
-                cargo_process.gen_pkg()?;
-                cargo_process.draw_flame_graph(flame_graph_result_path)?;
+                    let cargo_process =
+                        self.make_cargo_process(run.compiler, cwd, profile, &config, timeout);
+                    cargo_process.gen_pkg()?;
+                    cargo_process.draw_flame_graph(&run.flame_graph_path)?;
 
-                drop(cargo_process);
-                drop(ManuallyDrop::into_inner(timing_dir));
+                    drop(cargo_process);
+                    drop(ManuallyDrop::into_inner(timing_dir));
+                }
             }
         }
 
-        Ok(bench_result)
+        Ok(bench_results)
     }
 
     pub fn make_temp_dir(&self, base: &Path) -> anyhow::Result<TempDir> {
@@ -263,12 +705,12 @@ impl Benchamrk {
     }
 
     #[cfg(windows)]
-    fn copy(from: &Path, to: &Path) -> anyhow::Result<()> {
+    pub(super) fn copy(from: &Path, to: &Path) -> anyhow::Result<()> {
         crate::utils::fs::robocopy(from, to, &[])
     }
 
     #[cfg(unix)]
-    fn copy(from: &Path, to: &Path) -> anyhow::Result<()> {
+    pub(super) fn copy(from: &Path, to: &Path) -> anyhow::Result<()> {
         use crate::utils::command::command_output;
         use std::process::Command;
 
@@ -278,11 +720,15 @@ impl Benchamrk {
         Ok(())
     }
 
+    /// The process building the benchmark with `compiler` in `cwd`, with the
+    /// build config `config`.
     fn make_cargo_process<'a>(
         &'a self,
         compiler: Compiler<'a>,
         cwd: &'a Path,
         profile: Profile,
+        config: &BuildConfig,
+        timeout: Option<Duration>,
     ) -> Box<dyn CompileTimeProcessor + 'a> {
         let mut cargo_args = self
             .config
@@ -323,6 +769,8 @@ impl Benchamrk {
                             .map(String::from)
                             .collect(),
                         touch_file: self.config.touch_file.clone(),
+                        timeout,
+                        cargo_env: config.cargo_env(),
                     };
                     Box::new(process)
                 }
@@ -350,6 +798,8 @@ impl Benchamrk {
                                 .collect(),
                             touch_file: self.config.touch_file.clone(),
                             packages: packages.clone(),
+                            timeout,
+                            cargo_env: config.cargo_env(),
                         };
                         Box::new(process)
                     }
@@ -376,6 +826,8 @@ impl Benchamrk {
                                 .map(String::from)
                                 .collect(),
                             touch_file: self.config.touch_file.clone(),
+                            timeout,
+                            cargo_env: config.cargo_env(),
                         };
                         Box::new(process)
                     }
@@ -403,17 +855,25 @@ impl Benchamrk {
                         .map(String::from)
                         .collect(),
                     touch_file: self.config.touch_file.clone(),
+                    timeout,
+                    cargo_env: config.cargo_env(),
                 };
                 Box::new(process)
             }
         }
     }
 
+    /// The process building and running the benchmark with `compiler` in
+    /// `cwd`, with the build config `config`.
     pub fn make_runtime_process<'a>(
         &'a self,
         compiler: Compiler<'a>,
         cwd: &'a Path,
+        config: &BuildConfig,
         iterations: u32,
+        timeout: Option<Duration>,
+        stop_rule: Option<StopRule>,
+        warmup: u32,
     ) -> Result<Box<dyn Runtime + 'a>, String> {
         log::debug!(
             "make_runtime_process: get runtime args: {:?}",
@@ -440,6 +900,11 @@ impl Benchamrk {
                             .split_whitespace()
                             .map(String::from)
                             .collect(),
+                        timeout,
+                        cargo_env: config.cargo_env(),
+                        stop_rule,
+                        warmup,
+                        executables: TestExecutables::default(),
                     };
                     core::result::Result::Ok(Box::new(process))
                 }
@@ -450,7 +915,7 @@ impl Benchamrk {
                             self.name
                         ));
                     }
-                    let process = CargoExampleProcess::new(
+                    let mut process = CargoExampleProcess::new(
                         compiler,
                         self.name.clone(),
                         cwd,
@@ -467,11 +932,16 @@ impl Benchamrk {
                             .split_whitespace()
                             .map(String::from)
                             .collect(),
+                        config.cargo_env(),
                     );
+                    // Examples are built up front, only their runs time out.
+                    process.timeout = timeout;
+                    process.stop_rule = stop_rule;
+                    process.warmup = warmup;
                     core::result::Result::Ok(Box::new(process))
                 }
                 RuntimeTestType::Binary => {
-                    let process = RuntimeProcess::new(
+                    let mut process = RuntimeProcess::new(
                         compiler,
                         cwd,
                         self.name.clone(),
@@ -487,11 +957,15 @@ impl Benchamrk {
                             .clone()
                             .unwrap_or_else(|| String::from("Cargo.toml")),
                         iterations,
+                        timeout,
+                        config.cargo_env(),
                     );
+                    process.stop_rule = stop_rule;
+                    process.warmup = warmup;
                     core::result::Result::Ok(Box::new(process))
                 }
                 RuntimeTestType::Bench => {
-                    let process = CargoBenchProcess::new(
+                    let mut process = CargoBenchProcess::new(
                         compiler,
                         self.name.clone(),
                         cwd,
@@ -507,7 +981,11 @@ impl Benchamrk {
                             .split_whitespace()
                             .map(String::from)
                             .collect(),
+                        timeout,
+                        config.cargo_env(),
                     );
+                    process.stop_rule = stop_rule;
+                    process.warmup = warmup;
                     core::result::Result::Ok(Box::new(process))
                 }
                 RuntimeTestType::Packages => {
@@ -530,6 +1008,11 @@ impl Benchamrk {
                             .map(String::from)
                             .collect(),
                         packages: self.config.runtime_test_packages.clone().unwrap(),
+                        timeout,
+                        cargo_env: config.cargo_env(),
+                        stop_rule,
+                        warmup,
+                        executables: TestExecutables::default(),
                     }))
                 }
             },
@@ -552,6 +1035,11 @@ impl Benchamrk {
                         .split_whitespace()
                         .map(String::from)
                         .collect(),
+                    timeout,
+                    cargo_env: config.cargo_env(),
+                    stop_rule,
+                    warmup,
+                    executables: TestExecutables::default(),
                 };
                 core::result::Result::Ok(Box::new(process))
             }
@@ -560,6 +1048,7 @@ impl Benchamrk {
 }
 
 #[derive(Debug, Clone, serde::Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct BenchmarkConfig {
     pub cargo_opts: Option<String>,
     pub cargo_rustc_opts: Option<String>,
@@ -582,6 +1071,30 @@ pub struct BenchmarkConfig {
     pub disabled: bool,
     #[serde(default = "default_runs")]
     pub runs: usize,
+    /// Diffs under the `patches/` directory of the benchmark, applied in
+    /// order for the `IncrPatched` scenario.
+    #[serde(default)]
+    pub patches: Vec<String>,
+    /// Free-form labels, e.g. `small` or `ci`, to select benchmarks by with
+    /// `--tags`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Seconds after which a build or run of this benchmark is killed and
+    /// recorded as timed out. Overrides `--timeout`.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Warm-up runs before the measured iterations, left out of the
+    /// statistics. Overrides `--warmup`.
+    #[serde(default)]
+    pub warmup: Option<usize>,
+    /// How many times the benchmark counts in the suite-wide summary of
+    /// `summarize`, 0 to leave it out.
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    /// Named codegen options to sweep the benchmark across with
+    /// `--build-configs`, usually shared in a `suite-config.json`.
+    #[serde(default)]
+    pub build_configs: BTreeMap<String, BuildConfig>,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -601,18 +1114,86 @@ pub enum CompileTimeType {
     Packages,
 }
 
-pub struct BenchmarkSuit {
-    pub benchmarks: Vec<Benchamrk>,
-}
+#[cfg(test)]
+mod test_benchmark {
+    use std::path::PathBuf;
+
+    use tempfile::TempDir;
+
+    use crate::benchmark::{journal::Journal, profile::Profile, scenario::Scenario};
+
+    use super::{Benchamrk, BenchmarkConfig};
 
-impl BenchmarkSuit {
-    pub fn display_benchmarks(&self) -> String {
-        let mut names = String::from("+BenchmarkSuit");
-        for b in self.benchmarks.iter() {
-            names = names + "\n| ";
-            names = names + &b.name;
-            names = names + " " + b.path.to_str().unwrap().as_ref();
+    /// Test for Benchamrk::new
+    ///
+    /// Step1. Load a benchmark with a `perf-config.json`.
+    ///
+    /// Step2. Verify a benchmark without one is rejected.
+    #[test]
+    fn test_new_benchmark() {
+        let plus = Benchamrk::new(
+            "plus".to_string(),
+            PathBuf::from("test/benchmark/suit/group/plus"),
+        )
+        .unwrap();
+        assert_eq!(plus.name, "plus");
+
+        let err = Benchamrk::new("patch".to_string(), PathBuf::from("test/benchmark/patch"))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("missing a perf-config.json"));
+    }
+
+    /// Test for resuming a benchmark whose full build timed out
+    ///
+    /// Step1. Record the timed-out results of the first iteration, which
+    /// stand in for every scenario and patch.
+    ///
+    /// Step2. Resume and verify the iteration counts as measured and its
+    /// results are not recorded twice.
+    #[test]
+    fn test_resume_full_timeout() {
+        let benchmark = Benchamrk {
+            name: "patch".to_string(),
+            path: PathBuf::from("test/benchmark/patch"),
+            config: BenchmarkConfig {
+                patches: vec!["0-println.patch".to_string()],
+                ..Default::default()
+            },
+        };
+        let scenarios = [
+            Scenario::Full,
+            Scenario::IncrFull,
+            Scenario::IncrUnchanged,
+            Scenario::IncrPatched,
+        ];
+        let out_dir = TempDir::new().unwrap();
+        let mut journal = Journal::open(out_dir.path(), false).unwrap();
+        let mut results = vec![];
+        let timed_out = benchmark
+            .timed_out_results(1, Profile::Debug, scenarios.iter())
+            .unwrap();
+        assert_eq!(timed_out.len(), 4);
+        assert_eq!(timed_out[3].patch.as_deref(), Some("0-println"));
+        for result in timed_out {
+            journal.record(2, &mut results, result).unwrap();
+        }
+        assert_eq!(results.len(), 4);
+        drop(journal);
+
+        let mut journal = Journal::open(out_dir.path(), true).unwrap();
+        assert!(benchmark.is_measured(&journal, Profile::Debug, None, &scenarios, 1));
+        assert!(!benchmark.is_measured(&journal, Profile::Debug, None, &scenarios, 2));
+        let mut results = vec![];
+        for result in benchmark
+            .timed_out_results(1, Profile::Debug, scenarios.iter())
+            .unwrap()
+        {
+            journal.record(2, &mut results, result).unwrap();
         }
-        names
+        assert!(results.is_empty());
+        let merged = journal.results();
+        assert_eq!(merged[0].result_vec.len(), 4);
+        assert!(merged[0].result_vec.iter().all(|r| r.stats.timed_out));
     }
 }
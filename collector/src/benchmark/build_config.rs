@@ -0,0 +1,207 @@
+use std::{env, str::FromStr};
+
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Name standing for the build of a benchmark without any build config.
+pub const DEFAULT_BUILD_CONFIG: &str = "default";
+
+/// Cargo profiles a build config overrides. The `test` and `bench` profiles
+/// inherit from them.
+const PROFILES: [&str; 2] = ["DEV", "RELEASE"];
+
+/// Profile settings a build config overrides, with their key in cargo's
+/// environment config.
+const PROFILE_KEYS: [&str; 5] = ["OPT_LEVEL", "LTO", "CODEGEN_UNITS", "PANIC", "DEBUG"];
+
+const ENCODED_RUSTFLAGS: &str = "CARGO_ENCODED_RUSTFLAGS";
+
+/// Codegen options to build a benchmark with, named in the `build_configs`
+/// of a `suite-config.json` or `perf-config.json`, like
+/// `{"build_configs": {"fat-lto": {"lto": "fat", "codegen_units": 1}}}`.
+///
+/// Unset options keep the value of the profile the benchmark is built with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildConfig {
+    /// `0` to `3`, `s` or `z`.
+    #[serde(default, deserialize_with = "string_or_scalar")]
+    pub opt_level: Option<String>,
+    /// `off`, `thin`, `fat`, `true` or `false`.
+    #[serde(default, deserialize_with = "string_or_scalar")]
+    pub lto: Option<String>,
+    #[serde(default)]
+    pub codegen_units: Option<u32>,
+    /// `unwind` or `abort`.
+    #[serde(default)]
+    pub panic: Option<String>,
+    #[serde(default)]
+    pub target_cpu: Option<String>,
+    /// `0` to `2`, `line-tables-only`, `limited` or `full`.
+    #[serde(default, deserialize_with = "string_or_scalar")]
+    pub debuginfo: Option<String>,
+    #[serde(default)]
+    pub linker: Option<String>,
+    /// Unstable rustc flags without their `-Z`, like `share-generics=yes`.
+    #[serde(default)]
+    pub z_flags: Vec<String>,
+}
+
+/// Accept `3` and `true` as well as `"3"` and `"true"`.
+fn string_or_scalar<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    match Option::<Value>::deserialize(d)? {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(Value::Number(n)) => Ok(Some(n.to_string())),
+        Some(Value::Bool(b)) => Ok(Some(b.to_string())),
+        Some(v) => Err(serde::de::Error::custom(format!(
+            "invalid value {}, expected a string, number or bool",
+            v
+        ))),
+    }
+}
+
+impl BuildConfig {
+    /// Cargo's environment config overriding the profiles, e.g.
+    /// `CARGO_PROFILE_RELEASE_LTO=fat`.
+    pub fn profile_env(&self) -> Vec<(String, String)> {
+        let values = [
+            self.opt_level.clone(),
+            self.lto.clone(),
+            self.codegen_units.map(|n| n.to_string()),
+            self.panic.clone(),
+            self.debuginfo.clone(),
+        ];
+        PROFILES
+            .iter()
+            .flat_map(|profile| {
+                PROFILE_KEYS
+                    .iter()
+                    .zip(values.iter())
+                    .filter_map(move |(key, value)| {
+                        value
+                            .as_ref()
+                            .map(|v| (format!("CARGO_PROFILE_{}_{}", profile, key), v.clone()))
+                    })
+            })
+            .collect()
+    }
+
+    /// Flags passed to every rustc invocation of the build.
+    pub fn rustflags(&self) -> Vec<String> {
+        let mut flags = vec![];
+        if let Some(cpu) = &self.target_cpu {
+            flags.push(format!("-Ctarget-cpu={}", cpu));
+        }
+        if let Some(linker) = &self.linker {
+            flags.push(format!("-Clinker={}", linker));
+        }
+        flags.extend(self.z_flags.iter().map(|f| format!("-Z{}", f)));
+        flags
+    }
+
+    /// Environment to set on the cargo commands building with this config:
+    /// the profile overrides, and the rustflags collector was started with
+    /// followed by the ones of the config.
+    pub fn cargo_env(&self) -> Vec<(String, String)> {
+        let mut env = self.profile_env();
+        let flags = self.rustflags();
+        if flags.is_empty() {
+            return env;
+        }
+        // Cargo ignores `RUSTFLAGS` once the encoded flags are set.
+        let mut all: Vec<String> = match env::var(ENCODED_RUSTFLAGS) {
+            Ok(encoded) => encoded
+                .split('\x1f')
+                .filter(|f| !f.is_empty())
+                .map(String::from)
+                .collect(),
+            Err(_) => env::var("RUSTFLAGS")
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(String::from)
+                .collect(),
+        };
+        all.extend(flags);
+        env.push((ENCODED_RUSTFLAGS.to_string(), all.join("\x1f")));
+        env
+    }
+}
+
+/// Build configs to sweep the benchmarks across, parsed from a
+/// comma-separated list of names. `None` stands for the build without a
+/// config, named `default`.
+#[derive(Debug)]
+pub struct BuildConfigs {
+    pub names: Vec<Option<String>>,
+}
+
+impl FromStr for BuildConfigs {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut names = vec![];
+        for name in s.split(',').map(str::trim) {
+            match name {
+                "" => return Err(format!("empty build config name in `{}`", s)),
+                DEFAULT_BUILD_CONFIG => names.push(None),
+                _ => names.push(Some(name.to_string())),
+            }
+        }
+
+        Ok(Self { names })
+    }
+}
+
+#[cfg(test)]
+mod test_build_config {
+    use std::str::FromStr;
+
+    use super::{BuildConfig, BuildConfigs};
+
+    /// Test for BuildConfig
+    ///
+    /// Step1. Parse a config with numbers for string options and check the
+    /// profile overrides and rustflags it builds with.
+    ///
+    /// Step2. Check unknown options are rejected.
+    ///
+    /// Step3. Parse a list of build config names.
+    #[test]
+    fn test_build_config() {
+        let config: BuildConfig = serde_json::from_str(
+            r#"{"opt_level": 3, "lto": "fat", "codegen_units": 1, "target_cpu": "native", "z_flags": ["share-generics=yes"]}"#,
+        )
+        .unwrap();
+        let env = config.profile_env();
+        assert_eq!(env.len(), 6);
+        assert!(env.contains(&(
+            String::from("CARGO_PROFILE_RELEASE_OPT_LEVEL"),
+            String::from("3")
+        )));
+        assert!(env.contains(&(String::from("CARGO_PROFILE_DEV_LTO"), String::from("fat"))));
+        assert!(env.contains(&(
+            String::from("CARGO_PROFILE_DEV_CODEGEN_UNITS"),
+            String::from("1")
+        )));
+        assert_eq!(
+            config.rustflags(),
+            vec!["-Ctarget-cpu=native", "-Zshare-generics=yes"]
+        );
+        assert!(BuildConfig::default().profile_env().is_empty());
+        assert!(BuildConfig::default().cargo_env().is_empty());
+        let encoded = config
+            .cargo_env()
+            .into_iter()
+            .find(|(k, _)| k == "CARGO_ENCODED_RUSTFLAGS")
+            .unwrap()
+            .1;
+        assert!(encoded.ends_with("-Ctarget-cpu=native\x1f-Zshare-generics=yes"));
+
+        assert!(serde_json::from_str::<BuildConfig>(r#"{"opt-level": 3}"#).is_err());
+        assert!(serde_json::from_str::<BuildConfig>(r#"{"lto": ["fat"]}"#).is_err());
+
+        let configs = BuildConfigs::from_str("default,fat-lto").unwrap();
+        assert_eq!(configs.names, vec![None, Some(String::from("fat-lto"))]);
+        assert!(BuildConfigs::from_str("fat-lto,").is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use regex::Regex;
+
+/// Selects benchmarks by name and by the `tags` of their `perf-config.json`.
+///
+/// Name patterns may use `*` and `?` wildcards; a pattern without wildcards
+/// matches every name it is a prefix of.
+#[derive(Debug, Default)]
+pub struct BenchmarkFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+    tags: Vec<String>,
+}
+
+impl BenchmarkFilter {
+    /// Build a filter from comma-separated lists.
+    pub fn new(
+        include: Option<&str>,
+        exclude: Option<&str>,
+        tags: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        Ok(BenchmarkFilter {
+            include: split(include).map(to_regex).collect::<Result<_, _>>()?,
+            exclude: split(exclude).map(to_regex).collect::<Result<_, _>>()?,
+            tags: split(tags).map(String::from).collect(),
+        })
+    }
+
+    /// Select the benchmark named `name` only.
+    pub fn only(name: &str) -> Self {
+        BenchmarkFilter {
+            include: vec![Regex::new(&format!("^{}$", regex::escape(name))).unwrap()],
+            ..Default::default()
+        }
+    }
+
+    /// Whether the benchmark named `name` is selected by the include and
+    /// exclude lists. Checked before its config is loaded.
+    pub fn matches_name(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|r| r.is_match(name)))
+            && !self.exclude.iter().any(|r| r.is_match(name))
+    }
+
+    /// Whether a benchmark with `tags` is selected. Without requested tags
+    /// every benchmark is selected, otherwise it needs one of them.
+    pub fn matches_tags(&self, tags: &[String]) -> bool {
+        self.tags.is_empty() || self.tags.iter().any(|t| tags.contains(t))
+    }
+}
+
+fn split(list: Option<&str>) -> impl Iterator<Item = &str> {
+    list.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+fn to_regex(pattern: &str) -> anyhow::Result<Regex> {
+    let mut re = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    if !pattern.contains(['*', '?']) {
+        re.push_str(".*");
+    }
+    re.push('$');
+    Ok(Regex::new(&re)?)
+}
+
+#[cfg(test)]
+mod test_filter {
+    use super::BenchmarkFilter;
+
+    #[test]
+    fn test_match_name() {
+        let all = BenchmarkFilter::default();
+        assert!(all.matches_name("helloworld"));
+
+        let filter =
+            BenchmarkFilter::new(Some("hello, serde-*-json"), Some("helloworld-2"), None).unwrap();
+        assert!(filter.matches_name("helloworld"));
+        assert!(filter.matches_name("serde-1.0-json"));
+        assert!(!filter.matches_name("helloworld-2"));
+        assert!(!filter.matches_name("serde-1.0-json-2"));
+        assert!(!filter.matches_name("plus"));
+
+        let filter = BenchmarkFilter::new(None, Some("p?us"), None).unwrap();
+        assert!(filter.matches_name("helloworld"));
+        assert!(!filter.matches_name("plus"));
+
+        let filter = BenchmarkFilter::only("hello.world");
+        assert!(filter.matches_name("hello.world"));
+        assert!(!filter.matches_name("hello.world-2"));
+        assert!(!filter.matches_name("hello-world"));
+    }
+
+    #[test]
+    fn test_match_tags() {
+        let tags = vec!["small".to_string(), "ci".to_string()];
+        assert!(BenchmarkFilter::default().matches_tags(&[]));
+        assert!(BenchmarkFilter::new(None, None, Some("ci,large"))
+            .unwrap()
+            .matches_tags(&tags));
+        assert!(!BenchmarkFilter::new(None, None, Some("large"))
+            .unwrap()
+            .matches_tags(&tags));
+        assert!(!BenchmarkFilter::new(None, None, Some("large"))
+            .unwrap()
+            .matches_tags(&[]));
+    }
+}
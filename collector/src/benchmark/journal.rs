@@ -0,0 +1,275 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::{
+    execute::Stats,
+    statistics::{
+        compile_time_stat::{CompileTimeBenchResult, CompileTimeResult},
+        stability::StopRule,
+    },
+};
+
+use super::{profile::Profile, scenario::Scenario};
+
+pub const JOURNAL_FILE: &str = "compile_time_journal.jsonl";
+
+/// Results of a `bench_local` run, appended to `out_dir` as soon as each
+/// build is measured so that an interrupted run can be resumed.
+///
+/// Every line is a `CompileTimeBenchResult` holding a single result.
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    entries: Vec<CompileTimeBenchResult>,
+}
+
+impl Journal {
+    /// Open the journal in `out_dir`. With `resume` the results of the
+    /// previous run are kept, otherwise it starts out empty.
+    pub fn open(out_dir: &Path, resume: bool) -> anyhow::Result<Self> {
+        let path = out_dir.join(JOURNAL_FILE);
+        let mut entries = vec![];
+        if resume && path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read journal {:?}", path))?;
+            for (i, line) in content.lines().enumerate() {
+                match serde_json::from_str(line) {
+                    Ok(entry) => entries.push(entry),
+                    // A line torn by the interruption is measured again.
+                    Err(e) => eprintln!("Ignore line {} of journal {:?}: {}", i + 1, path, e),
+                }
+            }
+            println!("Resuming with {} results from {:?}", entries.len(), path);
+        }
+
+        // Rewrite the journal without torn lines before appending to it.
+        let mut file =
+            File::create(&path).with_context(|| format!("failed to create {:?}", path))?;
+        for entry in entries.iter() {
+            writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        }
+        let file = OpenOptions::new().append(true).open(&path)?;
+
+        Ok(Journal {
+            path,
+            file,
+            entries,
+        })
+    }
+
+    fn results_of<'a>(&'a self, benchmark: &'a str) -> impl Iterator<Item = &'a CompileTimeResult> {
+        self.entries
+            .iter()
+            .filter(move |e| e.benchmark == benchmark)
+            .flat_map(|e| e.result_vec.iter())
+    }
+
+    /// Whether the measured iteration `iteration` of a benchmark
+    /// configuration is in the journal. Warm-up runs do not count.
+    pub fn is_done(
+        &self,
+        benchmark: &str,
+        profile: Profile,
+        build_config: Option<&str>,
+        scenario: Scenario,
+        patch: Option<&str>,
+        iteration: usize,
+    ) -> bool {
+        self.results_of(benchmark).any(|r| {
+            !r.warmup
+                && r.profile == profile
+                && r.build_config.as_deref() == build_config
+                && r.scenario == scenario
+                && r.patch.as_deref() == patch
+                && r.iteration == iteration
+        })
+    }
+
+    /// Whether every scenario of `benchmark` measured with `profile` and
+    /// `build_config` so far is stable by `stop_rule`.
+    pub fn is_stable(
+        &self,
+        benchmark: &str,
+        profile: Profile,
+        build_config: Option<&str>,
+        stop_rule: &StopRule,
+    ) -> bool {
+        let mut groups: HashMap<(Scenario, Option<&str>), Vec<&Stats>> = HashMap::new();
+        self.results_of(benchmark)
+            .filter(|r| {
+                !r.warmup && r.profile == profile && r.build_config.as_deref() == build_config
+            })
+            .for_each(|r| {
+                groups
+                    .entry((r.scenario, r.patch.as_deref()))
+                    .or_default()
+                    .push(&r.stats)
+            });
+        !groups.is_empty()
+            && groups
+                .values()
+                .all(|stats| stop_rule.is_stable(stats.iter().copied()))
+    }
+
+    /// Add `result` to `results` and the journal, unless it was recorded
+    /// before the run was resumed.
+    pub fn record(
+        &mut self,
+        iterations: usize,
+        results: &mut Vec<CompileTimeResult>,
+        result: CompileTimeResult,
+    ) -> anyhow::Result<()> {
+        // Warm-up runs are repeated on resume, but only recorded once.
+        if self.results_of(&result.benchmark).any(|r| {
+            r.warmup == result.warmup
+                && r.profile == result.profile
+                && r.build_config == result.build_config
+                && r.scenario == result.scenario
+                && r.patch == result.patch
+                && r.iteration == result.iteration
+        }) {
+            return Ok(());
+        }
+
+        let mut entry = CompileTimeBenchResult::new(result.benchmark.clone(), iterations);
+        entry.add_result(result.clone());
+        writeln!(self.file, "{}", serde_json::to_string(&entry)?)
+            .and_then(|_| self.file.sync_data())
+            .with_context(|| format!("failed to write journal {:?}", self.path))?;
+        self.entries.push(entry);
+        results.push(result);
+        Ok(())
+    }
+
+    /// All results of the journal, merged per benchmark.
+    pub fn results(&self) -> Vec<CompileTimeBenchResult> {
+        let mut results: Vec<CompileTimeBenchResult> = vec![];
+        for entry in self.entries.iter() {
+            match results.iter_mut().find(|r| r.benchmark == entry.benchmark) {
+                Some(r) => {
+                    r.iterations = r.iterations.max(entry.iterations);
+                    entry.result_vec.iter().for_each(|res| {
+                        r.add_result(res.clone());
+                    });
+                }
+                None => results.push(entry.clone()),
+            }
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod test_journal {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    use tempfile::TempDir;
+
+    use crate::{
+        benchmark::{profile::Profile, scenario::Scenario},
+        execute::Stats,
+        statistics::compile_time_stat::CompileTimeResult,
+    };
+
+    use super::{Journal, JOURNAL_FILE};
+
+    fn result(benchmark: &str, iteration: usize, scenario: Scenario) -> CompileTimeResult {
+        CompileTimeResult::new(
+            benchmark.to_string(),
+            iteration,
+            Profile::Debug,
+            scenario,
+            Stats::new(),
+        )
+    }
+
+    /// Test for Journal
+    ///
+    /// Step1. Record results of two benchmarks and leave a torn line, like a
+    /// run killed while writing.
+    ///
+    /// Step2. Resume and verify the recorded results are kept and not
+    /// recorded twice, apart from warm-up runs and other build configs.
+    #[test]
+    fn test_resume_journal() {
+        let out_dir = TempDir::new().unwrap();
+        let mut journal = Journal::open(out_dir.path(), false).unwrap();
+        let mut results = vec![];
+        journal
+            .record(2, &mut results, result("helloworld", 1, Scenario::Full))
+            .unwrap();
+        journal
+            .record(2, &mut results, result("plus", 1, Scenario::Full))
+            .unwrap();
+        journal
+            .record(2, &mut results, result("helloworld", 2, Scenario::Full))
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        drop(journal);
+        write!(
+            OpenOptions::new()
+                .append(true)
+                .open(out_dir.path().join(JOURNAL_FILE))
+                .unwrap(),
+            "{{\"benchmark\":\"plus\",\"iterat"
+        )
+        .unwrap();
+
+        let mut journal = Journal::open(out_dir.path(), true).unwrap();
+        assert!(journal.is_done("plus", Profile::Debug, None, Scenario::Full, None, 1));
+        assert!(!journal.is_done("plus", Profile::Debug, None, Scenario::Full, None, 2));
+        assert!(!journal.is_done("plus", Profile::Release, None, Scenario::Full, None, 1));
+
+        let mut results = vec![];
+        journal
+            .record(2, &mut results, result("plus", 1, Scenario::Full))
+            .unwrap();
+        journal
+            .record(2, &mut results, result("plus", 2, Scenario::Full))
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        // Warm-up runs are kept apart from the measured iterations.
+        journal
+            .record(
+                2,
+                &mut results,
+                result("plus", 3, Scenario::Full).with_warmup(true),
+            )
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(!journal.is_done("plus", Profile::Debug, None, Scenario::Full, None, 3));
+
+        // So are the results of a build config.
+        let lto = Some("lto");
+        assert!(!journal.is_done("plus", Profile::Debug, lto, Scenario::Full, None, 1));
+        journal
+            .record(
+                2,
+                &mut results,
+                result("plus", 1, Scenario::Full).with_build_config(lto),
+            )
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(journal.is_done("plus", Profile::Debug, lto, Scenario::Full, None, 1));
+
+        let merged = journal.results();
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].benchmark, "helloworld");
+        assert_eq!(merged[0].result_vec.len(), 2);
+        assert_eq!(merged[1].iterations, 2);
+        assert_eq!(merged[1].result_vec.len(), 4);
+        assert_eq!(merged[1].measured().count(), 3);
+
+        // A new run starts over.
+        let journal = Journal::open(out_dir.path(), false).unwrap();
+        assert!(journal.results().is_empty());
+    }
+}
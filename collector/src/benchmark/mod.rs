@@ -1,3 +1,9 @@
+pub(crate) mod artifact;
 pub(crate) mod benchmark;
+pub mod build_config;
+pub mod filter;
+pub(crate) mod journal;
+pub mod patch;
 pub mod profile;
 pub mod scenario;
+pub mod suit;
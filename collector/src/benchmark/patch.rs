@@ -0,0 +1,86 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context};
+
+use crate::utils::command::command_output;
+
+/// A diff shipped in the `patches/` directory of a benchmark. Patches are
+/// applied one after another on top of an incrementally built benchmark to
+/// measure the `IncrPatched` scenario.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Patch {
+    /// Name of the patch recorded in results, i.e. the file stem of the diff.
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Patch {
+    pub fn new(path: PathBuf) -> anyhow::Result<Self> {
+        if !path.is_file() {
+            bail!("patch file {:?} does not exist", path);
+        }
+        // Patches are applied from inside the temporary copy of the
+        // benchmark, so a relative path would no longer resolve.
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("failed to canonicalize patch file {:?}", path))?;
+        let name = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(name) => name.to_string(),
+            None => bail!("invalid patch file name {:?}", path),
+        };
+        Ok(Patch { name, path })
+    }
+
+    /// Apply the patch to the benchmark copy in `dir`.
+    pub fn apply(&self, dir: &Path) -> anyhow::Result<()> {
+        log::debug!("applying patch {} to {:?}", self.name, dir);
+
+        let mut cmd = Command::new("patch");
+        cmd.current_dir(dir).arg("-Np1").arg("-i").arg(&self.path);
+        command_output(&mut cmd)
+            .with_context(|| format!("failed to apply patch {:?} in {:?}", self.path, dir))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_patch {
+    use std::{fs, path::PathBuf};
+
+    use tempfile::TempDir;
+
+    use super::Patch;
+
+    /// Test for Patch::apply
+    ///
+    /// Step1. Copy `test/benchmark/patch/src` into a temp dir.
+    ///
+    /// Step2. Apply `0-println.patch` and verify the source file changed.
+    #[test]
+    fn test_apply_patch() {
+        let root = PathBuf::from("test/benchmark/patch");
+        let patch = Patch::new(root.join("patches").join("0-println.patch")).unwrap();
+        assert_eq!(patch.name, "0-println");
+
+        let tmp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(tmp_dir.path().join("src")).unwrap();
+        fs::copy(
+            root.join("src").join("main.rs"),
+            tmp_dir.path().join("src").join("main.rs"),
+        )
+        .unwrap();
+
+        patch.apply(tmp_dir.path()).unwrap();
+
+        let src = fs::read_to_string(tmp_dir.path().join("src").join("main.rs")).unwrap();
+        assert!(src.contains("println!(\"patched\");"));
+    }
+
+    #[test]
+    fn test_missing_patch() {
+        assert!(Patch::new(PathBuf::from("test/benchmark/patch/patches/missing.patch")).is_err());
+    }
+}
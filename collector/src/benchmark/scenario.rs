@@ -1,3 +1,5 @@
+use std::{fmt::Display, str::FromStr};
+
 #[derive(
     Clone, Copy, Debug, Eq, Hash, PartialEq, clap::ArgEnum, serde::Deserialize, serde::Serialize,
 )]
@@ -17,3 +19,44 @@ impl Scenario {
         )
     }
 }
+
+impl FromStr for Scenario {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Self::Full),
+            "incr-full" => Ok(Self::IncrFull),
+            "incr-unchanged" => Ok(Self::IncrUnchanged),
+            "incr-patched" => Ok(Self::IncrPatched),
+            _ => Err(format!("Unknown Scenario {}", s)),
+        }
+    }
+}
+
+impl Display for Scenario {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scenario::Full => f.write_str("full"),
+            Scenario::IncrFull => f.write_str("incr-full"),
+            Scenario::IncrUnchanged => f.write_str("incr-unchanged"),
+            Scenario::IncrPatched => f.write_str("incr-patched"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Scenarios {
+    pub scenarios: Vec<Scenario>,
+}
+
+impl FromStr for Scenarios {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut scenarios = vec![];
+        for scenario in s.split(',') {
+            scenarios.push(Scenario::from_str(scenario)?);
+        }
+
+        Ok(Self { scenarios })
+    }
+}
@@ -0,0 +1,308 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs::{read_dir, File},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use serde_json::Value;
+
+use super::{
+    benchmark::{Benchamrk, BenchmarkConfig},
+    filter::BenchmarkFilter,
+};
+
+/// Config of a single benchmark.
+pub const CONFIG_FILE: &str = "perf-config.json";
+
+/// Defaults for all benchmarks of a suite or of a group below it. Keys set
+/// in a nested `suite-config.json` or in `perf-config.json` override them.
+pub const SUITE_CONFIG_FILE: &str = "suite-config.json";
+
+/// A config value that does not deserialize, e.g.
+/// `perf-config.json: packages[1]: invalid type: integer `1`, expected a string`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    pub file: PathBuf,
+    /// Path of the offending value, empty if the whole file is invalid.
+    pub field: String,
+    pub message: String,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.field.is_empty() {
+            write!(f, "{}: {}", self.file.display(), self.message)
+        } else {
+            write!(
+                f,
+                "{}: {}: {}",
+                self.file.display(),
+                self.field,
+                self.message
+            )
+        }
+    }
+}
+
+/// A benchmark skipped because of an invalid config.
+#[derive(Debug)]
+pub struct RejectedBenchmark {
+    pub name: String,
+    pub path: PathBuf,
+    pub errors: Vec<ConfigError>,
+}
+
+pub struct BenchmarkSuit {
+    pub benchmarks: Vec<Benchamrk>,
+    pub rejected: Vec<RejectedBenchmark>,
+}
+
+/// Config keys with the file each was read from.
+pub(super) type RawConfig = BTreeMap<String, (Value, PathBuf)>;
+
+impl BenchmarkSuit {
+    /// Discover the benchmarks under `dir`.
+    ///
+    /// A directory with a `perf-config.json` or a `Cargo.toml` is a
+    /// benchmark, any other directory is a group of benchmarks and searched
+    /// recursively. Benchmarks with an invalid config are rejected instead of
+    /// failing the whole suite.
+    pub fn load(dir: &Path, filter: &BenchmarkFilter) -> anyhow::Result<Self> {
+        let mut suit = BenchmarkSuit {
+            benchmarks: vec![],
+            rejected: vec![],
+        };
+        suit.load_group(dir, &RawConfig::new(), filter)?;
+
+        if suit.benchmarks.is_empty() {
+            eprintln!("Error: no benchmark found in '{}'", dir.display());
+        }
+        Ok(suit)
+    }
+
+    fn load_group(
+        &mut self,
+        dir: &Path,
+        defaults: &RawConfig,
+        filter: &BenchmarkFilter,
+    ) -> anyhow::Result<()> {
+        let mut defaults = defaults.clone();
+        let suite_config = dir.join(SUITE_CONFIG_FILE);
+        if suite_config.exists() {
+            match read_raw_config(&suite_config) {
+                Ok(raw) => defaults.extend(raw),
+                Err(e) => {
+                    self.rejected.push(RejectedBenchmark {
+                        name: dir.display().to_string(),
+                        path: dir.to_path_buf(),
+                        errors: vec![e],
+                    });
+                    return Ok(());
+                }
+            }
+        }
+
+        let mut entries = read_dir(dir)
+            .with_context(|| format!("failed to list benchmark dir '{}'", dir.display()))?
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|e| e.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            let name = match entry.file_name().into_string() {
+                Ok(s) => s,
+                Err(e) => bail!("non-utf8 benchmark name: {:?}", e),
+            };
+            if !entry.file_type()?.is_dir() {
+                if name != SUITE_CONFIG_FILE {
+                    println!("benchmark '{}' ignored", name);
+                }
+                continue;
+            }
+            if name.starts_with('.') {
+                continue;
+            }
+            if !path.join(CONFIG_FILE).exists() && !path.join("Cargo.toml").exists() {
+                self.load_group(&path, &defaults, filter)?;
+                continue;
+            }
+            if !filter.matches_name(&name) {
+                continue;
+            }
+
+            let mut errors = vec![];
+            if let Some(other) = self.benchmarks.iter().find(|b| b.name == name) {
+                errors.push(ConfigError {
+                    file: path.clone(),
+                    field: String::new(),
+                    message: format!("duplicate benchmark name, also at {:?}", other.path),
+                });
+            }
+            match load_config(&path, &defaults) {
+                Ok(config) if errors.is_empty() => {
+                    if filter.matches_tags(&config.tags) {
+                        self.benchmarks.push(Benchamrk { name, path, config });
+                    }
+                }
+                Ok(_) => self.rejected.push(RejectedBenchmark { name, path, errors }),
+                Err(e) => {
+                    errors.extend(e);
+                    self.rejected.push(RejectedBenchmark { name, path, errors });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn display_benchmarks(&self) -> String {
+        let mut names = String::from("+BenchmarkSuit");
+        for b in self.benchmarks.iter() {
+            names += &format!("\n| {} {}", b.name, b.path.display());
+        }
+        for r in self.rejected.iter() {
+            names += &format!("\n| {} rejected:", r.name);
+            for e in r.errors.iter() {
+                names += &format!("\n|   {}", e);
+            }
+        }
+        names
+    }
+}
+
+/// Load the config of the benchmark in `path`, with the keys of its
+/// `perf-config.json` overriding `defaults`. Every invalid key is reported.
+pub(super) fn load_config(
+    path: &Path,
+    defaults: &RawConfig,
+) -> Result<BenchmarkConfig, Vec<ConfigError>> {
+    let mut raw = defaults.clone();
+    let config_path = path.join(CONFIG_FILE);
+    if config_path.exists() {
+        raw.extend(read_raw_config(&config_path).map_err(|e| vec![e])?);
+    }
+
+    // Deserialize the keys one by one to find all the errors at once.
+    let mut errors = vec![];
+    for (key, (value, file)) in raw.iter() {
+        let single = Value::Object([(key.clone(), value.clone())].into_iter().collect());
+        if let Err(e) = serde_path_to_error::deserialize::<_, BenchmarkConfig>(single) {
+            errors.push(ConfigError {
+                file: file.clone(),
+                field: e.path().to_string(),
+                message: e.into_inner().to_string(),
+            });
+        }
+    }
+    if let Some((patches, file)) = raw.get("patches") {
+        let patch_dir = path.join("patches");
+        let patches: Vec<String> = serde_json::from_value(patches.clone()).unwrap_or_default();
+        for (i, patch) in patches.iter().enumerate() {
+            if !patch_dir.join(patch).is_file() {
+                errors.push(ConfigError {
+                    file: file.clone(),
+                    field: format!("patches[{}]", i),
+                    message: format!("no such patch {:?}", patch_dir.join(patch)),
+                });
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let config = serde_json::from_value(Value::Object(
+        raw.into_iter().map(|(k, (v, _))| (k, v)).collect(),
+    ))
+    .map_err(|e| {
+        vec![ConfigError {
+            file: config_path,
+            field: String::new(),
+            message: e.to_string(),
+        }]
+    })?;
+    Ok(config)
+}
+
+fn read_raw_config(file: &Path) -> Result<RawConfig, ConfigError> {
+    let error = |message: String| ConfigError {
+        file: file.to_path_buf(),
+        field: String::new(),
+        message,
+    };
+    let value: Value = File::open(file)
+        .map_err(|e| error(e.to_string()))
+        .and_then(|f| serde_json::from_reader(f).map_err(|e| error(e.to_string())))?;
+    match value {
+        Value::Object(map) => Ok(map
+            .into_iter()
+            .map(|(k, v)| (k, (v, file.to_path_buf())))
+            .collect()),
+        _ => Err(error(String::from("expected a json object"))),
+    }
+}
+
+#[cfg(test)]
+mod test_suit {
+    use std::path::Path;
+
+    use crate::benchmark::filter::BenchmarkFilter;
+
+    use super::BenchmarkSuit;
+
+    /// Test for BenchmarkSuit::load
+    ///
+    /// The suite under `test/benchmark/suit` has a default config with a
+    /// build config, a nested group overriding it and two broken benchmarks.
+    ///
+    /// Step1. Verify the valid benchmarks are found in the group, with the
+    /// defaults applied and overridden.
+    ///
+    /// Step2. Verify every error of the broken configs is reported with its
+    /// field.
+    #[test]
+    fn test_load_suit() {
+        let suit = BenchmarkSuit::load(
+            Path::new("test/benchmark/suit"),
+            &BenchmarkFilter::default(),
+        )
+        .unwrap();
+
+        let names: Vec<_> = suit.benchmarks.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["plus", "helloworld"]);
+        let helloworld = &suit.benchmarks[1];
+        assert_eq!(helloworld.config.runs, 5);
+        assert_eq!(helloworld.config.tags, vec!["small"]);
+        let fat_lto = helloworld.build_config(Some("fat-lto")).unwrap();
+        assert_eq!(fat_lto.lto.as_deref(), Some("fat"));
+        assert_eq!(fat_lto.codegen_units, Some(1));
+        assert!(helloworld.build_config(Some("thin-lto")).is_none());
+        let plus = &suit.benchmarks[0];
+        assert_eq!(plus.config.runs, 1);
+        assert_eq!(plus.config.tags, vec!["small", "group"]);
+
+        let rejected: Vec<_> = suit.rejected.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(rejected, vec!["broken", "not-json"]);
+        let fields: Vec<_> = suit.rejected[0]
+            .errors
+            .iter()
+            .map(|e| e.field.as_str())
+            .collect();
+        assert_eq!(fields, vec!["packages[1]", "unknown_key", "patches[0]"]);
+        assert!(suit.rejected[0].errors[2].message.contains("no-such.patch"));
+        assert_eq!(suit.rejected[1].errors.len(), 1);
+    }
+
+    #[test]
+    fn test_load_filtered_suit() {
+        let suit = BenchmarkSuit::load(
+            Path::new("test/benchmark/suit"),
+            &BenchmarkFilter::new(None, Some("broken,not-json"), Some("group")).unwrap(),
+        )
+        .unwrap();
+        let names: Vec<_> = suit.benchmarks.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["plus"]);
+        assert!(suit.rejected.is_empty());
+    }
+}
@@ -6,119 +6,64 @@ use std::{
 };
 
 use clap::Parser;
+use collector::statistics::metadata::ToolchainIdentity;
 use serde::Deserialize;
 
 fn main() {
     let cli = Cli::parse();
-    match cli.subcommand {
-        Commands::MutiCompileTime {
-            compilers_config,
-            benchmark_dir,
-            out,
-            collector,
-            iterations,
-            flamegraph,
-            cargo,
-        } => {
-            let compilers = match parse_compiler_json(&compilers_config) {
-                Ok(compilers) => compilers,
-                Err(err) => {
-                    eprintln!("Fail to parse {:?}\n{}", compilers_config, err);
-                    return;
-                }
-            };
-
-            compilers.iter().for_each(|compiler| {
-                compiler.paths.iter().for_each(|path| {
-                    let path_string = String::from(path.to_str().unwrap());
-                    let mut path_cycle = path_string.split('/').cycle();
-                    let mut commit_id = path_cycle.next().unwrap();
-                    let mut tmp = commit_id;
-                    while tmp != "bin" {
-                        commit_id = tmp;
-                        tmp = path_cycle.next().unwrap();
-                    }
-
-                    let out = out.join(&compiler.name).join(commit_id);
-                    match run_collector(
-                        &collector,
-                        &String::from("bench_local"),
-                        &benchmark_dir,
-                        &out,
-                        iterations,
-                        flamegraph,
-                        &cargo,
-                        &path,
-                    ) {
-                        Ok(output) => {
-                            println!(
-                                "Successfully benched rustc {:?} with benchmark_suit {:?}.",
-                                compiler, benchmark_dir
-                            );
-                            match write_output(&out, &output) {
-                                Ok(_) => (),
-                                Err(err) => eprintln!("Fail to write output to {:?}\n{}", out, err),
-                            };
-                        }
-                        Err(err) => eprintln!("Fail to run collector.\n{}", err),
-                    }
-                })
-            });
+    let (subcommand, options) = match cli.subcommand {
+        Commands::MutiCompileTime(options) => ("bench_local", options),
+        Commands::MutiRuntime(options) => ("bench_runtime_local", options),
+    };
+
+    let compilers = match parse_compiler_json(&options.compilers_config) {
+        Ok(compilers) => compilers,
+        Err(err) => {
+            eprintln!("Fail to parse {:?}\n{}", options.compilers_config, err);
+            return;
         }
-        Commands::MutiRuntime {
-            compilers_config,
-            benchmark_dir,
-            out,
-            collector,
-            iterations,
-            flamegraph,
-            cargo,
-        } => {
-            let compilers = match parse_compiler_json(&compilers_config) {
-                Ok(compilers) => compilers,
-                Err(err) => {
-                    eprintln!("Fail to parse {:?}\n{}", compilers_config, err);
-                    return;
-                }
-            };
-
-            compilers.iter().for_each(|compiler| {
-                compiler.paths.iter().for_each(|path| {
-                    let path_string = String::from(path.to_str().unwrap());
-                    let mut path_cycle = path_string.split('/').into_iter();
-                    let mut commit_id = path_cycle.next().unwrap();
-                    let mut tmp = commit_id;
-                    while tmp != "bin" {
-                        commit_id = tmp;
-                        tmp = path_cycle.next().unwrap();
+    };
+
+    // All rustcs are measured by one collector run, which interleaves their
+    // iterations and writes the results of each to `<out>/<name>/<commit>`.
+    let rustcs: Vec<(String, &PathBuf)> = compilers
+        .iter()
+        .flat_map(|compiler| {
+            compiler
+                .paths
+                .iter()
+                .filter_map(|path| match ToolchainIdentity::detect(path) {
+                    Ok(identity) if !identity.name().is_empty() => {
+                        Some((format!("{}/{}", compiler.name, identity.name()), path))
                     }
-
-                    let out = out.join(&compiler.name).join(commit_id);
-                    match run_collector(
-                        &collector,
-                        &String::from("bench_runtime_local"),
-                        &benchmark_dir,
-                        &out,
-                        iterations,
-                        flamegraph,
-                        &cargo,
-                        &path,
-                    ) {
-                        Ok(output) => {
-                            println!(
-                                "Successfully benched rustc {:?} with benchmark_suit {:?}.",
-                                compiler, benchmark_dir
-                            );
-                            match write_output(&out, &output) {
-                                Ok(_) => (),
-                                Err(err) => eprintln!("Fail to write output to {:?}\n{}", out, err),
-                            };
-                        }
-                        Err(err) => eprintln!("Fail to run collector.\n{}", err),
+                    Ok(_) => {
+                        eprintln!("Fail to identify {:?}, skipped.", path);
+                        None
+                    }
+                    Err(err) => {
+                        eprintln!("Fail to identify {:?}, skipped.\n{}", path, err);
+                        None
                     }
                 })
-            });
+        })
+        .collect();
+    if rustcs.is_empty() {
+        eprintln!("No rustc in {:?}", options.compilers_config);
+        return;
+    }
+
+    match run_collector(subcommand, &options, &rustcs) {
+        Ok(output) => {
+            println!(
+                "Successfully benched rustcs {:?} with benchmark_suit {:?}.",
+                compilers, options.benchmark_dir
+            );
+            match write_output(&options.out, &output) {
+                Ok(_) => (),
+                Err(err) => eprintln!("Fail to write output to {:?}\n{}", options.out, err),
+            };
         }
+        Err(err) => eprintln!("Fail to run collector.\n{}", err),
     }
 }
 
@@ -150,33 +95,50 @@ fn write_output(dir: &PathBuf, output: &Output) -> anyhow::Result<()> {
 }
 
 fn run_collector(
-    collector: &PathBuf,
-    subcommand: &String,
-    benchmark_dir: &PathBuf,
-    out: &PathBuf,
-    iterations: u32,
-    flamegraph: u32,
-    cargo: &PathBuf,
-    rustc: &PathBuf,
+    subcommand: &str,
+    options: &MutiOptions,
+    rustcs: &[(String, &PathBuf)],
 ) -> anyhow::Result<Output> {
-    let mut cmd = Command::new(collector);
+    match collector_command(subcommand, options, rustcs).output() {
+        Ok(output) => Ok(output),
+        Err(err) => anyhow::Result::Err(err.into()),
+    }
+}
+
+/// The collector run measuring `rustcs`. The results of each rustc go to
+/// `<out>/<id>`: collector nests them itself when it measures several
+/// toolchains, a single one is given its directory explicitly.
+fn collector_command(
+    subcommand: &str,
+    options: &MutiOptions,
+    rustcs: &[(String, &PathBuf)],
+) -> Command {
+    let (first_id, first) = &rustcs[0];
+    let out_dir = if rustcs.len() > 1 {
+        options.out.clone()
+    } else {
+        options.out.join(first_id)
+    };
+    let mut cmd = Command::new(&options.collector);
     cmd.arg(subcommand)
         .arg("--bench-dir")
-        .arg(benchmark_dir)
+        .arg(&options.benchmark_dir)
         .arg("--out-dir")
-        .arg(out)
+        .arg(out_dir)
         .arg("--iterations")
-        .arg(iterations.to_string())
+        .arg(options.iterations.to_string())
         .arg("--flamegraph")
-        .arg(flamegraph.to_string())
+        .arg(options.flamegraph.to_string())
         .arg("--cargo")
-        .arg(cargo)
-        .arg(rustc);
-
-    match cmd.output() {
-        Ok(output) => Ok(output),
-        Err(err) => anyhow::Result::Err(err.into()),
+        .arg(&options.cargo)
+        .arg("--id")
+        .arg(first_id)
+        .arg(first);
+    for (id, rustc) in &rustcs[1..] {
+        cmd.arg("--with")
+            .arg(format!("{}={}", id, rustc.to_string_lossy()));
     }
+    cmd
 }
 
 #[derive(Debug, clap::Parser)]
@@ -189,50 +151,32 @@ pub struct Cli {
 #[derive(Debug, clap::Subcommand)]
 #[clap(rename_all = "snake_case")]
 pub enum Commands {
-    MutiCompileTime {
-        #[clap(long = "compilers-config")]
-        compilers_config: PathBuf,
-
-        #[clap(long = "benchmark-dir")]
-        benchmark_dir: PathBuf,
-
-        #[clap(long = "out")]
-        out: PathBuf,
-
-        #[clap(long = "collector")]
-        collector: PathBuf,
-
-        #[clap(long = "iterations")]
-        iterations: u32,
-
-        #[clap(long = "flamegraph")]
-        flamegraph: u32,
+    MutiCompileTime(MutiOptions),
+    MutiRuntime(MutiOptions),
+}
 
-        #[clap(long = "cargo")]
-        cargo: PathBuf,
-    },
-    MutiRuntime {
-        #[clap(long = "compilers-config")]
-        compilers_config: PathBuf,
+#[derive(Debug, clap::Args)]
+pub struct MutiOptions {
+    #[clap(long = "compilers-config")]
+    compilers_config: PathBuf,
 
-        #[clap(long = "benchmark-dir")]
-        benchmark_dir: PathBuf,
+    #[clap(long = "benchmark-dir")]
+    benchmark_dir: PathBuf,
 
-        #[clap(long = "out")]
-        out: PathBuf,
+    #[clap(long = "out")]
+    out: PathBuf,
 
-        #[clap(long = "collector")]
-        collector: PathBuf,
+    #[clap(long = "collector")]
+    collector: PathBuf,
 
-        #[clap(long = "iterations")]
-        iterations: u32,
+    #[clap(long = "iterations")]
+    iterations: u32,
 
-        #[clap(long = "flamegraph")]
-        flamegraph: u32,
+    #[clap(long = "flamegraph")]
+    flamegraph: u32,
 
-        #[clap(long = "cargo")]
-        cargo: PathBuf,
-    },
+    #[clap(long = "cargo")]
+    cargo: PathBuf,
 }
 
 #[derive(Deserialize, Debug)]
@@ -240,3 +184,52 @@ struct RustCompiler {
     name: String,
     paths: Vec<PathBuf>,
 }
+
+#[cfg(test)]
+mod test_muti_rustc_perf {
+    use std::path::PathBuf;
+
+    use super::{collector_command, MutiOptions};
+
+    /// Test for collector_command
+    ///
+    /// Step1. Check a single rustc writes its results to `<out>/<id>`.
+    ///
+    /// Step2. Check several rustcs are measured by one run into `<out>`,
+    /// which nests the results of each.
+    #[test]
+    fn test_collector_command() {
+        let options = MutiOptions {
+            compilers_config: PathBuf::from("compilers.json"),
+            benchmark_dir: PathBuf::from("benchmarks"),
+            out: PathBuf::from("results"),
+            collector: PathBuf::from("collector"),
+            iterations: 3,
+            flamegraph: 0,
+            cargo: PathBuf::from("cargo"),
+        };
+        let args = |rustcs: &[(String, &PathBuf)]| -> Vec<String> {
+            collector_command("bench_local", &options, rustcs)
+                .get_args()
+                .map(|a| a.to_string_lossy().to_string())
+                .collect()
+        };
+        let out_dir = |args: &[String]| {
+            let i = args.iter().position(|a| a == "--out-dir").unwrap();
+            args[i + 1].clone()
+        };
+
+        let stable = PathBuf::from("stable/bin/rustc");
+        let single = args(&[(String::from("stable/abc"), &stable)]);
+        assert_eq!(out_dir(&single), "results/stable/abc");
+        assert!(!single.contains(&String::from("--with")));
+
+        let nightly = PathBuf::from("nightly/bin/rustc");
+        let several = args(&[
+            (String::from("stable/abc"), &stable),
+            (String::from("nightly/def"), &nightly),
+        ]);
+        assert_eq!(out_dir(&several), "results");
+        assert!(several.contains(&String::from("nightly/def=nightly/bin/rustc")));
+    }
+}
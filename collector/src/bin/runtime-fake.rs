@@ -4,6 +4,13 @@ use std::{
     time::{Duration, Instant},
 };
 
+#[cfg(unix)]
+use collector::execute::rusage;
+use collector::execute::{
+    events::{DEFAULT_PERF_EVENTS, PERF_EVENTS_ENV},
+    host::pin_from_env,
+};
+
 fn main() {
     let mut arg_os = env::args_os();
     let _name = arg_os.next().unwrap().into_string().unwrap();
@@ -13,6 +20,7 @@ fn main() {
     let elf = env::var_os("RUNTIME_ELF").unwrap();
 
     raise_process_priority();
+    pin_from_env();
 
     if let Some(pos) = args.iter().position(|arg| arg == "--wrap-rustc-with") {
         let mut cmd;
@@ -35,8 +43,11 @@ fn main() {
                     // the output, but we want standard output on all systems.
                     .env("LC_NUMERIC", "C")
                     .arg("-x;")
+                    // Multiplexed counts are scaled by the collector, which
+                    // needs the raw counts and their coverage.
+                    .arg("--no-scale")
                     .arg("-e")
-                    .arg("instructions:u,cycles:u,task-clock,cpu-clock,faults,context-switches,branch-misses,cache-misses")
+                    .arg(perf_events())
                     .arg("--log-fd")
                     .arg("1")
                     .arg("setarch")
@@ -78,9 +89,24 @@ fn main() {
                     .arg(&elf)
                     .args(&args);
             }
+            #[cfg(unix)]
+            "Rusage" => {
+                cmd = Command::new(&elf);
+                cmd.args(&args).stderr(Stdio::null());
+            }
             _ => panic!(),
         }
 
+        #[cfg(unix)]
+        if wrapper == "Rusage" {
+            let (_status, usage) = rusage::run(&mut cmd).expect("failed to spawn");
+            usage
+                .to_perf_lines()
+                .iter()
+                .for_each(|line| println!("{}", line));
+            return;
+        }
+
         let start = Instant::now();
 
         let _status = cmd.status().expect("failed to spawn");
@@ -130,3 +156,9 @@ fn print_time(dur: Duration) {
         dur.subsec_nanos()
     );
 }
+
+/// The events requested by the collector, falling back to the default set
+/// when the wrapper is run by hand.
+fn perf_events() -> String {
+    env::var(PERF_EVENTS_ENV).unwrap_or_else(|_| DEFAULT_PERF_EVENTS.to_string())
+}
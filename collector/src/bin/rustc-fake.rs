@@ -1,4 +1,9 @@
 use anyhow::Context;
+use collector::execute::events::{DEFAULT_PERF_EVENTS, PERF_EVENTS_ENV};
+use collector::execute::host::pin_from_env;
+#[cfg(unix)]
+use collector::execute::rusage;
+use collector::execute::self_profile::SELF_PROFILE_OUTPUT;
 use std::env;
 use std::ffi::OsString;
 use std::fs;
@@ -70,6 +75,7 @@ fn main() {
         let wrapper = wrapper.to_str().unwrap();
 
         raise_process_priority();
+        pin_from_env();
 
         // These strings come from `PerfTool::name()`.
         match wrapper {
@@ -82,8 +88,11 @@ fn main() {
                     // the output, but we want standard output on all systems.
                     .env("LC_NUMERIC", "C")
                     .arg("-x;")
+                    // Multiplexed counts are scaled by the collector, which
+                    // needs the raw counts and their coverage.
+                    .arg("--no-scale")
                     .arg("-e")
-                    .arg("instructions:u,cycles:u,task-clock,cpu-clock,faults,context-switches,branch-misses,cache-misses")
+                    .arg(perf_events())
                     .arg("--log-fd")
                     .arg("1")
                     .arg("setarch")
@@ -112,6 +121,23 @@ fn main() {
                 }
             }
 
+            #[cfg(unix)]
+            "Rusage" => {
+                let mut cmd = Command::new(&tool);
+                cmd.args(&args);
+                determinism_env(&mut cmd);
+                let (status, usage) = rusage::run(&mut cmd).expect("failed to spawn");
+                assert!(
+                    status.success(),
+                    "command did not complete successfully: {:?}",
+                    cmd
+                );
+                usage
+                    .to_perf_lines()
+                    .iter()
+                    .for_each(|line| println!("{}", line));
+            }
+
             "XperfStat" | "XperfStatSelfProfile" => {
                 // For Windows, we use a combination of xperf and tracelog to capture ETW events
                 // including hardware performance counters. To do this, we start an ETW trace using
@@ -453,19 +479,28 @@ fn process_self_profile_output(prof_out_dir: PathBuf, args: &[OsString]) {
             Err(e1) => match run_summarize("summarize-9.0", &prof_out_dir, filename) {
                 Ok(s) => s,
                 Err(e2) => {
-                    panic!("failed to run summarize and summarize-9.0. Errors:\nsummarize: {:?}\nsummarize-9.0: {:?}", e1, e2);
+                    // The raw profile is still collected, so do not fail the
+                    // measurement.
+                    eprintln!("failed to run summarize and summarize-9.0. Errors:\nsummarize: {:?}\nsummarize-9.0: {:?}", e1, e2);
+                    return;
                 }
             },
         };
-        println!("!self-profile-output:{}", json);
+        print_self_profile_output(&json);
     } else {
         let prefix = prefix.expect(&format!("found prefix {:?}", prof_out_dir));
-        let json = run_summarize("summarize", &prof_out_dir, &prefix)
+        let json = match run_summarize("summarize", &prof_out_dir, &prefix)
             .or_else(|_| run_summarize("summarize-0.7", &prof_out_dir, &prefix))
-            .expect("able to run summarize or summarize-0.7");
+        {
+            Ok(json) => json,
+            Err(e) => {
+                eprintln!("failed to run summarize or summarize-0.7: {:?}", e);
+                return;
+            }
+        };
         println!("!self-profile-dir:{}", prof_out_dir.to_str().unwrap());
         println!("!self-profile-prefix:{}", prefix);
-        println!("!self-profile-output:{}", json);
+        print_self_profile_output(&json);
     }
 }
 
@@ -514,6 +549,14 @@ fn print_time(dur: Duration) {
     );
 }
 
+/// The collector reads the summary from a single line of output.
+fn print_self_profile_output(json: &str) {
+    match serde_json::from_str::<serde_json::Value>(json) {
+        Ok(value) => println!("{}{}", SELF_PROFILE_OUTPUT, value),
+        Err(e) => eprintln!("invalid summarize output: {:?}", e),
+    }
+}
+
 fn run_summarize(name: &str, prof_out_dir: &Path, prefix: &str) -> anyhow::Result<String> {
     let mut cmd = Command::new(name);
     cmd.current_dir(&prof_out_dir);
@@ -556,3 +599,9 @@ pub fn raise_process_priority() {
 
 #[cfg(windows)]
 pub fn raise_process_priority() {}
+
+/// The events requested by the collector, falling back to the default set
+/// when the wrapper is run by hand.
+fn perf_events() -> String {
+    env::var(PERF_EVENTS_ENV).unwrap_or_else(|_| DEFAULT_PERF_EVENTS.to_string())
+}
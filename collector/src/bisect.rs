@@ -0,0 +1,292 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::{
+    benchmark::{profile::Profile, scenario::Scenario},
+    statistics::{
+        paired::PairedComparison,
+        significance::{Comparison, Verdict},
+    },
+    toolchain::LocalToolchain,
+};
+
+/// The metric of one benchmark configuration a bisection watches.
+#[derive(Debug, Clone, Serialize)]
+pub struct BisectTarget {
+    pub benchmark: String,
+    /// Missing for runtime benchmarks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    /// Missing for the build without a build config.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_config: Option<String>,
+    /// Missing for runtime benchmarks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<Scenario>,
+    /// The patch to watch in the `IncrPatched` scenario, any one if missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
+    pub metric: String,
+    /// Change of the mean against the baseline in percent, negative for an
+    /// improvement.
+    pub threshold: f64,
+}
+
+impl BisectTarget {
+    /// The comparison of the watched metric among `comparisons`. Fails if
+    /// more than one matches, as with several patches and none chosen.
+    pub fn select<'c>(
+        &self,
+        comparisons: &'c [PairedComparison],
+    ) -> anyhow::Result<Option<&'c PairedComparison>> {
+        let mut matching = comparisons.iter().filter(|c| {
+            c.benchmark == self.benchmark
+                && c.profile == self.profile
+                && c.build_config == self.build_config
+                && c.scenario == self.scenario
+                && (self.patch.is_none() || c.patch == self.patch)
+                && c.metric == self.metric
+        });
+        let selected = matching.next();
+        if let Some(other) = matching.next() {
+            anyhow::bail!(
+                "both patch {:?} and {:?} of `{}` match, choose one with `--patch`",
+                selected
+                    .and_then(|c| c.patch.as_deref())
+                    .unwrap_or_default(),
+                other.patch.as_deref().unwrap_or_default(),
+                self.benchmark
+            );
+        }
+        Ok(selected)
+    }
+
+    /// Whether `comparison` is a significant change at least as large as
+    /// the threshold, in its direction.
+    pub fn crosses(&self, comparison: &Comparison) -> bool {
+        if self.threshold >= 0.0 {
+            comparison.verdict == Verdict::SignificantRegression
+                && comparison.change >= self.threshold
+        } else {
+            comparison.verdict == Verdict::SignificantImprovement
+                && comparison.change <= self.threshold
+        }
+    }
+}
+
+/// One toolchain measured against the baseline.
+#[derive(Debug, Serialize)]
+pub struct BisectStep {
+    pub toolchain: String,
+    pub rustc: PathBuf,
+    /// `None` if the metric was not measured or had too few samples.
+    pub comparison: Option<Comparison>,
+    pub crosses: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BisectReport {
+    pub target: BisectTarget,
+    pub baseline: String,
+    /// The steps in the order they were measured.
+    pub steps: Vec<BisectStep>,
+    /// The first toolchain past the threshold, `None` if the last one is
+    /// not.
+    pub first_crossing: Option<String>,
+    /// The toolchain right before `first_crossing`.
+    pub last_before: Option<String>,
+}
+
+impl BisectReport {
+    /// Bisect `toolchains`, oldest first, for the first one whose change
+    /// against the first one crosses the threshold of `target`. `measure`
+    /// measures toolchain `i` along with the first one and compares them.
+    pub fn run(
+        target: BisectTarget,
+        toolchains: &[(String, LocalToolchain)],
+        mut measure: impl FnMut(usize) -> anyhow::Result<Vec<PairedComparison>>,
+    ) -> anyhow::Result<Self> {
+        let mut steps = vec![];
+        let first = bisect(toolchains.len(), |i| {
+            let (id, toolchain) = &toolchains[i];
+            println!("Bisecting: measuring {} against {}", id, toolchains[0].0);
+            let comparisons = measure(i)?;
+            let comparison = target
+                .select(&comparisons)?
+                .and_then(|c| c.comparison.clone());
+            let crosses = comparison.as_ref().is_some_and(|c| target.crosses(c));
+            match &comparison {
+                Some(c) => println!(
+                    "{}: {:+.2}% (p = {:.3}), {}",
+                    id,
+                    c.change,
+                    c.p_value,
+                    if crosses {
+                        "past the threshold"
+                    } else {
+                        "below the threshold"
+                    }
+                ),
+                None => println!("{}: {} not measured", id, target.metric),
+            }
+            steps.push(BisectStep {
+                toolchain: id.clone(),
+                rustc: toolchain.rustc.clone(),
+                comparison,
+                crosses,
+            });
+            Ok(crosses)
+        })?;
+
+        Ok(BisectReport {
+            target,
+            baseline: toolchains[0].0.clone(),
+            steps,
+            first_crossing: first.map(|i| toolchains[i].0.clone()),
+            last_before: first.map(|i| toolchains[i - 1].0.clone()),
+        })
+    }
+}
+
+/// Binary search for the first of `len` ordered items for which `probe`
+/// holds, assuming it does not for the first item and holds for every item
+/// after one it holds for. The last item is probed first, `None` if it does
+/// not hold.
+pub fn bisect(
+    len: usize,
+    mut probe: impl FnMut(usize) -> anyhow::Result<bool>,
+) -> anyhow::Result<Option<usize>> {
+    if len < 2 || !probe(len - 1)? {
+        return Ok(None);
+    }
+    let (mut good, mut bad) = (0, len - 1);
+    while bad - good > 1 {
+        let mid = good + (bad - good) / 2;
+        if probe(mid)? {
+            bad = mid;
+        } else {
+            good = mid;
+        }
+    }
+    Ok(Some(bad))
+}
+
+#[cfg(test)]
+mod test_bisect {
+    use crate::{
+        benchmark::{profile::Profile, scenario::Scenario},
+        statistics::{
+            paired::PairedComparison,
+            significance::{Comparison, SignificanceTest},
+        },
+    };
+
+    use super::{bisect, BisectTarget};
+
+    /// Test for bisect
+    ///
+    /// Step1. Bisect ranges where the probe first holds at every position,
+    /// and check the number of probes.
+    ///
+    /// Step2. Bisect a range where the last item does not hold.
+    #[test]
+    fn test_bisect() {
+        for len in 2..20 {
+            for first in 1..len {
+                let mut probes = vec![];
+                let found = bisect(len, |i| {
+                    probes.push(i);
+                    Ok(i >= first)
+                })
+                .unwrap();
+                assert_eq!(found, Some(first));
+                assert!(probes.len() <= 1 + (len as f64).log2().ceil() as usize);
+            }
+        }
+
+        let mut probes = 0;
+        assert_eq!(
+            bisect(10, |_| {
+                probes += 1;
+                Ok(false)
+            })
+            .unwrap(),
+            None
+        );
+        assert_eq!(probes, 1);
+    }
+
+    /// Test for BisectTarget::crosses
+    ///
+    /// Step1. Check a significant regression crosses a smaller threshold
+    /// only.
+    ///
+    /// Step2. Check a negative threshold looks for an improvement.
+    #[test]
+    fn test_crosses() {
+        let old = [100.0, 101.0, 99.0, 100.5, 99.5, 100.0];
+        let new: Vec<f64> = old.iter().map(|x| x * 1.05).collect();
+        let regression = Comparison::new(&old, &new, SignificanceTest::default()).unwrap();
+        let improvement = Comparison::new(&new, &old, SignificanceTest::default()).unwrap();
+        let target = |threshold| BisectTarget {
+            benchmark: String::from("a"),
+            profile: None,
+            build_config: None,
+            scenario: None,
+            patch: None,
+            metric: String::from("wall-time"),
+            threshold,
+        };
+
+        assert!(target(2.0).crosses(&regression));
+        assert!(!target(10.0).crosses(&regression));
+        assert!(!target(2.0).crosses(&improvement));
+
+        assert!(target(-2.0).crosses(&improvement));
+        assert!(!target(-2.0).crosses(&regression));
+    }
+
+    /// Test for BisectTarget::select
+    ///
+    /// Step1. Select the comparison of the chosen patch among the
+    /// comparisons of two patches.
+    ///
+    /// Step2. Check selecting without a patch fails, and a build config
+    /// nothing was built with selects nothing.
+    #[test]
+    fn test_select() {
+        let comparison = |patch: &str| PairedComparison {
+            benchmark: String::from("a"),
+            profile: Some(Profile::Debug),
+            build_config: None,
+            scenario: Some(Scenario::IncrPatched),
+            patch: Some(patch.to_string()),
+            metric: String::from("wall-time"),
+            baseline: String::from("old"),
+            toolchain: String::from("new"),
+            pairs: 0,
+            ratio: None,
+            comparison: None,
+        };
+        let comparisons = [comparison("0-println.patch"), comparison("1-add.patch")];
+        let mut target = BisectTarget {
+            benchmark: String::from("a"),
+            profile: Some(Profile::Debug),
+            build_config: None,
+            scenario: Some(Scenario::IncrPatched),
+            patch: Some(String::from("1-add.patch")),
+            metric: String::from("wall-time"),
+            threshold: 2.0,
+        };
+
+        let selected = target.select(&comparisons).unwrap().unwrap();
+        assert_eq!(selected.patch.as_deref(), Some("1-add.patch"));
+
+        target.patch = None;
+        assert!(target.select(&comparisons).is_err());
+        target.patch = Some(String::from("0-println.patch"));
+        target.build_config = Some(String::from("fat-lto"));
+        assert!(target.select(&comparisons).unwrap().is_none());
+    }
+}
@@ -19,6 +19,8 @@ pub struct BinaryPackageProcess<'a> {
     pub touch_file: Option<String>,
     pub packages: Vec<String>,
     pub target_path: Option<PathBuf>,
+    /// Environment of the build config, set on the cargo commands only.
+    pub cargo_env: Vec<(String, String)>,
 }
 
 impl<'a> BinaryProcess for BinaryPackageProcess<'a> {
@@ -29,6 +31,7 @@ impl<'a> BinaryProcess for BinaryPackageProcess<'a> {
                 .env("RUSTC", &*self.compiler.rustc)
                 .env("CARGO_INCREMENTAL", "0")
                 .env("RUSTC_BOOTSTRAP", "1")
+                .envs(self.cargo_env.iter().cloned())
                 .arg("rustc")
                 .arg("--manifest-path")
                 .arg(&self.manifest_path)
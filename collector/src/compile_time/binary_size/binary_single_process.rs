@@ -18,6 +18,8 @@ pub struct BinarySingleProcess<'a> {
     pub rustc_args: Vec<String>,
     pub touch_file: Option<String>,
     pub target_path: Option<PathBuf>,
+    /// Environment of the build config, set on the cargo commands only.
+    pub cargo_env: Vec<(String, String)>,
 }
 
 impl<'a> BinaryProcess for BinarySingleProcess<'a> {
@@ -28,6 +30,7 @@ impl<'a> BinaryProcess for BinarySingleProcess<'a> {
             .env("RUSTC", &*self.compiler.rustc)
             .env("CARGO_INCREMENTAL", "0")
             .env("RUSTC_BOOTSTRAP", "1")
+            .envs(self.cargo_env.iter().cloned())
             .arg("rustc")
             .arg("--manifest-path")
             .arg(&self.manifest_path)
@@ -7,13 +7,14 @@ use std::{
 
 use crate::{
     benchmark::{
-        benchmark::{Benchamrk, BenchmarkSuit, CompileTimeType},
+        benchmark::{Benchamrk, CompileTimeType},
+        build_config::BuildConfig,
+        filter::BenchmarkFilter,
         profile::Profile,
         scenario::Scenario,
+        suit::BenchmarkSuit,
     },
-    compile_time::{
-        binary_size::binary_single_process::BinarySingleProcess, discover_benchmark_suit,
-    },
+    compile_time::binary_size::binary_single_process::BinarySingleProcess,
     execute::Stats,
     statistics::compile_time_stat::CompileTimeResult,
     toolchain::{Compiler, LocalToolchain},
@@ -72,14 +73,16 @@ pub trait BinaryProcess {
     }
 }
 
+/// Measure the binary size of the benchmarks in `benchmark_dir` built with
+/// every profile of `profiles` and build config of `build_configs`.
 pub fn bench_binary_size(
     ltc: &LocalToolchain,
     profiles: &[Profile],
+    build_configs: &[Option<String>],
     benchmark_dir: PathBuf,
+    filter: &BenchmarkFilter,
 ) -> anyhow::Result<Vec<CompileTimeBenchResult>> {
-    let benchmark_suit = BenchmarkSuit {
-        benchmarks: discover_benchmark_suit(&benchmark_dir)?,
-    };
+    let benchmark_suit = BenchmarkSuit::load(&benchmark_dir, filter)?;
     println!("{}", benchmark_suit.display_benchmarks());
 
     let mut results = vec![];
@@ -89,7 +92,7 @@ pub fn bench_binary_size(
         println!("Binary-size benchmarking for '{}'", b.name.as_str());
         num_benchmark_left -= 1;
 
-        match b.bench_binary_size(ltc, profiles) {
+        match b.bench_binary_size(ltc, profiles, build_configs) {
             Ok(result) => results.push(result),
             Err(e) => eprintln!("Fail to bench '{}'! {}", b.name, e),
         };
@@ -103,6 +106,7 @@ impl Benchamrk {
         &self,
         ltc: &LocalToolchain,
         profiles: &[Profile],
+        build_configs: &[Option<String>],
     ) -> anyhow::Result<CompileTimeBenchResult> {
         if self.config.disabled {
             println!("Skipping {}: disabled", self.name);
@@ -110,29 +114,44 @@ impl Benchamrk {
 
         print!("Preparing {}...", self.name);
 
-        let tmp_dir = ManuallyDrop::new(self.make_temp_dir(&self.path)?);
-
         let mut bench_result = CompileTimeBenchResult::new(self.name.clone(), 1);
 
-        for profile in profiles {
-            let binary_size_process = self.make_binray_size_process(
-                Compiler::from_toolchain(ltc),
-                tmp_dir.path(),
-                *profile,
-            );
-
-            if let Some(stats) = binary_size_process.run_rustc()? {
-                bench_result.add_result(CompileTimeResult::new(
-                    self.name.clone(),
-                    1,
+        for build_config in build_configs.iter().map(|c| c.as_deref()) {
+            let Some(config) = self.build_config(build_config) else {
+                println!(
+                    "Skipping {}: no build config `{}`",
+                    self.name,
+                    build_config.unwrap_or_default()
+                );
+                continue;
+            };
+            // Artifacts of another build config would add to the size.
+            let tmp_dir = ManuallyDrop::new(self.make_temp_dir(&self.path)?);
+
+            for profile in profiles {
+                let binary_size_process = self.make_binray_size_process(
+                    Compiler::from_toolchain(ltc),
+                    tmp_dir.path(),
                     *profile,
-                    Scenario::Full,
-                    stats,
-                ));
+                    &config,
+                );
+
+                if let Some(stats) = binary_size_process.run_rustc()? {
+                    bench_result.add_result(
+                        CompileTimeResult::new(
+                            self.name.clone(),
+                            1,
+                            *profile,
+                            Scenario::Full,
+                            stats,
+                        )
+                        .with_build_config(build_config),
+                    );
+                }
             }
-        }
 
-        drop(ManuallyDrop::into_inner(tmp_dir));
+            drop(ManuallyDrop::into_inner(tmp_dir));
+        }
 
         println!("Bench success.");
 
@@ -144,6 +163,7 @@ impl Benchamrk {
         compiler: Compiler<'a>,
         cwd: &'a Path,
         profile: Profile,
+        config: &BuildConfig,
     ) -> Box<dyn BinaryProcess + 'a> {
         let compile_type = if let Some(t) = &self.config.compile_time_type {
             t
@@ -182,6 +202,7 @@ impl Benchamrk {
                     .collect(),
                 touch_file: self.config.touch_file.clone(),
                 target_path: self.config.target_path.clone(),
+                cargo_env: config.cargo_env(),
             }),
             CompileTimeType::Packages => Box::new(BinaryPackageProcess {
                 compiler,
@@ -214,6 +235,7 @@ impl Benchamrk {
                 touch_file: self.config.touch_file.clone(),
                 packages: self.config.packages.clone().unwrap(),
                 target_path: self.config.target_path.clone(),
+                cargo_env: config.cargo_env(),
             }),
         }
     }
@@ -229,8 +251,9 @@ mod test_binary_size {
     use anyhow::Context;
 
     use crate::{
-        benchmark::profile::Profile,
+        benchmark::{filter::BenchmarkFilter, profile::Profile},
         compile_time::binary_size::{binary_single_process::BinarySingleProcess, BinaryProcess},
+        statistics::metadata::ToolchainIdentity,
         toolchain::{Compiler, LocalToolchain},
     };
 
@@ -272,11 +295,13 @@ mod test_binary_size {
             &LocalToolchain {
                 rustc: PathBuf::from(get_rustc().unwrap()),
                 cargo: PathBuf::from(get_cargo().unwrap()),
-                flame_graph: PathBuf::new(),
                 id: String::new(),
+                identity: ToolchainIdentity::default(),
             },
             &[Profile::Release],
+            &[None],
             PathBuf::from("test/binary_size/benchmarks"),
+            &BenchmarkFilter::default(),
         )
         .unwrap();
 
@@ -310,6 +335,7 @@ mod test_binary_size {
             rustc_args: vec![],
             touch_file: None,
             target_path: None,
+            cargo_env: vec![],
         };
         let binary_size = binary_process.get_binary_size(&PathBuf::from(".")).unwrap();
         assert!((binary_size as f64 / (1 << 20) as f64) > 15.);
@@ -1,19 +1,21 @@
-use std::{
-    path::{Path, PathBuf},
-    process::Command,
-};
+use std::{fs, path::Path, process::Command, time::Duration};
 
 use crate::{
     benchmark::profile::Profile,
-    execute::{process_benchmark_output, Stats},
-    toolchain::{Compiler, PerfTool},
+    execute::{
+        events::{EventSet, PERF_EVENTS_ENV},
+        process_benchmark_output,
+        self_profile::SelfProfile,
+        Stats,
+    },
+    toolchain::{Bencher, Compiler, PerfTool},
     utils::{
         self,
-        command::{command_discard_output, command_output},
+        command::{command_discard_output, command_output_with_timeout, is_timeout},
     },
 };
 
-use super::{CompileTimeProcessor, FAKE_FLAMEGRAPH, FAKE_RUSTC};
+use super::{CompileTimeProcessor, RustcMeasurement, FAKE_FLAMEGRAPH, FAKE_RUSTC};
 
 pub struct CargoPackageProcess<'a> {
     pub compiler: Compiler<'a>,
@@ -26,6 +28,9 @@ pub struct CargoPackageProcess<'a> {
     pub rustc_args: Vec<String>,
     pub touch_file: Option<String>,
     pub packages: Vec<String>,
+    pub timeout: Option<Duration>,
+    /// Environment of the build config, set on the cargo commands only.
+    pub cargo_env: Vec<(String, String)>,
 }
 
 impl<'a> CargoPackageProcess<'a> {
@@ -45,6 +50,7 @@ impl<'a> CargoPackageProcess<'a> {
             // verification) so unconditionally enable unstable features, even
             // on stable compilers.
             .env("RUSTC_BOOTSTRAP", "1")
+            .envs(self.cargo_env.iter().cloned())
             .current_dir(cwd)
             .arg(subcommand)
             .arg("--package")
@@ -71,6 +77,7 @@ impl<'a> CargoPackageProcess<'a> {
             .arg(&self.compiler.cargo)
             .arg("rustc")
             .env("RUSTC", self.compiler.rustc)
+            .envs(self.cargo_env.iter().cloned())
             .arg("--package")
             .arg(package)
             .arg("--manifest-path")
@@ -83,12 +90,13 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
     fn run_rustc(
         &mut self,
         perf_tool: &PerfTool,
-        _event_filter_file: &PathBuf,
+        events: &EventSet,
         needs_final: bool,
-    ) -> anyhow::Result<Option<Stats>> {
-        let cargo_subcommand = "rustc";
+    ) -> anyhow::Result<Option<RustcMeasurement>> {
+        let cargo_subcommand = perf_tool.get_bencher().cargo_subcommand();
 
         let mut stats_sum = Stats::default();
+        let mut self_profile_sum: Option<SelfProfile> = None;
 
         let mut i = 1;
         for package in &self.packages {
@@ -102,6 +110,7 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
             i += 1;
 
             let mut cmd = self.base_command(self.cwd, cargo_subcommand, package);
+            cmd.env(PERF_EVENTS_ENV, events.to_arg());
 
             match self.profile {
                 Profile::Check => {
@@ -128,12 +137,9 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
             // we want to wrap rustc.
             if needs_final {
                 let perf_tool_name = perf_tool.name();
-                match perf_tool.get_bencher() {
-                    crate::toolchain::Bencher::PerfStat => (),
-                    crate::toolchain::Bencher::PerfRecord => {
-                        cmd.arg("--output")
-                            .arg(self.cwd.join(format!("{}_perf.data", package)));
-                    }
+                if perf_tool.get_bencher() == crate::toolchain::Bencher::PerfRecord {
+                    cmd.arg("--output")
+                        .arg(self.cwd.join(format!("{}_perf.data", package)));
                 }
                 // If we're using a processor, we expect that only the crate
                 // we're interested in benchmarking will be built, not any
@@ -178,13 +184,38 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
             log::info!("cwd: {:?}", self.cwd);
             log::info!("env: {:?}", cmd.get_envs());
             log::info!("cmd: {:?}", cmd);
-            let output = command_output(&mut cmd);
+            let output = command_output_with_timeout(&mut cmd, self.timeout);
             match output {
                 Ok(output) => {
                     log::debug!("output: \n{:?}", output);
+                    // Every package leaves its own artifact in the same dir.
+                    let bencher = perf_tool.get_bencher();
+                    if let Some(artifact) = bencher.artifact() {
+                        let package_artifact = self.cwd.join(format!("{}_{}", package, artifact));
+                        if bencher == Bencher::LlvmLines {
+                            fs::write(package_artifact, &output.stdout)?;
+                        } else if self.cwd.join(artifact).exists() {
+                            fs::rename(self.cwd.join(artifact), package_artifact)?;
+                        }
+                    }
+                    if bencher == Bencher::PerfStatSelfProfile {
+                        match SelfProfile::from_output(&String::from_utf8_lossy(&output.stdout)) {
+                            Ok(Some(self_profile)) => match &mut self_profile_sum {
+                                Some(sum) => *sum += self_profile,
+                                None => self_profile_sum = Some(self_profile),
+                            },
+                            Ok(None) => (),
+                            Err(err) => eprintln!(
+                                "Fail to parse self-profile of package {} [{:?}]: {:?}",
+                                package, self.profile, err
+                            ),
+                        }
+                    }
                     match perf_tool.get_bencher() {
-                        crate::toolchain::Bencher::PerfStat => {
-                            let stats = process_benchmark_output(output);
+                        crate::toolchain::Bencher::PerfStat
+                        | crate::toolchain::Bencher::PerfStatSelfProfile
+                        | crate::toolchain::Bencher::Rusage => {
+                            let stats = process_benchmark_output(output, events.min_coverage);
                             match stats {
                                 Ok(stats) => stats_sum += stats,
                                 Err(err) => {
@@ -197,9 +228,11 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
                                 }
                             }
                         }
-                        crate::toolchain::Bencher::PerfRecord => (),
+                        _ => (),
                     }
                 }
+                // The whole build is recorded as timed out.
+                Err(err) if is_timeout(&err) => return Err(err),
                 Err(err) => {
                     eprintln!(
                         "Fail to bench package {} [{:?}]. Skip",
@@ -212,8 +245,10 @@ impl<'a> CompileTimeProcessor for CargoPackageProcess<'a> {
         }
 
         match perf_tool.get_bencher() {
-            crate::toolchain::Bencher::PerfStat => return Ok(Some(stats_sum)),
-            crate::toolchain::Bencher::PerfRecord => return Ok(None),
+            crate::toolchain::Bencher::PerfStat
+            | crate::toolchain::Bencher::PerfStatSelfProfile
+            | crate::toolchain::Bencher::Rusage => return Ok(Some((stats_sum, self_profile_sum))),
+            _ => return Ok(None),
         }
     }
 
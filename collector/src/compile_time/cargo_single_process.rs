@@ -1,21 +1,27 @@
 use std::{
-    path::{Path, PathBuf},
-    process::Command,
+    fs,
+    path::Path,
+    process::{Command, Output},
+    time::Duration,
 };
 
 use anyhow::Context;
 
 use crate::{
     benchmark::profile::Profile,
-    execute::{process_benchmark_output, Stats},
-    toolchain::{Compiler, PerfTool},
+    execute::{
+        events::{EventSet, PERF_EVENTS_ENV},
+        process_benchmark_output,
+        self_profile::SelfProfile,
+    },
+    toolchain::{Bencher, Compiler, PerfTool},
     utils::{
         self,
-        command::{command_discard_output, command_output},
+        command::{command_discard_output, command_output, command_output_with_timeout},
     },
 };
 
-use super::{CompileTimeProcessor, FAKE_FLAMEGRAPH, FAKE_RUSTC};
+use super::{CompileTimeProcessor, RustcMeasurement, FAKE_FLAMEGRAPH, FAKE_RUSTC};
 
 pub struct CargoSingleProcess<'a> {
     pub compiler: Compiler<'a>,
@@ -27,6 +33,28 @@ pub struct CargoSingleProcess<'a> {
     pub cargo_args: Vec<String>,
     pub rustc_args: Vec<String>,
     pub touch_file: Option<String>,
+    pub timeout: Option<Duration>,
+    /// Environment of the build config, set on the cargo commands only.
+    pub cargo_env: Vec<(String, String)>,
+}
+
+/// Parse the `perf stat` counts and the self-profile printed by `rustc-fake`.
+/// A malformed self-profile is reported and left out, the counts are kept.
+fn process_self_profile_output(
+    output: Output,
+    min_coverage: f64,
+) -> anyhow::Result<RustcMeasurement> {
+    let self_profile = match SelfProfile::from_output(&String::from_utf8_lossy(&output.stdout)) {
+        Ok(self_profile) => self_profile,
+        Err(err) => {
+            eprintln!("Fail to parse self-profile: {:?}", err);
+            None
+        }
+    };
+    Ok((
+        process_benchmark_output(output, min_coverage)?,
+        self_profile,
+    ))
 }
 
 impl<'a> CargoSingleProcess<'a> {
@@ -46,6 +74,7 @@ impl<'a> CargoSingleProcess<'a> {
             // verification) so unconditionally enable unstable features, even
             // on stable compilers.
             .env("RUSTC_BOOTSTRAP", "1")
+            .envs(self.cargo_env.iter().cloned())
             .current_dir(cwd)
             .arg(subcommand)
             .arg("--manifest-path")
@@ -71,7 +100,8 @@ impl<'a> CargoSingleProcess<'a> {
             .arg("rustc")
             .env("RUSTC", self.compiler.rustc)
             .env("CARGO_INCREMENTAL", "0")
-            .env("RUSTC_BOOTSTRAP", "1");
+            .env("RUSTC_BOOTSTRAP", "1")
+            .envs(self.cargo_env.iter().cloned());
         cmd
     }
 
@@ -89,12 +119,13 @@ impl<'a> CompileTimeProcessor for CargoSingleProcess<'a> {
     fn run_rustc(
         &mut self,
         perf_tool: &PerfTool,
-        _event_filter_file: &PathBuf,
+        events: &EventSet,
         needs_final: bool,
-    ) -> anyhow::Result<Option<Stats>> {
-        let cargo_subcommand = "rustc";
+    ) -> anyhow::Result<Option<RustcMeasurement>> {
+        let cargo_subcommand = perf_tool.get_bencher().cargo_subcommand();
 
         let mut cmd = self.base_command(self.cwd, cargo_subcommand);
+        cmd.env(PERF_EVENTS_ENV, events.to_arg());
 
         cmd.arg("-p").arg(self.get_pkgid(self.cwd)?);
         match self.profile {
@@ -165,13 +196,24 @@ impl<'a> CompileTimeProcessor for CargoSingleProcess<'a> {
         log::info!("cwd: {:?}", self.cwd);
         log::info!("env: {:?}", cmd.get_envs());
         log::info!("cmd: {:?}", cmd);
-        let output = command_output(&mut cmd)
+        let output = command_output_with_timeout(&mut cmd, self.timeout)
             .with_context(|| format!("fail to start benchmark process."))?;
         log::debug!("output: \n{:?}", output);
 
+        // `cargo llvm-lines` prints its report instead of leaving a file.
+        if perf_tool.get_bencher() == Bencher::LlvmLines {
+            fs::write(self.cwd.join("llvm-lines"), &output.stdout)?;
+        }
+
         match perf_tool.get_bencher() {
-            crate::toolchain::Bencher::PerfStat => Ok(Some(process_benchmark_output(output)?)),
-            crate::toolchain::Bencher::PerfRecord => Ok(None),
+            crate::toolchain::Bencher::PerfStat | crate::toolchain::Bencher::Rusage => Ok(Some((
+                process_benchmark_output(output, events.min_coverage)?,
+                None,
+            ))),
+            crate::toolchain::Bencher::PerfStatSelfProfile => Ok(Some(
+                process_self_profile_output(output, events.min_coverage)?,
+            )),
+            _ => Ok(None),
         }
     }
 
@@ -211,7 +253,7 @@ impl<'a> CompileTimeProcessor for CargoSingleProcess<'a> {
         cmd.arg("--profile").arg("check");
         cmd.args(&self.cargo_args);
 
-        match command_output(&mut cmd) {
+        match command_output_with_timeout(&mut cmd, self.timeout) {
             Ok(_) => {
                 cmd = self.base_command(self.cwd, "clean");
                 command_output(&mut cmd)?;
@@ -229,3 +271,31 @@ impl<'a> CompileTimeProcessor for CargoSingleProcess<'a> {
         self.incremental = incr;
     }
 }
+
+#[cfg(all(test, unix))]
+mod test_cargo_single_process {
+    use std::{os::unix::process::ExitStatusExt, process::Output};
+
+    use crate::execute::events::DEFAULT_MIN_COVERAGE;
+
+    use super::process_self_profile_output;
+
+    /// Test for process_self_profile_output
+    ///
+    /// Step1. Parse output with a truncated self-profile and verify the
+    /// `perf stat` counts are kept without a self-profile.
+    #[test]
+    fn test_malformed_self_profile() {
+        let output = Output {
+            status: ExitStatusExt::from_raw(0),
+            stdout: b"1000;;instructions:u;1000;100.00;;\n!self-profile-output:{\"query_data\":[\n"
+                .to_vec(),
+            stderr: vec![],
+        };
+
+        let (stats, self_profile) =
+            process_self_profile_output(output, DEFAULT_MIN_COVERAGE).unwrap();
+        assert_eq!(stats.stats["instructions:u"], 1000.);
+        assert!(self_profile.is_none());
+    }
+}
@@ -1,107 +1,160 @@
 use std::{
-    fs::read_dir,
+    env::current_dir,
+    fs::create_dir_all,
     path::{Path, PathBuf},
 };
 
-use anyhow::{bail, Context, Ok};
+use anyhow::{Context, Ok};
 
 use crate::{
     benchmark::{
-        benchmark::{Benchamrk, BenchmarkSuit},
-        profile::Profile,
-        scenario::Scenario,
+        benchmark::Benchamrk, filter::BenchmarkFilter, journal::Journal, profile::Profile,
+        scenario::Scenario, suit::BenchmarkSuit,
     },
-    execute::Stats,
-    statistics::compile_time_stat::CompileTimeBenchResult,
+    execute::{events::EventSet, self_profile::SelfProfile, Stats},
+    statistics::stability::StopRule,
     toolchain::{Compiler, LocalToolchain, PerfTool},
+    utils::rng::SplitMix64,
 };
 
 pub(crate) mod binary_size;
 pub(crate) mod cargo_package_process;
 pub(crate) mod cargo_single_process;
 
+/// A toolchain measured by `bench_local`, with the place of its results.
+pub struct CompileTimeRun<'a> {
+    pub id: String,
+    pub compiler: Compiler<'a>,
+    /// Directory of the results, artifacts and journal of the toolchain.
+    pub out_dir: PathBuf,
+    pub flame_graph_path: PathBuf,
+    pub journal: Journal,
+}
+
+impl<'a> CompileTimeRun<'a> {
+    /// Prepare `out_dir` to hold the results of `ltc`. With `resume` the
+    /// results of the previous run in `out_dir` are kept.
+    pub fn new(
+        ltc: &'a LocalToolchain,
+        id: String,
+        out_dir: PathBuf,
+        resume: bool,
+    ) -> anyhow::Result<Self> {
+        let flame_graph_path = current_dir()?.join(out_dir.join("flamegraphs"));
+        create_dir_all(&flame_graph_path)
+            .with_context(|| format!("Fail to create output dir {:?}", flame_graph_path))?;
+        log::debug!("{:?}", flame_graph_path);
+        let journal = Journal::open(&out_dir, resume)?;
+        Ok(CompileTimeRun {
+            id,
+            compiler: Compiler::from_toolchain(ltc),
+            out_dir,
+            flame_graph_path,
+            journal,
+        })
+    }
+}
+
+/// Measure the benchmarks in `benchmark_dir` with every toolchain of
+/// `runs` and every build config of `build_configs`, see
+/// `Benchamrk::measure_compile_time`. The results are in the journal of
+/// each run.
 pub(crate) fn bench_compile_time(
-    ltc: &LocalToolchain,
+    runs: &mut [CompileTimeRun<'_>],
     perf_tool: &PerfTool,
-    event_filter_file: &PathBuf,
+    events: &EventSet,
     profiles: &[Profile],
+    build_configs: &[Option<String>],
     scenarios: &[Scenario],
     benchmark_dir: PathBuf,
+    filter: &BenchmarkFilter,
     iterations: usize,
-    flame_graph_path: &Path,
     flamegraph_flag: i32,
-    out_dir: &Path,
-) -> anyhow::Result<Vec<CompileTimeBenchResult>> {
-    let benchmark_suit = BenchmarkSuit {
-        benchmarks: discover_benchmark_suit(&benchmark_dir)?,
-    };
+    timeout: Option<u64>,
+    stop_rule: Option<&StopRule>,
+    warmup: usize,
+    rng: &mut SplitMix64,
+) -> anyhow::Result<()> {
+    let benchmark_suit = BenchmarkSuit::load(&benchmark_dir, filter)?;
     println!("{}", benchmark_suit.display_benchmarks());
 
-    let result = bench(
+    bench(
+        runs,
         perf_tool,
-        event_filter_file,
-        &profiles,
-        &scenarios,
-        Compiler::from_toolchain(ltc),
+        events,
+        profiles,
+        build_configs,
+        scenarios,
         &benchmark_suit.benchmarks,
         Some(iterations),
-        flame_graph_path,
         flamegraph_flag,
-        out_dir,
-    )?;
-    Ok(result)
+        timeout,
+        stop_rule,
+        warmup,
+        rng,
+    )
 }
 
-pub(crate) fn bench<'a>(
+pub(crate) fn bench(
+    runs: &mut [CompileTimeRun<'_>],
     perf_tool: &PerfTool,
-    event_filter_file: &PathBuf,
+    events: &EventSet,
     profiles: &[Profile],
+    build_configs: &[Option<String>],
     scenarios: &[Scenario],
-    compiler: Compiler<'_>,
     benchmarks: &[Benchamrk],
     iterations: Option<usize>,
-    flame_graph_path: &Path,
     flamegraph_flag: i32,
-    out_dir: &Path,
-) -> anyhow::Result<Vec<CompileTimeBenchResult>> {
-    let mut result_vec = vec![];
+    timeout: Option<u64>,
+    stop_rule: Option<&StopRule>,
+    warmup: usize,
+    rng: &mut SplitMix64,
+) -> anyhow::Result<()> {
     let mut num_benchmark_left = benchmarks.len();
     benchmarks.iter().for_each(|b| {
         println!("{} benchmarks waiting.", num_benchmark_left);
         println!("Compile-time benchmarking for '{}'", b.name.as_str());
         num_benchmark_left -= 1;
 
-        let result = b
-            .measure_compile_time(
-                perf_tool,
-                event_filter_file,
-                profiles,
-                scenarios,
-                compiler,
-                iterations,
-                flame_graph_path,
-                flamegraph_flag,
-                out_dir,
-            )
-            .with_context(|| format!("Fail to bench '{}'!", b.name));
-        match result {
-            core::result::Result::Ok(r) => result_vec.push(r),
-            Err(s) => {
+        for build_config in build_configs {
+            let result = b
+                .measure_compile_time(
+                    perf_tool,
+                    events,
+                    profiles,
+                    build_config.as_deref(),
+                    scenarios,
+                    runs,
+                    iterations,
+                    flamegraph_flag,
+                    timeout,
+                    stop_rule,
+                    warmup,
+                    rng,
+                )
+                .with_context(|| format!("Fail to bench '{}'!", b.name));
+            if let Err(s) = result {
                 eprintln!("{}", s);
                 eprintln!("Please check your perf tool, rust compiler or the benchmark program.\n");
             }
         }
     });
-    Ok(result_vec)
+    // Results of a resumed run and of benchmarks failing after some
+    // iterations are kept in the journals as well.
+    Ok(())
 }
 
+/// Stats of a measured build, and the self-profile of the measured rustc
+/// when the bencher collects one.
+pub(crate) type RustcMeasurement = (Stats, Option<SelfProfile>);
+
 pub(crate) trait CompileTimeProcessor {
     fn run_rustc(
         &mut self,
         perf_tool: &PerfTool,
-        event_filter_file: &PathBuf,
+        events: &EventSet,
         needs_final: bool,
-    ) -> anyhow::Result<Option<Stats>>;
+    ) -> anyhow::Result<Option<RustcMeasurement>>;
     fn draw_flame_graph(&self, dir: &Path) -> anyhow::Result<()>;
     fn gen_pkg(&self) -> anyhow::Result<()>;
     fn increment(&mut self, incr: bool);
@@ -122,27 +175,3 @@ lazy_static::lazy_static! {
         fake_runtime
     };
 }
-
-pub(crate) fn discover_benchmark_suit(dir: &Path) -> anyhow::Result<Vec<Benchamrk>> {
-    let mut benchmarks = vec![];
-
-    for entry in read_dir(dir)
-        .with_context(|| format!("failed to list benchmark dir '{}'", dir.display()))?
-    {
-        let entry = entry?;
-        let path = entry.path();
-        let name = match entry.file_name().into_string() {
-            core::result::Result::Ok(s) => s,
-            Err(e) => bail!("non-utf8 benchmark name: {:?}", e),
-        };
-        if !entry.file_type()?.is_dir() {
-            println!("benchmark '{}' ignored", name);
-            continue;
-        }
-        benchmarks.push(Benchamrk::new(name, path)?);
-    }
-    if benchmarks.is_empty() {
-        eprintln!("Error: no benchmark found in '{}'", dir.display());
-    }
-    Ok(benchmarks)
-}
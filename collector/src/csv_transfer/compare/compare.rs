@@ -1,10 +1,10 @@
 use std::{collections::HashMap, fs::read_dir, path::PathBuf};
 
-use crate::csv_transfer::get_sub_dir;
+use crate::csv_transfer::{get_sub_dir, toolchain_name};
 
 use super::{
     stat::compare_stats,
-    writer::{write_csv, write_tex},
+    writer::{write_csv, write_significance_csv, write_tex},
 };
 
 /// `FilePair` is used to store csv files of current & old rustc versions
@@ -15,12 +15,14 @@ pub(super) struct FilePair {
 }
 
 /// `do_compare` will compare statistics grouped by profile & metric,
-/// calculate the change rates and write them to csv file & tex file.
+/// calculate the change rates and their significance and write them to csv
+/// files & tex file.
 pub(crate) fn do_compare(dir: PathBuf) -> anyhow::Result<()> {
     find_file_pairs(dir.clone())?.into_iter().for_each(|pair| {
         let stat = compare_stats(pair).unwrap();
 
         write_csv(&stat, dir.clone()).unwrap();
+        write_significance_csv(&stat, dir.clone()).unwrap();
         write_tex(&stat, dir.clone()).unwrap()
     });
 
@@ -46,46 +48,39 @@ fn find_file_pairs(dir: PathBuf) -> anyhow::Result<Vec<FilePair>> {
 
     if let Some(current_dir) = current_dir {
         if let Some(old_dir) = old_dir {
-            for entry in read_dir(current_dir)? {
-                let entry = entry?;
-                if entry.file_name().to_str().unwrap().ends_with(".csv") {
-                    file_map.insert(
-                        String::from(entry.file_name().to_str().unwrap()),
-                        String::new(),
-                    );
+            // Csv files are named after their toolchain, and paired by the
+            // rest of their name.
+            let csv_files = |dir: &PathBuf| -> anyhow::Result<Vec<(String, String)>> {
+                let prefix = format!("{}_", toolchain_name(dir));
+                let mut files = vec![];
+                for entry in read_dir(dir)? {
+                    let name = entry?.file_name().to_string_lossy().to_string();
+                    if name.ends_with(".csv") {
+                        let key = name.strip_prefix(&prefix).unwrap_or(&name).to_string();
+                        files.push((key, name));
+                    }
                 }
-            }
+                Ok(files)
+            };
 
-            for entry in read_dir(old_dir)? {
-                let entry = entry?;
-                if entry.file_name().to_str().unwrap().ends_with(".csv") {
-                    if let Some(pair) = file_map.get_mut(&String::from(
-                        entry
-                            .file_name()
-                            .to_str()
-                            .unwrap()
-                            .replace("old", "current"),
-                    )) {
-                        *pair = String::from(entry.file_name().to_str().unwrap());
-                    } else {
-                        file_map.remove(&String::from(
-                            entry
-                                .file_name()
-                                .to_str()
-                                .unwrap()
-                                .replace("old", "current"),
-                        ));
-                        eprintln!(
-                            "csv file for {} not found.",
-                            entry
-                                .file_name()
-                                .to_str()
-                                .unwrap()
-                                .replace("old", "current")
-                        );
-                    }
+            for (key, name) in csv_files(&current_dir)? {
+                file_map.insert(key, (name, String::new()));
+            }
+            for (key, name) in csv_files(&old_dir)? {
+                match file_map.get_mut(&key) {
+                    Some(pair) => pair.1 = name,
+                    None => eprintln!("csv file for {} not found.", name),
                 }
             }
+
+            return Ok(file_map
+                .into_values()
+                .filter(|(_, old)| !old.is_empty())
+                .map(|(new, old)| FilePair {
+                    new: current_dir.join(new),
+                    old: old_dir.join(old),
+                })
+                .collect());
         } else {
             eprintln!("Statistics for old version not found.");
         }
@@ -93,13 +88,7 @@ fn find_file_pairs(dir: PathBuf) -> anyhow::Result<Vec<FilePair>> {
         eprintln!("Statistics for current version not found.");
     }
 
-    Ok(file_map
-        .into_iter()
-        .map(|(a, b)| FilePair {
-            new: dir.to_path_buf().join("current").join(a),
-            old: dir.to_path_buf().join("old").join(b),
-        })
-        .collect())
+    Ok(vec![])
 }
 
 #[cfg(test)]
@@ -194,6 +183,18 @@ mod test {
                 PathBuf::from("test/csv_transfer/compare/demo_data/debug_context-switches.csv"),
                 PathBuf::from("test/csv_transfer/compare/demo_data/debug_context-switches std.csv"),
             ),
+            (
+                PathBuf::from("test/csv_transfer/compare/demo_data/debug_branch-misses_significance.csv"),
+                PathBuf::from("test/csv_transfer/compare/demo_data/debug_branch-misses_significance std.csv"),
+            ),
+            (
+                PathBuf::from("test/csv_transfer/compare/demo_data/debug_cache-misses_significance.csv"),
+                PathBuf::from("test/csv_transfer/compare/demo_data/debug_cache-misses_significance std.csv"),
+            ),
+            (
+                PathBuf::from("test/csv_transfer/compare/demo_data/debug_context-switches_significance.csv"),
+                PathBuf::from("test/csv_transfer/compare/demo_data/debug_context-switches_significance std.csv"),
+            ),
             (
                 PathBuf::from("test/csv_transfer/compare/demo_data/debug_branch-misses.tex"),
                 PathBuf::from("test/csv_transfer/compare/demo_data/debug_branch-misses std.tex"),
@@ -5,12 +5,19 @@ use std::{
     path::PathBuf,
 };
 
+use crate::statistics::significance::{Comparison, SignificanceTest};
+
 use super::compare::FilePair;
 
 /// `Stat` maps benchmark-name to average_statistic.
 #[derive(Debug)]
 pub struct Stat {
     pub data: HashMap<String, f64>,
+    /// Per-iteration values of each benchmark.
+    pub samples: HashMap<String, Vec<f64>>,
+    /// Significance of the change of each benchmark, only set by
+    /// `compare_stats`.
+    pub significance: HashMap<String, Comparison>,
     pub metric: String,
     pub profile: String,
 }
@@ -22,6 +29,15 @@ pub(super) fn compare_stats(file_pair: FilePair) -> anyhow::Result<Stat> {
     assert_eq!(new_stat.metric, old_stat.metric);
     assert_eq!(new_stat.profile, old_stat.profile);
 
+    let significance = new_stat
+        .samples
+        .iter()
+        .filter_map(|(n, new)| {
+            let old = old_stat.samples.get(n)?;
+            Comparison::new(old, new, SignificanceTest::default()).map(|c| (n.clone(), c))
+        })
+        .collect();
+
     Ok(Stat {
         data: new_stat
             .data
@@ -34,13 +50,15 @@ pub(super) fn compare_stats(file_pair: FilePair) -> anyhow::Result<Stat> {
                 )
             })
             .collect(),
+        samples: HashMap::new(),
+        significance,
         metric: new_stat.metric.clone(),
         profile: new_stat.profile.clone(),
     })
 }
 
 fn get_stats(csv_file: PathBuf) -> anyhow::Result<Stat> {
-    let mut stat = vec![];
+    let mut stat: Vec<(String, Vec<f64>)> = vec![];
 
     let mut reader = BufReader::new(File::open(&csv_file)?);
 
@@ -51,13 +69,13 @@ fn get_stats(csv_file: PathBuf) -> anyhow::Result<Stat> {
     while reader.read_line(&mut buf)? != 0 {
         if first_line == true {
             buf.split(',').into_iter().for_each(|s| {
-                stat.push((s.trim().to_string(), 0f64));
+                stat.push((s.trim().to_string(), vec![]));
             });
             first_line = false;
         } else {
             let mut col_num = 0;
             buf.split(',').into_iter().for_each(|s| {
-                stat[col_num].1 += s.trim().parse::<f64>().unwrap();
+                stat[col_num].1.push(s.trim().parse::<f64>().unwrap());
                 col_num += 1;
             });
             line_num += 1;
@@ -68,9 +86,11 @@ fn get_stats(csv_file: PathBuf) -> anyhow::Result<Stat> {
 
     Ok(Stat {
         data: stat
-            .into_iter()
-            .map(|(s, v)| (s, v / line_num as f64))
+            .iter()
+            .map(|(s, v)| (s.clone(), v.iter().sum::<f64>() / line_num as f64))
             .collect(),
+        samples: stat.into_iter().collect(),
+        significance: HashMap::new(),
         metric: csv_file
             .file_name()
             .unwrap()
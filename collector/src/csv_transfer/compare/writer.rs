@@ -4,6 +4,8 @@ use std::{
     path::PathBuf,
 };
 
+use crate::statistics::significance::{write_comparisons_csv, Verdict};
+
 use super::stat::Stat;
 
 pub fn write_csv(stat: &Stat, dir: PathBuf) -> anyhow::Result<()> {
@@ -40,6 +42,15 @@ pub fn write_csv(stat: &Stat, dir: PathBuf) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Write the significance of the change of every benchmark, one benchmark
+/// per line.
+pub fn write_significance_csv(stat: &Stat, dir: PathBuf) -> anyhow::Result<()> {
+    write_comparisons_csv(
+        &stat.significance,
+        &dir.join(stat.profile.clone() + "_" + stat.metric.as_str() + "_significance.csv"),
+    )
+}
+
 pub fn write_tex(stat: &Stat, dir: PathBuf) -> anyhow::Result<()> {
     let mut writer = BufWriter::new(File::create(
         dir.join(stat.profile.clone() + "_" + stat.metric.as_str() + ".tex"),
@@ -80,12 +91,15 @@ pub fn write_tex(stat: &Stat, dir: PathBuf) -> anyhow::Result<()> {
     writer.write_all(
         (data_sorted
             .iter()
-            .map(|(_, v)| {
-                if *v < 0.0 {
-                    format!("\\color{{green}}{:.2}\\%", v)
-                } else {
-                    format!("\\color{{gray}}{:.2}\\%", v)
-                }
+            .map(|(n, v)| {
+                let color = match stat.significance.get(n).map(|c| c.verdict) {
+                    Some(Verdict::SignificantImprovement) => "green",
+                    Some(Verdict::SignificantRegression) => "red",
+                    Some(Verdict::Noise) => "gray",
+                    None if *v < 0.0 => "green",
+                    None => "gray",
+                };
+                format!("\\color{{{}}}{:.2}\\%", color, v)
             })
             .collect::<Vec<String>>()
             .join(" & ")
@@ -4,7 +4,10 @@ use std::{
     path::PathBuf,
 };
 
-use crate::benchmark::profile::Profiles;
+use crate::benchmark::{
+    profile::{Profile, Profiles},
+    scenario::Scenario,
+};
 use crate::statistics::compile_time_stat::CompileTimeResultSet;
 
 pub fn read_compile_time_json(path: &PathBuf) -> anyhow::Result<CompileTimeResultSet> {
@@ -30,49 +33,125 @@ pub fn create_compile_time_csv(
 
     let header: Vec<String> = results.iter().map(|r| r.get_benchmark()).collect();
 
-    let metrics: Vec<&String> = results
-        .first()
-        .unwrap()
-        .get_stats_ref_by_profile(profiles.profiles.first().unwrap())
-        .first()
-        .unwrap()
-        .stats
-        .iter()
-        .map(|element| element.0)
-        .collect();
-
-    for metric in metrics {
-        for profile in &profiles.profiles {
-            let file = File::create(out.join(
-                rustc_id.to_string() + "_" + profile.to_string().as_str() + "_" + metric + ".csv",
-            ))?;
-            let mut csv_writer = csv::Writer::from_writer(BufWriter::new(file));
-
-            csv_writer.write_record(&header)?;
-
-            for i in 0..results.first().unwrap().get_iterations() {
-                let datas: Vec<String> = results
-                    .iter()
-                    .map(|r| {
-                        // eprintln!("{}[{}] {} {}", r.get_benchmark(), i, rustc_id, metric);
-                        if i >= r.get_stats_ref_by_profile(profile).len() {
-                            return String::from("0");
-                        }
-                        match r.get_stats_ref_by_profile(profile)[i].stats.get(metric) {
-                            Some(f) => f.to_string(),
-                            None => {
-                                eprintln!("Corrupted output data to write {}!", out.display());
-                                // corrupted = true;
-                                String::from("0")
+    for profile in &profiles.profiles {
+        // Every build config, scenario (and patch) recorded for this profile
+        // is written to csv files of its own.
+        let mut keys: Vec<(Option<&str>, Scenario, Option<String>)> = vec![];
+        results
+            .iter()
+            .flat_map(|r| r.result_vec.iter())
+            .filter(|r| r.profile == *profile)
+            .for_each(|r| {
+                let key = (r.build_config.as_deref(), r.scenario, r.patch.clone());
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            });
+
+        for (build_config, scenario, patch) in keys {
+            let metrics: Vec<&String> = match results
+                .iter()
+                .flat_map(|r| {
+                    r.get_stats_ref_by_profile_scenario(profile, build_config, &scenario, &patch)
+                })
+                .next()
+            {
+                Some(stats) => stats.stats.keys().collect(),
+                None => continue,
+            };
+            let label = csv_label(profile, build_config, &scenario, &patch);
+
+            for metric in metrics {
+                let file = File::create(
+                    out.join(rustc_id.to_string() + "_" + &label + "_" + metric + ".csv"),
+                )?;
+                let mut csv_writer = csv::Writer::from_writer(BufWriter::new(file));
+
+                csv_writer.write_record(&header)?;
+
+                for i in 0..results.first().unwrap().get_iterations() {
+                    let datas: Vec<String> = results
+                        .iter()
+                        .map(|r| {
+                            let stats = r.get_stats_ref_by_profile_scenario(
+                                profile,
+                                build_config,
+                                &scenario,
+                                &patch,
+                            );
+                            if i >= stats.len() {
+                                return String::from("0");
+                            }
+                            match stats[i].stats.get(metric) {
+                                Some(f) => f.to_string(),
+                                None => {
+                                    eprintln!("Corrupted output data to write {}!", out.display());
+                                    String::from("0")
+                                }
                             }
-                        }
-                    })
-                    .collect();
+                        })
+                        .collect();
 
-                csv_writer.write_record(&datas)?;
+                    csv_writer.write_record(&datas)?;
+                }
             }
         }
     }
 
     Ok(())
 }
+
+/// The profile segment of a csv file name. `Full` keeps the bare profile name
+/// so that files of non-incremental runs without a build config are named as
+/// before. `_` separates the segments of the file name, so it must not
+/// appear in the label.
+fn csv_label(
+    profile: &Profile,
+    build_config: Option<&str>,
+    scenario: &Scenario,
+    patch: &Option<String>,
+) -> String {
+    let mut label = profile.to_string();
+    if let Some(build_config) = build_config {
+        label = label + "-" + &build_config.replace('_', "-");
+    }
+    if *scenario != Scenario::Full {
+        label = label + "-" + &scenario.to_string();
+    }
+    if let Some(patch) = patch {
+        label = label + "-" + &patch.replace('_', "-");
+    }
+    label
+}
+
+#[cfg(test)]
+mod test_compile_time_csv {
+    use crate::benchmark::{profile::Profile, scenario::Scenario};
+
+    use super::csv_label;
+
+    #[test]
+    fn test_csv_label() {
+        assert_eq!(
+            csv_label(&Profile::Debug, None, &Scenario::Full, &None),
+            "debug"
+        );
+        assert_eq!(
+            csv_label(&Profile::Release, None, &Scenario::IncrUnchanged, &None),
+            "release-incr-unchanged"
+        );
+        assert_eq!(
+            csv_label(&Profile::Release, Some("fat_lto"), &Scenario::Full, &None),
+            "release-fat-lto"
+        );
+        assert_eq!(
+            csv_label(
+                &Profile::Check,
+                None,
+                &Scenario::IncrPatched,
+                &Some("0-add_fn".to_string())
+            ),
+            "check-incr-patched-0-add-fn"
+        );
+    }
+}
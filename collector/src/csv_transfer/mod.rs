@@ -1,4 +1,8 @@
-use std::{fs::read_dir, path::PathBuf, vec};
+use std::{
+    fs::{self, read_dir},
+    path::{Path, PathBuf},
+    vec,
+};
 
 use anyhow::{bail, Context};
 
@@ -8,12 +12,13 @@ use crate::{
         compile_time::create_compile_time_csv,
         runtime::{create_runtime_csv, read_runtime_json},
     },
+    statistics::{compile_time_stat::CompileTimeResultSet, runtime_stat::RuntimeResultVec},
 };
 
 use self::{compare::compare::do_compare, compile_time::read_compile_time_json};
 
-mod compile_time;
-mod runtime;
+pub(crate) mod compile_time;
+pub(crate) mod runtime;
 
 pub mod compare;
 
@@ -78,14 +83,7 @@ fn generate_compile_time_csv(in_dir: &PathBuf, out_dir: &PathBuf, profiles: &Pro
         }
     };
 
-    let rustc_id = if in_dir.to_str().unwrap().split('/').last().unwrap().len() != 0 {
-        in_dir.to_str().unwrap().split('/').last().unwrap()
-    } else {
-        in_dir.to_str().unwrap()[0..in_dir.to_str().unwrap().len() - 1]
-            .split('/')
-            .last()
-            .unwrap()
-    };
+    let rustc_id = &toolchain_name(in_dir);
 
     match create_compile_time_csv(
         out_dir,
@@ -117,7 +115,7 @@ fn generate_runtime_csv(in_dir: &PathBuf, out_dir: &PathBuf) {
         }
     };
 
-    let rustc_id = in_dir.to_str().unwrap().split('/').last().unwrap();
+    let rustc_id = &toolchain_name(in_dir);
 
     match create_runtime_csv(
         out_dir,
@@ -135,6 +133,34 @@ fn generate_runtime_csv(in_dir: &PathBuf, out_dir: &PathBuf) {
     };
 }
 
+/// Name of the toolchain of the results in `dir`, as recorded in their
+/// metadata. Results written before toolchains were identified are named
+/// after `dir`. The name prefixes csv file names, so `/` and the `_`
+/// separating their segments are replaced.
+pub(crate) fn toolchain_name(dir: &Path) -> String {
+    let recorded = read_input_dir(&dir.to_path_buf()).ok().and_then(|f| {
+        let content = fs::read_to_string(f).ok()?;
+        let metadata = match serde_json::from_str::<CompileTimeResultSet>(&content) {
+            Ok(set) => set.metadata,
+            Err(_) => {
+                serde_json::from_str::<RuntimeResultVec>(&content)
+                    .ok()?
+                    .metadata
+            }
+        };
+        metadata
+            .map(|m| m.toolchain.name().to_string())
+            .filter(|name| !name.is_empty())
+    });
+    recorded
+        .unwrap_or_else(|| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+        .replace(['/', '_'], "-")
+}
+
 pub fn read_input_dir(path: &PathBuf) -> anyhow::Result<PathBuf> {
     for entry in read_dir(path)
         .with_context(|| format!("failed to read input directory '{}'", path.display()))?
@@ -157,3 +183,28 @@ pub fn read_input_dir(path: &PathBuf) -> anyhow::Result<PathBuf> {
         path.display()
     ))
 }
+
+#[cfg(test)]
+mod test_csv_transfer {
+    use std::path::Path;
+
+    use super::toolchain_name;
+
+    /// Test for toolchain_name
+    ///
+    /// Step1. Name results labeled `stable/1.76_0` and check the name is
+    /// safe to prefix csv file names with.
+    ///
+    /// Step2. Name results without metadata after their directory.
+    #[test]
+    fn test_toolchain_name() {
+        assert_eq!(
+            toolchain_name(Path::new("test/csv_transfer/labeled")),
+            "stable-1.76-0"
+        );
+        assert_eq!(
+            toolchain_name(Path::new("test/csv_transfer/compare")),
+            "compare"
+        );
+    }
+}
@@ -18,13 +18,13 @@ pub fn create_runtime_csv(
     rustc_id: &str,
     data: &RuntimeResultVec,
 ) -> anyhow::Result<()> {
-    if data.0.len() <= 0 {
+    if data.results.len() <= 0 {
         eprintln!("{} has empty data set, will be skipped.", out.display());
         return Ok(());
     }
     create_dir_all(out)?;
 
-    let results = &data.0;
+    let results = &data.results;
 
     let header: Vec<String> = results.iter().map(|r| r.name.clone()).collect();
 
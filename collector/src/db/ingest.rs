@@ -0,0 +1,202 @@
+use std::{fs, path::Path, time::UNIX_EPOCH};
+
+use anyhow::{bail, Context};
+use rusqlite::params;
+
+use crate::{
+    benchmark::{profile::Profile, scenario::Scenario},
+    execute::Stats,
+    statistics::{
+        compile_time_stat::{CompileTimeBenchResult, CompileTimeResultSet},
+        metadata::Metadata,
+        runtime_stat::RuntimeResultVec,
+    },
+};
+
+use super::{Database, ResultKind};
+
+/// Profile and scenario runtime benchmarks are stored under, they are always
+/// built from scratch in release mode.
+const RUNTIME_PROFILE: Profile = Profile::Release;
+const RUNTIME_SCENARIO: Scenario = Scenario::Full;
+
+/// A result file of the collector.
+enum ResultFile {
+    CompileTime(CompileTimeResultSet),
+    Runtime(RuntimeResultVec),
+    BinarySize(Vec<CompileTimeBenchResult>),
+}
+
+/// The stats of one measured iteration.
+struct Row<'a> {
+    benchmark: &'a str,
+    profile: Profile,
+    /// Empty for the build without a build config.
+    build_config: &'a str,
+    scenario: Scenario,
+    patch: &'a str,
+    iteration: usize,
+    stats: &'a Stats,
+}
+
+impl ResultFile {
+    fn read(file: &Path) -> anyhow::Result<Self> {
+        let content =
+            fs::read_to_string(file).with_context(|| format!("failed to read {:?}", file))?;
+        if let Ok(set) = serde_json::from_str(&content) {
+            return Ok(ResultFile::CompileTime(set));
+        }
+        if let Ok(results) = serde_json::from_str(&content) {
+            return Ok(ResultFile::BinarySize(results));
+        }
+        serde_json::from_str(&content)
+            .map(ResultFile::Runtime)
+            .with_context(|| format!("{:?} is not a result file of the collector", file))
+    }
+
+    fn kind(&self) -> ResultKind {
+        match self {
+            ResultFile::CompileTime(_) => ResultKind::CompileTime,
+            ResultFile::Runtime(_) => ResultKind::Runtime,
+            ResultFile::BinarySize(_) => ResultKind::BinarySize,
+        }
+    }
+
+    /// The id of the results, or else the name of their toolchain.
+    fn id(&self) -> Option<&str> {
+        match self {
+            ResultFile::CompileTime(set) if !set.id.is_empty() => Some(&set.id),
+            _ => self
+                .metadata()
+                .map(|m| m.toolchain.name())
+                .filter(|name| !name.is_empty()),
+        }
+    }
+
+    fn metadata(&self) -> Option<&Metadata> {
+        match self {
+            ResultFile::CompileTime(set) => set.metadata.as_ref(),
+            ResultFile::Runtime(results) => results.metadata.as_ref(),
+            ResultFile::BinarySize(_) => None,
+        }
+    }
+
+    /// Stats of the measured iterations, without warm-up runs.
+    fn rows(&self) -> Vec<Row<'_>> {
+        match self {
+            ResultFile::CompileTime(set) => compile_time_rows(&set.results),
+            ResultFile::BinarySize(results) => compile_time_rows(results),
+            ResultFile::Runtime(results) => results
+                .results
+                .iter()
+                .flat_map(|result| {
+                    result.stats.iter().enumerate().map(|(i, stats)| Row {
+                        benchmark: &result.name,
+                        profile: RUNTIME_PROFILE,
+                        build_config: result.build_config.as_deref().unwrap_or_default(),
+                        scenario: RUNTIME_SCENARIO,
+                        patch: "",
+                        iteration: i + 1,
+                        stats,
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+fn compile_time_rows(results: &[CompileTimeBenchResult]) -> Vec<Row<'_>> {
+    results
+        .iter()
+        .flat_map(|result| {
+            result.measured().map(|r| Row {
+                benchmark: &result.benchmark,
+                profile: r.profile,
+                build_config: r.build_config.as_deref().unwrap_or_default(),
+                scenario: r.scenario,
+                patch: r.patch.as_deref().unwrap_or_default(),
+                iteration: r.iteration,
+                stats: &r.stats,
+            })
+        })
+        .collect()
+}
+
+impl Database {
+    /// Add the results of `file`, a `compile_time_results.json`,
+    /// `runtime_results.json` or `compiled_binary_size.json`, under the
+    /// toolchain `id`, or the id or toolchain recorded in the file. Returns
+    /// the kind of the file and the number of values added.
+    ///
+    /// The run is dated by the start time in its metadata, or else by the
+    /// modification time of the file, so ingesting a file again replaces
+    /// its values. Timed-out iterations record no values.
+    pub fn ingest(&mut self, file: &Path, id: Option<&str>) -> anyhow::Result<(ResultKind, usize)> {
+        let results = ResultFile::read(file)?;
+        let toolchain = match id.or(results.id()) {
+            Some(toolchain) => toolchain,
+            None => bail!("{:?} records no toolchain id, pass it with `--id`", file),
+        };
+        let timestamp = match results.metadata().filter(|m| m.start_time > 0) {
+            Some(metadata) => metadata.start_time,
+            None => fs::metadata(file)?
+                .modified()?
+                .duration_since(UNIX_EPOCH)?
+                .as_secs(),
+        };
+
+        let count = self
+            .insert(toolchain, timestamp, &results)
+            .with_context(|| format!("failed to add {:?} to the database", file))?;
+        Ok((results.kind(), count))
+    }
+
+    fn insert(
+        &mut self,
+        toolchain: &str,
+        timestamp: u64,
+        results: &ResultFile,
+    ) -> anyhow::Result<usize> {
+        let kind = results.kind().to_string();
+        let timestamp = timestamp as i64;
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO run (toolchain, kind, timestamp, metadata)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                toolchain,
+                kind,
+                timestamp,
+                results.metadata().map(serde_json::to_string).transpose()?
+            ],
+        )?;
+
+        let mut count = 0;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO measurement (toolchain, kind, timestamp, benchmark,
+                     profile, build_config, scenario, patch, iteration, metric, value)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?;
+            for row in results.rows() {
+                for (metric, value) in row.stats.stats.iter() {
+                    count += insert.execute(params![
+                        toolchain,
+                        kind,
+                        timestamp,
+                        row.benchmark,
+                        row.profile.to_string(),
+                        row.build_config,
+                        row.scenario.to_string(),
+                        row.patch,
+                        row.iteration as i64,
+                        metric,
+                        value
+                    ])?;
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+}
@@ -0,0 +1,326 @@
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use anyhow::Context;
+use rusqlite::Connection;
+
+use crate::benchmark::{profile::Profile, scenario::Scenario};
+
+mod ingest;
+mod query;
+
+pub use query::ExportFormat;
+
+/// Tables of the results database. Every measured value of every iteration
+/// is a row of `measurement`, keyed by the toolchain, the kind of result,
+/// the start of the run, the benchmark configuration and the metric. The
+/// build config is empty for the build without one. The metadata of each
+/// run is kept in `run`.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS run (
+    toolchain TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    metadata TEXT,
+    PRIMARY KEY (toolchain, kind, timestamp)
+);
+CREATE TABLE IF NOT EXISTS measurement (
+    toolchain TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    benchmark TEXT NOT NULL,
+    profile TEXT NOT NULL,
+    build_config TEXT NOT NULL DEFAULT '',
+    scenario TEXT NOT NULL,
+    patch TEXT NOT NULL,
+    iteration INTEGER NOT NULL,
+    metric TEXT NOT NULL,
+    value REAL NOT NULL,
+    PRIMARY KEY (toolchain, kind, timestamp, benchmark, profile, build_config, scenario, patch,
+        iteration, metric)
+);
+CREATE INDEX IF NOT EXISTS measurement_metric ON measurement (metric, benchmark);
+";
+
+/// Move the values of a database created before build configs into a
+/// `measurement` table keyed by them, as the key of a table can not be
+/// altered in place.
+const ADD_BUILD_CONFIG: &str = "
+ALTER TABLE measurement RENAME TO measurement_old;
+DROP INDEX IF EXISTS measurement_metric;
+";
+
+const COPY_MEASUREMENTS: &str = "
+INSERT INTO measurement (toolchain, kind, timestamp, benchmark, profile, scenario, patch,
+    iteration, metric, value)
+SELECT toolchain, kind, timestamp, benchmark, profile, scenario, patch, iteration, metric, value
+FROM measurement_old;
+DROP TABLE measurement_old;
+";
+
+/// The result files a database is filled from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ResultKind {
+    /// `compile_time_results.json` of `bench_local`.
+    CompileTime,
+    /// `runtime_results.json` of `bench_runtime_local`.
+    Runtime,
+    /// `compiled_binary_size.json` of `binary_local`.
+    BinarySize,
+}
+
+impl ResultKind {
+    /// Name of the result file of this kind.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ResultKind::CompileTime => "compile_time_results.json",
+            ResultKind::Runtime => "runtime_results.json",
+            ResultKind::BinarySize => "compiled_binary_size.json",
+        }
+    }
+}
+
+impl FromStr for ResultKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compile_time" => Ok(Self::CompileTime),
+            "runtime" => Ok(Self::Runtime),
+            "binary_size" => Ok(Self::BinarySize),
+            _ => Err(format!("Unknown result kind {}", s)),
+        }
+    }
+}
+
+impl Display for ResultKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResultKind::CompileTime => f.write_str("compile_time"),
+            ResultKind::Runtime => f.write_str("runtime"),
+            ResultKind::BinarySize => f.write_str("binary_size"),
+        }
+    }
+}
+
+/// Selects rows of the database, every field that is set must match.
+#[derive(Debug, Default, Clone)]
+pub struct Slice {
+    pub toolchain: Option<String>,
+    pub kind: Option<ResultKind>,
+    pub benchmark: Option<String>,
+    pub profile: Option<Profile>,
+    /// Empty for the build without a build config.
+    pub build_config: Option<String>,
+    pub scenario: Option<Scenario>,
+    pub metric: Option<String>,
+}
+
+impl Slice {
+    /// `WHERE` clause of the slice and its parameters.
+    fn condition(&self) -> (String, Vec<String>) {
+        let mut clauses = vec![String::from("1")];
+        let mut params = vec![];
+        let mut add = |column: &str, value: Option<String>| {
+            if let Some(value) = value {
+                clauses.push(format!("{} = ?", column));
+                params.push(value);
+            }
+        };
+        add("toolchain", self.toolchain.clone());
+        add("kind", self.kind.map(|k| k.to_string()));
+        add("benchmark", self.benchmark.clone());
+        add("profile", self.profile.map(|p| p.to_string()));
+        add("build_config", self.build_config.clone());
+        add("scenario", self.scenario.map(|s| s.to_string()));
+        add("metric", self.metric.clone());
+        (clauses.join(" AND "), params)
+    }
+}
+
+/// A local SQLite database holding the results of many runs, so that they
+/// can be compared across toolchains without walking result directories.
+pub struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    /// Open the database at `path`, creating it if it does not exist.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut conn = Connection::open(path)
+            .with_context(|| format!("failed to open database {:?}", path))?;
+        let tx = conn.transaction()?;
+        let has_column = |column: &str| -> rusqlite::Result<bool> {
+            tx.query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('measurement') WHERE name = ?1",
+                [column],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|n| n > 0)
+        };
+        // A new database has no `measurement` table to move.
+        let outdated = has_column("value")? && !has_column("build_config")?;
+        if outdated {
+            tx.execute_batch(ADD_BUILD_CONFIG)?;
+        }
+        tx.execute_batch(SCHEMA)
+            .with_context(|| format!("failed to create tables in {:?}", path))?;
+        if outdated {
+            tx.execute_batch(COPY_MEASUREMENTS)
+                .with_context(|| format!("failed to add build configs to {:?}", path))?;
+        }
+        tx.commit()?;
+        Ok(Database { conn })
+    }
+}
+
+#[cfg(test)]
+mod test_db {
+    use std::{fs, path::Path};
+
+    use tempfile::TempDir;
+
+    use crate::{
+        benchmark::profile::Profile,
+        statistics::{compile_time_stat::CompileTimeResultSet, runtime_stat::RuntimeResultVec},
+    };
+
+    use super::{Database, ExportFormat, ResultKind, Slice};
+
+    /// Test for Database
+    ///
+    /// Step1. Ingest the result files in `test/db`, twice, and verify the
+    /// files without a toolchain id need `--id`.
+    ///
+    /// Step2. Query the history of `wall-time`.
+    ///
+    /// Step3. Export the compile-time and runtime results back to json and
+    /// compare them with the ingested files.
+    ///
+    /// Step4. Ingest results swept across two build configs and verify both
+    /// are kept apart and exported.
+    #[test]
+    fn test_db() {
+        let dir = TempDir::new().unwrap();
+        let mut db = Database::open(&dir.path().join("results.db")).unwrap();
+        let test_dir = Path::new("test/db");
+
+        for _ in 0..2 {
+            assert_eq!(
+                db.ingest(&test_dir.join("compile_time_results.json"), None)
+                    .unwrap(),
+                (ResultKind::CompileTime, 8)
+            );
+        }
+        assert!(db
+            .ingest(&test_dir.join("runtime_results.json"), None)
+            .is_err());
+        assert_eq!(
+            db.ingest(&test_dir.join("runtime_results.json"), Some("stable"))
+                .unwrap(),
+            (ResultKind::Runtime, 4)
+        );
+        assert_eq!(
+            db.ingest(&test_dir.join("compiled_binary_size.json"), Some("stable"))
+                .unwrap(),
+            (ResultKind::BinarySize, 1)
+        );
+
+        let history = db
+            .history(
+                "wall-time",
+                &Slice {
+                    profile: Some(Profile::Release),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].kind, "compile_time");
+        assert_eq!(history[0].timestamp, 1792324181);
+        assert_eq!(history[0].iterations, 2);
+        assert!((history[0].mean - 0.0701910755).abs() < 1e-9);
+        assert_eq!(history[1].kind, "runtime");
+        assert_eq!(history[1].min, 0.004942151);
+
+        let out_dir = dir.path().join("export");
+        let dirs = db
+            .export(
+                &Slice {
+                    kind: Some(ResultKind::CompileTime),
+                    ..Default::default()
+                },
+                ExportFormat::Json,
+                &out_dir,
+            )
+            .unwrap();
+        assert_eq!(dirs, vec![out_dir.join("compile_time").join("stable")]);
+        let read = |path: &Path| fs::read_to_string(path).unwrap();
+        let exported: CompileTimeResultSet =
+            serde_json::from_str(&read(&dirs[0].join("compile_time_results.json"))).unwrap();
+        let ingested: CompileTimeResultSet =
+            serde_json::from_str(&read(&test_dir.join("compile_time_results.json"))).unwrap();
+        assert_eq!(exported.id, "stable");
+        assert_eq!(exported.metadata, ingested.metadata);
+        assert_eq!(exported.results[0].iterations, 2);
+        assert_eq!(exported.results[0].result_vec.len(), 4);
+        assert_eq!(
+            exported.calculate_statistics().len(),
+            ingested.calculate_statistics().len()
+        );
+
+        let dirs = db
+            .export(
+                &Slice {
+                    kind: Some(ResultKind::Runtime),
+                    metric: Some("max-rss".to_string()),
+                    ..Default::default()
+                },
+                ExportFormat::Json,
+                &out_dir,
+            )
+            .unwrap();
+        let exported: RuntimeResultVec =
+            serde_json::from_str(&read(&dirs[0].join("runtime_results.json"))).unwrap();
+        assert_eq!(exported.results[0].name, "plus");
+        assert_eq!(exported.results[0].stats.len(), 2);
+        assert_eq!(exported.results[0].stats[1].stats["max-rss"], 13612.0);
+        assert!(exported.results[0].stats[1]
+            .stats
+            .get("wall-time")
+            .is_none());
+
+        assert_eq!(
+            db.ingest(&test_dir.join("compile_time_results_configs.json"), None)
+                .unwrap(),
+            (ResultKind::CompileTime, 8)
+        );
+        let configs = Slice {
+            toolchain: Some("configs".to_string()),
+            ..Default::default()
+        };
+        let history = db.history("wall-time", &configs).unwrap();
+        let build_configs: Vec<_> = history.iter().map(|e| e.build_config.as_str()).collect();
+        assert_eq!(build_configs, vec!["", "fat-lto"]);
+        assert!(history.iter().all(|e| e.iterations == 2));
+        assert!((history[1].mean - 0.915).abs() < 1e-9);
+        let history = db
+            .history(
+                "wall-time",
+                &Slice {
+                    build_config: Some("fat-lto".to_string()),
+                    ..configs.clone()
+                },
+            )
+            .unwrap();
+        assert_eq!(history.len(), 1);
+
+        let dirs = db.export(&configs, ExportFormat::Json, &out_dir).unwrap();
+        let exported: CompileTimeResultSet =
+            serde_json::from_str(&read(&dirs[0].join("compile_time_results.json"))).unwrap();
+        let statistics = exported.calculate_statistics();
+        assert_eq!(statistics.len(), 2);
+        assert!(statistics.iter().all(|s| s.iterations_used == 2));
+        assert!(statistics
+            .iter()
+            .any(|s| s.build_config.as_deref() == Some("fat-lto")));
+    }
+}
@@ -0,0 +1,323 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Context;
+use rusqlite::{params, params_from_iter, OptionalExtension};
+use serde::Serialize;
+
+use crate::{
+    benchmark::{
+        profile::{Profile, Profiles},
+        scenario::Scenario,
+    },
+    csv_transfer::{compile_time::create_compile_time_csv, runtime::create_runtime_csv},
+    execute::Stats,
+    statistics::{
+        compile_time_stat::{CompileTimeBenchResult, CompileTimeResult, CompileTimeResultSet},
+        metadata::Metadata,
+        runtime_stat::{RuntimeResult, RuntimeResultVec},
+    },
+    toolchain::ResultWriter,
+};
+
+use super::{Database, ResultKind, Slice};
+
+/// Summary of a metric of one benchmark configuration in one run.
+#[derive(Debug, Serialize)]
+pub struct HistoryEntry {
+    pub kind: String,
+    pub benchmark: String,
+    pub profile: String,
+    pub build_config: String,
+    pub scenario: String,
+    pub patch: String,
+    pub toolchain: String,
+    /// Start of the run in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// `timestamp` as UTC date and time.
+    pub date: String,
+    pub iterations: usize,
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(format!("Unknown export format {}", s)),
+        }
+    }
+}
+
+/// Benchmark, timestamp, profile, build config, scenario, patch and
+/// iteration of a row, ordered the way the rows are exported.
+type IterationKey = (String, i64, String, String, String, String, i64);
+
+/// The rows of one toolchain and kind of results.
+#[derive(Default)]
+struct Run {
+    stats: BTreeMap<IterationKey, Stats>,
+}
+
+impl Run {
+    fn timestamps(&self) -> Vec<i64> {
+        let mut timestamps: Vec<i64> = self.stats.keys().map(|k| k.1).collect();
+        timestamps.sort_unstable();
+        timestamps.dedup();
+        timestamps
+    }
+
+    /// Compile-time results of the run. Iterations of the same benchmark
+    /// configuration in several runs are numbered one after another.
+    fn compile_time_results(self) -> anyhow::Result<Vec<CompileTimeBenchResult>> {
+        let mut results: Vec<CompileTimeBenchResult> = vec![];
+        let mut counts: HashMap<(String, String, String, String, String), usize> = HashMap::new();
+        for ((benchmark, _, profile, build_config, scenario, patch, _), stats) in self.stats {
+            let iteration = counts
+                .entry((
+                    benchmark.clone(),
+                    profile.clone(),
+                    build_config.clone(),
+                    scenario.clone(),
+                    patch.clone(),
+                ))
+                .or_default();
+            *iteration += 1;
+
+            let mut result = CompileTimeResult::new(
+                benchmark.clone(),
+                *iteration,
+                Profile::from_str(&profile).map_err(anyhow::Error::msg)?,
+                Scenario::from_str(&scenario).map_err(anyhow::Error::msg)?,
+                stats,
+            );
+            if !patch.is_empty() {
+                result = result.with_patch(patch);
+            }
+            if !build_config.is_empty() {
+                result = result.with_build_config(Some(&build_config));
+            }
+
+            let iteration = *iteration;
+            match results.iter_mut().find(|r| r.benchmark == benchmark) {
+                Some(r) => {
+                    r.iterations = r.iterations.max(iteration);
+                    r.add_result(result);
+                }
+                None => {
+                    let mut r = CompileTimeBenchResult::new(benchmark, iteration);
+                    r.add_result(result);
+                    results.push(r);
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    fn runtime_results(self) -> Vec<RuntimeResult> {
+        let mut results: Vec<RuntimeResult> = vec![];
+        for ((benchmark, _, _, build_config, ..), stats) in self.stats {
+            let build_config = Some(build_config.as_str()).filter(|c| !c.is_empty());
+            match results
+                .iter_mut()
+                .find(|r| r.name == benchmark && r.build_config.as_deref() == build_config)
+            {
+                Some(r) => {
+                    r.append(stats);
+                }
+                None => {
+                    let mut r = RuntimeResult::new(benchmark).with_build_config(build_config);
+                    r.append(stats);
+                    results.push(r);
+                }
+            }
+        }
+        results
+    }
+}
+
+impl Database {
+    /// Summaries of `metric` for every benchmark configuration and run in
+    /// `slice`, oldest run first.
+    pub fn history(&self, metric: &str, slice: &Slice) -> anyhow::Result<Vec<HistoryEntry>> {
+        let slice = Slice {
+            metric: Some(metric.to_string()),
+            ..slice.clone()
+        };
+        let (condition, params) = slice.condition();
+        let mut query = self.conn.prepare(&format!(
+            "SELECT kind, benchmark, profile, build_config, scenario, patch, toolchain, timestamp,
+                 datetime(timestamp, 'unixepoch'), COUNT(value), AVG(value), MIN(value), MAX(value)
+             FROM measurement WHERE {}
+             GROUP BY kind, benchmark, profile, build_config, scenario, patch, toolchain, timestamp
+             ORDER BY kind, benchmark, profile, build_config, scenario, patch, timestamp,
+                 toolchain",
+            condition
+        ))?;
+        let entries = query
+            .query_map(params_from_iter(params), |row| {
+                Ok(HistoryEntry {
+                    kind: row.get(0)?,
+                    benchmark: row.get(1)?,
+                    profile: row.get(2)?,
+                    build_config: row.get(3)?,
+                    scenario: row.get(4)?,
+                    patch: row.get(5)?,
+                    toolchain: row.get(6)?,
+                    timestamp: row.get::<_, i64>(7)? as u64,
+                    date: row.get(8)?,
+                    iterations: row.get::<_, i64>(9)? as usize,
+                    mean: row.get(10)?,
+                    min: row.get(11)?,
+                    max: row.get(12)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    /// Write the results in `slice` to `out_dir` in the format the collector
+    /// wrote them in, to `<kind>/<toolchain>` directories like the ones of
+    /// `muti-rustc-perf`. Several runs of a toolchain are merged into one
+    /// result set. Returns the directories written to.
+    pub fn export(
+        &self,
+        slice: &Slice,
+        format: ExportFormat,
+        out_dir: &Path,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let mut runs: BTreeMap<(String, ResultKind), Run> = BTreeMap::new();
+        let (condition, params) = slice.condition();
+        let mut query = self.conn.prepare(&format!(
+            "SELECT toolchain, kind, timestamp, benchmark, profile, build_config, scenario, patch,
+                 iteration, metric, value
+             FROM measurement WHERE {}",
+            condition
+        ))?;
+        let mut rows = query.query(params_from_iter(params))?;
+        while let Some(row) = rows.next()? {
+            let kind: String = row.get(1)?;
+            let kind = ResultKind::from_str(&kind).map_err(anyhow::Error::msg)?;
+            let key = (
+                row.get(3)?,
+                row.get(2)?,
+                row.get(4)?,
+                row.get(5)?,
+                row.get(6)?,
+                row.get(7)?,
+                row.get(8)?,
+            );
+            runs.entry((row.get(0)?, kind))
+                .or_default()
+                .stats
+                .entry(key)
+                .or_default()
+                .stats
+                .insert(row.get(9)?, row.get(10)?);
+        }
+
+        let mut dirs = vec![];
+        for ((toolchain, kind), run) in runs {
+            let dir = out_dir.join(kind.to_string()).join(&toolchain);
+            // The metadata only describes the export of a single run.
+            let metadata = match run.timestamps()[..] {
+                [timestamp] => self.metadata(&toolchain, kind, timestamp)?,
+                _ => None,
+            };
+            self.write_run(&dir, &toolchain, kind, run, metadata, format)
+                .with_context(|| format!("failed to export {} results to {:?}", kind, dir))?;
+            dirs.push(dir);
+        }
+        Ok(dirs)
+    }
+
+    fn metadata(
+        &self,
+        toolchain: &str,
+        kind: ResultKind,
+        timestamp: i64,
+    ) -> anyhow::Result<Option<Metadata>> {
+        let metadata: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "SELECT metadata FROM run WHERE toolchain = ?1 AND kind = ?2 AND timestamp = ?3",
+                params![toolchain, kind.to_string(), timestamp],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(match metadata.flatten() {
+            Some(metadata) => Some(serde_json::from_str(&metadata)?),
+            None => None,
+        })
+    }
+
+    fn write_run(
+        &self,
+        dir: &Path,
+        toolchain: &str,
+        kind: ResultKind,
+        run: Run,
+        metadata: Option<Metadata>,
+        format: ExportFormat,
+    ) -> anyhow::Result<()> {
+        let writer = || ResultWriter::new(dir.to_path_buf(), PathBuf::from(kind.file_name()));
+        match kind {
+            ResultKind::CompileTime | ResultKind::BinarySize => {
+                let results = run.compile_time_results()?;
+                let mut profiles = Profiles { profiles: vec![] };
+                results
+                    .iter()
+                    .flat_map(|r| r.result_vec.iter())
+                    .for_each(|r| {
+                        if !profiles.profiles.contains(&r.profile) {
+                            profiles.profiles.push(r.profile);
+                        }
+                    });
+                let mut set = CompileTimeResultSet::new(toolchain.to_string(), results);
+                if let Some(metadata) = metadata {
+                    set = set.with_metadata(metadata);
+                }
+
+                match (format, kind) {
+                    (ExportFormat::Json, ResultKind::BinarySize) => {
+                        writer()?.write(serde_json::to_string(&set.results)?)?;
+                    }
+                    (ExportFormat::Json, _) => {
+                        writer()?.write(serde_json::to_string(&set)?)?;
+                    }
+                    (ExportFormat::Csv, _) => {
+                        create_compile_time_csv(&dir.to_path_buf(), toolchain, &set, &profiles)?
+                    }
+                }
+            }
+            ResultKind::Runtime => {
+                let results = RuntimeResultVec {
+                    results: run.runtime_results(),
+                    metadata,
+                };
+                match format {
+                    ExportFormat::Json => {
+                        writer()?.write(serde_json::to_string(&results)?)?;
+                    }
+                    ExportFormat::Csv => {
+                        create_runtime_csv(&dir.to_path_buf(), toolchain, &results)?
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
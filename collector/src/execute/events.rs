@@ -0,0 +1,151 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+/// Environment variable used to pass the comma-separated event list to
+/// `rustc-fake` and `runtime-fake`.
+pub const PERF_EVENTS_ENV: &str = "PERF_EVENTS";
+
+/// Events measured by `perf stat` when no event set is given.
+pub const DEFAULT_PERF_EVENTS: &str =
+    "instructions:u,cycles:u,task-clock,cpu-clock,faults,context-switches,branch-misses,cache-misses";
+
+/// Events active for less than this percentage of a run, because perf had
+/// to multiplex them, are left out as missing by default.
+pub const DEFAULT_MIN_COVERAGE: f64 = 50.;
+
+lazy_static::lazy_static! {
+    /// Symbolic events (`L1-dcache-load-misses:u`), raw PMU codes (`r01c2`)
+    /// and PMU events (`cpu/event=0x3c,umask=0x00/u`) as accepted by `perf -e`.
+    static ref EVENT_RE: regex::Regex = regex::Regex::new(
+        r"^(?:[A-Za-z][A-Za-z0-9_.\-]*|r[0-9A-Fa-f]+|[A-Za-z0-9_\-]+/[A-Za-z0-9_=,.\-]+/[ukhIGHpPSD]*)(?::[ukhIGHpPSD]+)?$"
+    )
+    .unwrap();
+}
+
+/// The `perf stat` events to record for every measured process.
+///
+/// Loaded from a json file like
+/// `{ "events": ["instructions:u", "LLC-load-misses", "r01c2"], "min_coverage": 50 }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventSet {
+    pub events: Vec<String>,
+    /// Minimum percentage of the run a multiplexed event must be counted,
+    /// events counted for less are left out as missing.
+    #[serde(default = "default_min_coverage")]
+    pub min_coverage: f64,
+}
+
+fn default_min_coverage() -> f64 {
+    DEFAULT_MIN_COVERAGE
+}
+
+impl Default for EventSet {
+    fn default() -> Self {
+        EventSet {
+            events: DEFAULT_PERF_EVENTS.split(',').map(String::from).collect(),
+            min_coverage: DEFAULT_MIN_COVERAGE,
+        }
+    }
+}
+
+impl EventSet {
+    /// Read and validate an event set. An empty path selects the default
+    /// event set.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        if path.as_os_str().is_empty() {
+            return Ok(Self::default());
+        }
+        let event_set: EventSet = serde_json::from_reader(BufReader::new(
+            File::open(path).with_context(|| format!("failed to open event set {:?}", path))?,
+        ))
+        .with_context(|| format!("failed to parse event set {:?}", path))?;
+        event_set
+            .validate()
+            .with_context(|| format!("invalid event set {:?}", path))?;
+        Ok(event_set)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.events.is_empty() {
+            bail!("event set is empty");
+        }
+        for (i, event) in self.events.iter().enumerate() {
+            if !EVENT_RE.is_match(event) {
+                bail!("`{}` is not a valid perf event", event);
+            }
+            if self.events[..i].contains(event) {
+                bail!("event `{}` is listed twice", event);
+            }
+        }
+        if !(0. ..=100.).contains(&self.min_coverage) {
+            bail!("min_coverage {} is not a percentage", self.min_coverage);
+        }
+        Ok(())
+    }
+
+    /// The argument of `perf stat -e`.
+    pub fn to_arg(&self) -> String {
+        self.events.join(",")
+    }
+}
+
+#[cfg(test)]
+mod test_events {
+    use std::path::PathBuf;
+
+    use super::{EventSet, DEFAULT_PERF_EVENTS};
+
+    #[test]
+    fn test_default_event_set() {
+        let events = EventSet::from_file(&PathBuf::new()).unwrap();
+        events.validate().unwrap();
+        assert_eq!(events.to_arg(), DEFAULT_PERF_EVENTS);
+    }
+
+    #[test]
+    fn test_validate_event_set() {
+        let valid = EventSet {
+            events: vec![
+                "instructions:u".to_string(),
+                "L1-dcache-load-misses".to_string(),
+                "LLC-load-misses:u".to_string(),
+                "stalled-cycles-frontend".to_string(),
+                "r01c2".to_string(),
+                "cpu/event=0x3c,umask=0x00/u".to_string(),
+            ],
+            min_coverage: 0.,
+        };
+        valid.validate().unwrap();
+        assert!(EventSet {
+            min_coverage: 120.,
+            ..valid
+        }
+        .validate()
+        .is_err());
+
+        for events in [
+            vec![],
+            vec!["instructions:u,cycles:u".to_string()],
+            vec!["cycles; rm -rf".to_string()],
+            vec!["cycles".to_string(), "cycles".to_string()],
+        ] {
+            assert!(EventSet {
+                events,
+                ..EventSet::default()
+            }
+            .validate()
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_event_set_file() {
+        let events = EventSet::from_file(&PathBuf::from("test/execute/event_set.json")).unwrap();
+        assert_eq!(events.events.len(), 4);
+        assert_eq!(events.min_coverage, 25.);
+        assert!(EventSet::from_file(&PathBuf::from("test/execute/missing.json")).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+/// Environment variable used to pass the CPU set, like `2-3,6`, that
+/// `rustc-fake` and `runtime-fake` pin the measured process to.
+pub const CPU_SET_ENV: &str = "COLLECTOR_CPU_SET";
+
+/// Parse a CPU list in the format of `/sys/devices/system/cpu/online` and
+/// `taskset -c`, e.g. `0-3,6`.
+pub fn parse_cpu_set(list: &str) -> anyhow::Result<Vec<usize>> {
+    let mut cpus = vec![];
+    for range in list.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+        let parse = |cpu: &str| {
+            cpu.trim()
+                .parse::<usize>()
+                .with_context(|| format!("invalid cpu {:?} in cpu set {:?}", cpu, list))
+        };
+        match range.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse(first)?, parse(last)?);
+                if first > last {
+                    bail!("invalid cpu range {:?} in cpu set {:?}", range, list);
+                }
+                cpus.extend(first..=last);
+            }
+            None => cpus.push(parse(range)?),
+        }
+    }
+    if cpus.is_empty() {
+        bail!("empty cpu set {:?}", list);
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    Ok(cpus)
+}
+
+/// Pin the current process, and the processes it spawns afterwards, to
+/// `cpus`.
+#[cfg(target_os = "linux")]
+pub fn pin_to_cpus(cpus: &[usize]) -> anyhow::Result<()> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for cpu in cpus {
+            libc::CPU_SET(*cpu, &mut set);
+        }
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error())
+                .with_context(|| format!("failed to pin process to cpus {:?}", cpus));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_to_cpus(cpus: &[usize]) -> anyhow::Result<()> {
+    bail!("pinning to cpus {:?} is only supported on linux", cpus)
+}
+
+/// Pin the current process to the CPU set passed by the collector, if any.
+/// Failures are reported but do not stop the measurement.
+pub fn pin_from_env() {
+    if let Ok(list) = std::env::var(CPU_SET_ENV) {
+        if let Err(e) = parse_cpu_set(&list).and_then(|cpus| pin_to_cpus(&cpus)) {
+            eprintln!("{:?}", e);
+        }
+    }
+}
+
+/// State of the machine relevant to the noise of measurements, read from
+/// `/sys/devices/system/cpu`. Fields are `None` where the kernel does not
+/// expose them, e.g. in most virtual machines.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HostConditions {
+    /// Distinct cpufreq governors of the online CPUs.
+    #[serde(default)]
+    pub governors: Vec<String>,
+    /// Whether the CPU may boost above its base frequency.
+    #[serde(default)]
+    pub turbo: Option<bool>,
+    /// Whether simultaneous multithreading is active.
+    #[serde(default)]
+    pub smt: Option<bool>,
+    /// CPUs the measured processes are pinned to, like `2-3`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_set: Option<String>,
+}
+
+impl HostConditions {
+    /// Read the conditions of this machine.
+    pub fn detect() -> Self {
+        Self::detect_in(Path::new("/sys/devices/system/cpu"))
+    }
+
+    /// Read the conditions from `cpu_dir`, a copy of `/sys/devices/system/cpu`.
+    pub fn detect_in(cpu_dir: &Path) -> Self {
+        let read = |path: PathBuf| fs::read_to_string(path).ok().map(|s| s.trim().to_string());
+
+        let mut governors: Vec<String> = fs::read_dir(cpu_dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with("cpu") && name[3..].parse::<usize>().is_ok()
+            })
+            .filter_map(|entry| read(entry.path().join("cpufreq/scaling_governor")))
+            .collect();
+        governors.sort();
+        governors.dedup();
+
+        // intel_pstate reports the inverse of the generic cpufreq knob.
+        let turbo = read(cpu_dir.join("intel_pstate/no_turbo"))
+            .map(|no_turbo| no_turbo == "0")
+            .or_else(|| read(cpu_dir.join("cpufreq/boost")).map(|boost| boost == "1"));
+        let smt = read(cpu_dir.join("smt/active")).map(|active| active == "1");
+
+        HostConditions {
+            governors,
+            turbo,
+            smt,
+            cpu_set: None,
+        }
+    }
+
+    pub fn with_cpu_set(mut self, cpu_set: Option<String>) -> Self {
+        self.cpu_set = cpu_set;
+        self
+    }
+
+    /// Why this machine is not ready for benchmarking. Conditions that could
+    /// not be read are not reported.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if self.governors.iter().any(|g| g != "performance") {
+            problems.push(format!(
+                "cpufreq governors are {:?}, expected \"performance\"",
+                self.governors
+            ));
+        }
+        if self.turbo == Some(true) {
+            problems.push(String::from("turbo boost is enabled"));
+        }
+        if self.smt == Some(true) {
+            problems.push(String::from("SMT is active"));
+        }
+        problems
+    }
+
+    /// Report the problems of the machine. In strict mode they refuse the
+    /// run.
+    pub fn check(&self, strict: bool) -> anyhow::Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            return Ok(());
+        }
+        if strict {
+            bail!(
+                "host is not ready for benchmarking:\n  {}",
+                problems.join("\n  ")
+            );
+        }
+        for problem in problems {
+            eprintln!("Warning: {}, results may be noisy.", problem);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_host {
+    use std::path::Path;
+
+    use super::{parse_cpu_set, HostConditions};
+
+    #[test]
+    fn test_parse_cpu_set() {
+        assert_eq!(parse_cpu_set("0-3,6").unwrap(), vec![0, 1, 2, 3, 6]);
+        assert_eq!(parse_cpu_set(" 2, 1,2 ").unwrap(), vec![1, 2]);
+        assert!(parse_cpu_set("").is_err());
+        assert!(parse_cpu_set("3-1").is_err());
+        assert!(parse_cpu_set("a").is_err());
+    }
+
+    /// `test/execute/host` holds the sysfs files of a machine set up for
+    /// benchmarking and of a noisy one.
+    #[test]
+    fn test_host_conditions() {
+        let ready = HostConditions::detect_in(Path::new("test/execute/host/ready"));
+        assert_eq!(ready.governors, vec!["performance"]);
+        assert_eq!(ready.turbo, Some(false));
+        assert_eq!(ready.smt, Some(false));
+        assert!(ready.check(true).is_ok());
+
+        let noisy = HostConditions::detect_in(Path::new("test/execute/host/noisy"));
+        assert_eq!(noisy.governors, vec!["performance", "powersave"]);
+        assert_eq!(noisy.turbo, Some(true));
+        assert_eq!(noisy.smt, Some(true));
+        assert_eq!(noisy.problems().len(), 3);
+        assert!(noisy.check(false).is_ok());
+        assert!(noisy.check(true).is_err());
+
+        let unknown = HostConditions::detect_in(Path::new("test/execute/host/missing"));
+        assert_eq!(unknown, HostConditions::default());
+        assert!(unknown.check(true).is_ok());
+    }
+}
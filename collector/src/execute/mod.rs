@@ -6,54 +6,76 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
-pub fn process_benchmark_output(output: Output) -> Result<Stats, DeserializeStatError> {
+pub mod events;
+pub mod host;
+#[cfg(unix)]
+pub mod rusage;
+pub mod self_profile;
+
+/// Parse the CSV output of `perf stat -x;`.
+///
+/// Events that were multiplexed with others are only counted for part of
+/// the run. Their counts are scaled up to the full run and the coverage is
+/// kept in `Stats::multiplexed`. Events covering less than `min_coverage`
+/// percent of the run are left out as missing, the other events are kept.
+pub fn process_benchmark_output(
+    output: Output,
+    min_coverage: f64,
+) -> Result<Stats, DeserializeStatError> {
     let stdout = String::from_utf8(output.stdout.clone()).expect("utf8 output");
     let mut stats = Stats::new();
-    let mut perf_output = false;
     for line in stdout.lines() {
-        // filter out cargo test output msg.
-        if perf_output == false {
-            if line.contains("instructions") {
-                perf_output = true;
-            } else {
-                continue;
-            }
-        }
-        if line.contains("ignored") {
-            continue;
-        }
         // github.com/torvalds/linux/blob/bc78d646e708/tools/perf/Documentation/perf-stat.txt#L281
-        macro_rules! get {
-            ($e: expr) => {
-                match $e {
-                    Some(s) => s,
-                    None => {
-                        log::warn!("unhandled line: {}", line);
-                        continue;
-                    }
-                }
-            };
+        let parts: Vec<&str> = line.split(';').map(|s| s.trim()).collect();
+        // Filter out anything that is not a perf record, e.g. cargo test
+        // output msg or the output of the measured program.
+        if parts.len() < 5
+            || parts[2].is_empty()
+            || !parts[0].starts_with(|c: char| c.is_ascii_digit() || c == '<')
+        {
+            continue;
         }
-        let mut parts = line.split(';').map(|s| s.trim());
-        let cnt = get!(parts.next());
-        let _unit = get!(parts.next());
-        let name = get!(parts.next());
-        let _time = get!(parts.next());
-        let pct = get!(parts.next());
-        if cnt == "<not supported>" || cnt.len() == 0 {
+        let cnt = parts[0];
+        let name = parts[2];
+        let pct = parts[4];
+        // `<not supported>` or `<not counted>`
+        if cnt.starts_with('<') {
+            log::warn!("event `{}` is {}", name, cnt);
+            stats.add_missing(name.to_owned());
             continue;
         }
-        if !pct.starts_with("100.") {
-            panic!(
-                "measurement of `{}` only active for {}% of the time",
-                name, pct
+        let cnt: f64 = cnt
+            .parse()
+            .map_err(|e| DeserializeStatError::ParseError(cnt.to_string(), e))?;
+        // Events without a percentage (e.g. software events on some
+        // kernels) were not multiplexed.
+        let coverage = if pct.is_empty() {
+            100.
+        } else {
+            pct.parse()
+                .map_err(|e| DeserializeStatError::ParseError(pct.to_string(), e))?
+        };
+        if coverage < 100. {
+            if coverage < min_coverage {
+                log::warn!(
+                    "event `{}` only active for {}% of the time, below the minimum of {}%",
+                    name,
+                    coverage,
+                    min_coverage
+                );
+                stats.add_missing(name.to_owned());
+                continue;
+            }
+            log::warn!(
+                "event `{}` only active for {}% of the time, scaling its count",
+                name,
+                coverage
             );
+            stats.multiplexed.insert(name.to_owned(), coverage);
+            stats.insert(name.to_owned(), cnt * 100. / coverage);
+        } else {
+            stats.insert(name.to_owned(), cnt);
         }
-        stats.insert(
-            name.to_owned(),
-            cnt.parse()
-                .map_err(|e| DeserializeStatError::ParseError(cnt.to_string(), e))?,
-        );
     }
 
     log::info!("{:?}", stdout.lines());
@@ -67,6 +89,18 @@ pub fn process_benchmark_output(output: Output) -> Result<Stats, DeserializeStat
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Stats {
     pub stats: HashMap<String, f64>,
+    /// Requested events that perf could not count on this machine, or only
+    /// counted for less than the minimum coverage.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+    /// Coverage in percent of the events that were multiplexed, their counts
+    /// in `stats` are already scaled to the full run.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub multiplexed: HashMap<String, f64>,
+    /// The measured process was killed after running into its timeout,
+    /// `stats` is empty then.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
 }
 
 impl Default for Stats {
@@ -79,9 +113,24 @@ impl Stats {
     pub fn new() -> Stats {
         Stats {
             stats: HashMap::new(),
+            missing: vec![],
+            multiplexed: HashMap::new(),
+            timed_out: false,
         }
     }
 
+    /// Placeholder for an iteration killed after running into its timeout.
+    pub fn timed_out() -> Stats {
+        Stats {
+            timed_out: true,
+            ..Stats::new()
+        }
+    }
+
+    pub fn is_multiplexed(&self) -> bool {
+        !self.multiplexed.is_empty()
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> + '_ {
         self.stats.iter().map(|(k, v)| (k.as_str(), *v))
     }
@@ -93,6 +142,18 @@ impl Stats {
     pub fn insert(&mut self, stat: String, value: f64) {
         self.stats.insert(stat, value);
     }
+
+    pub fn add_missing(&mut self, stat: String) {
+        if !self.missing.contains(&stat) {
+            self.missing.push(stat);
+        }
+    }
+
+    /// Keep the lowest coverage seen for `stat`.
+    pub fn add_multiplexed(&mut self, stat: String, coverage: f64) {
+        let entry = self.multiplexed.entry(stat).or_insert(coverage);
+        *entry = entry.min(coverage);
+    }
 }
 
 impl Add for Stats {
@@ -106,6 +167,15 @@ impl Add for Stats {
                 None => res.insert(label.clone(), left_val.clone()),
             }
         }
+        self.missing
+            .into_iter()
+            .chain(rhs.missing)
+            .for_each(|m| res.add_missing(m));
+        self.multiplexed
+            .into_iter()
+            .chain(rhs.multiplexed)
+            .for_each(|(m, c)| res.add_multiplexed(m, c));
+        res.timed_out = self.timed_out || rhs.timed_out;
         res
     }
 }
@@ -119,6 +189,11 @@ impl AddAssign for Stats {
                 None => self.insert(label.clone(), right_val.clone()),
             }
         }
+        rhs.missing.into_iter().for_each(|m| self.add_missing(m));
+        rhs.multiplexed
+            .into_iter()
+            .for_each(|(m, c)| self.add_multiplexed(m, c));
+        self.timed_out |= rhs.timed_out;
     }
 }
 
@@ -131,3 +206,68 @@ pub enum DeserializeStatError {
     #[error("could not process xperf data")]
     XperfError(#[from] anyhow::Error),
 }
+
+#[cfg(all(test, unix))]
+mod test_process_output {
+    use std::{os::unix::process::ExitStatusExt, process::Output};
+
+    use super::{events::DEFAULT_MIN_COVERAGE, process_benchmark_output};
+
+    fn output(stdout: &str) -> Output {
+        Output {
+            status: ExitStatusExt::from_raw(0),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: vec![],
+        }
+    }
+
+    /// Events requested through an event set are carried over, program
+    /// output is ignored and unsupported events are reported as missing.
+    #[test]
+    fn test_process_benchmark_output() {
+        let stats = process_benchmark_output(
+            output(
+                "running 1 test\n\
+             test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out\n\
+             1234;;LLC-load-misses:u;1000;100.00;;\n\
+             <not supported>;;stalled-cycles-frontend;0;100.00;;\n\
+             42;;r01c2;1000;100.00;;\n\
+             0.500000000;;wall-time;4;100.00\n",
+            ),
+            DEFAULT_MIN_COVERAGE,
+        )
+        .unwrap();
+
+        assert_eq!(stats.stats.len(), 3);
+        assert_eq!(stats.stats["LLC-load-misses:u"], 1234.);
+        assert_eq!(stats.stats["r01c2"], 42.);
+        assert_eq!(stats.stats["wall-time"], 0.5);
+        assert_eq!(stats.missing, vec!["stalled-cycles-frontend".to_string()]);
+        assert!(!stats.is_multiplexed());
+    }
+
+    /// Counts of multiplexed events are scaled to the full run, events
+    /// below the coverage threshold are missing while the others are kept.
+    #[test]
+    fn test_process_multiplexed_output() {
+        let out = "1000;;instructions:u;1000;100.00;;\n\
+                   250;;LLC-load-misses:u;250;25.00;;\n";
+
+        let stats = process_benchmark_output(output(out), 20.).unwrap();
+        assert_eq!(stats.stats["instructions:u"], 1000.);
+        assert_eq!(stats.stats["LLC-load-misses:u"], 1000.);
+        assert!(stats.is_multiplexed());
+        assert_eq!(stats.multiplexed["LLC-load-misses:u"], 25.);
+
+        let stats = process_benchmark_output(output(out), 50.).unwrap();
+        assert_eq!(stats.stats.len(), 1);
+        assert_eq!(stats.stats["instructions:u"], 1000.);
+        assert_eq!(stats.missing, vec!["LLC-load-misses:u".to_string()]);
+        assert!(!stats.is_multiplexed());
+    }
+
+    #[test]
+    fn test_process_empty_output() {
+        assert!(process_benchmark_output(output("Hello, world!\n"), DEFAULT_MIN_COVERAGE).is_err());
+    }
+}
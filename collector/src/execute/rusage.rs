@@ -0,0 +1,140 @@
+use std::{
+    fs, io, mem,
+    os::unix::process::ExitStatusExt,
+    process::{Command, ExitStatus},
+    time::{Duration, Instant},
+};
+
+/// Resource usage of a finished process and all the children it waited for.
+pub struct Usage {
+    pub wall_time: Duration,
+    pub rusage: libc::rusage,
+    /// `read_bytes` and `write_bytes` of `/proc/<pid>/io`, if readable.
+    pub io_bytes: Option<(u64, u64)>,
+}
+
+/// Run `cmd` to completion and collect its resource usage without perf.
+pub fn run(cmd: &mut Command) -> io::Result<(ExitStatus, Usage)> {
+    let start = Instant::now();
+    let child = cmd.spawn()?;
+    let pid = child.id() as libc::pid_t;
+
+    // Wait without reaping first: the I/O accounting of the process tree is
+    // only readable from /proc until the child is reaped.
+    retry_on_intr(|| unsafe {
+        let mut info: libc::siginfo_t = mem::zeroed();
+        libc::waitid(
+            libc::P_PID,
+            pid as libc::id_t,
+            &mut info,
+            libc::WEXITED | libc::WNOWAIT,
+        )
+    })?;
+    let wall_time = start.elapsed();
+    let io_bytes = fs::read_to_string(format!("/proc/{}/io", pid))
+        .ok()
+        .and_then(|s| parse_proc_io(&s));
+
+    let mut status = 0;
+    let mut rusage: libc::rusage = unsafe { mem::zeroed() };
+    retry_on_intr(|| unsafe { libc::wait4(pid, &mut status, 0, &mut rusage) })?;
+
+    Ok((
+        ExitStatus::from_raw(status),
+        Usage {
+            wall_time,
+            rusage,
+            io_bytes,
+        },
+    ))
+}
+
+fn retry_on_intr(mut f: impl FnMut() -> libc::c_int) -> io::Result<()> {
+    loop {
+        if f() >= 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+fn parse_proc_io(content: &str) -> Option<(u64, u64)> {
+    let field = |name: &str| {
+        content
+            .lines()
+            .find_map(|l| l.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|v| v.trim().parse().ok())
+    };
+    Some((field("read_bytes")?, field("write_bytes")?))
+}
+
+fn seconds(tv: libc::timeval) -> f64 {
+    tv.tv_sec as f64 + tv.tv_usec as f64 / 1e6
+}
+
+impl Usage {
+    /// The usage formatted like the CSV output of `perf stat`, so that it can
+    /// be parsed by `process_benchmark_output`.
+    pub fn to_perf_lines(&self) -> Vec<String> {
+        let r = &self.rusage;
+        let mut values = vec![
+            ("wall-time", self.wall_time.as_secs_f64()),
+            ("user-time", seconds(r.ru_utime)),
+            ("system-time", seconds(r.ru_stime)),
+            ("max-rss", r.ru_maxrss as f64),
+            ("minor-faults", r.ru_minflt as f64),
+            ("major-faults", r.ru_majflt as f64),
+            ("voluntary-context-switches", r.ru_nvcsw as f64),
+            ("involuntary-context-switches", r.ru_nivcsw as f64),
+        ];
+        if let Some((read, write)) = self.io_bytes {
+            values.push(("io-read-bytes", read as f64));
+            values.push(("io-write-bytes", write as f64));
+        }
+        values
+            .into_iter()
+            .map(|(name, value)| format!("{};;{};0;100.00", value, name))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_rusage {
+    use std::process::Command;
+
+    use crate::execute::{events::DEFAULT_MIN_COVERAGE, process_benchmark_output};
+
+    use super::{parse_proc_io, run};
+
+    #[test]
+    fn test_parse_proc_io() {
+        let content = "rchar: 1948\nwchar: 0\nsyscr: 7\nsyscw: 0\n\
+                       read_bytes: 4096\nwrite_bytes: 8192\ncancelled_write_bytes: 0\n";
+        assert_eq!(parse_proc_io(content), Some((4096, 8192)));
+        assert_eq!(parse_proc_io("rchar: 1948\n"), None);
+    }
+
+    /// The usage of a short process parses as perf stat output.
+    #[test]
+    fn test_run() {
+        let (status, usage) = run(Command::new("sh").arg("-c").arg("exit 3")).unwrap();
+        assert_eq!(status.code(), Some(3));
+
+        let stdout = usage.to_perf_lines().join("\n");
+        let stats = process_benchmark_output(
+            std::process::Output {
+                status,
+                stdout: stdout.into_bytes(),
+                stderr: vec![],
+            },
+            DEFAULT_MIN_COVERAGE,
+        )
+        .unwrap();
+        assert!(stats.stats["wall-time"] > 0.);
+        assert!(stats.stats["max-rss"] > 0.);
+        assert!(stats.stats.contains_key("voluntary-context-switches"));
+    }
+}
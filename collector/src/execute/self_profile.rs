@@ -0,0 +1,118 @@
+use std::ops::AddAssign;
+
+use serde::{Deserialize, Serialize};
+
+/// Marker `rustc-fake` prints before the json summary of a self-profile.
+pub const SELF_PROFILE_OUTPUT: &str = "!self-profile-output:";
+
+/// Per-query data of one `rustc -Zself-profile` run, as summarized by
+/// `summarize summarize --json` from measureme.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SelfProfile {
+    pub query_data: Vec<QueryData>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QueryData {
+    pub label: String,
+    /// Time spent in the query itself, without nested queries, in seconds.
+    #[serde(deserialize_with = "de_secs")]
+    pub self_time: f64,
+    pub invocation_count: u32,
+    pub number_of_cache_hits: u32,
+    /// Time spent waiting for the query in other threads, in seconds.
+    #[serde(deserialize_with = "de_secs")]
+    pub blocked_time: f64,
+    /// Time spent loading the query result from the incremental cache, in
+    /// seconds.
+    #[serde(deserialize_with = "de_secs")]
+    pub incremental_load_time: f64,
+}
+
+/// Accepts both the `{"secs": 1, "nanos": 500}` durations written by
+/// `summarize` and the plain seconds this crate writes back.
+fn de_secs<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Secs {
+        Duration { secs: u64, nanos: u32 },
+        Secs(f64),
+    }
+    Ok(match Secs::deserialize(deserializer)? {
+        Secs::Duration { secs, nanos } => secs as f64 + nanos as f64 / 1e9,
+        Secs::Secs(secs) => secs,
+    })
+}
+
+impl SelfProfile {
+    /// Find the self-profile summary printed by `rustc-fake`, if any.
+    pub fn from_output(stdout: &str) -> anyhow::Result<Option<Self>> {
+        match stdout
+            .lines()
+            .find_map(|l| l.strip_prefix(SELF_PROFILE_OUTPUT))
+        {
+            Some(json) => Ok(Some(serde_json::from_str(json)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn query(&self, label: &str) -> Option<&QueryData> {
+        self.query_data.iter().find(|q| q.label == label)
+    }
+}
+
+/// Merge the profiles of several rustc invocations, e.g. of all packages of
+/// a workspace.
+impl AddAssign for SelfProfile {
+    fn add_assign(&mut self, rhs: Self) {
+        for query in rhs.query_data {
+            match self.query_data.iter_mut().find(|q| q.label == query.label) {
+                Some(q) => {
+                    q.self_time += query.self_time;
+                    q.invocation_count += query.invocation_count;
+                    q.number_of_cache_hits += query.number_of_cache_hits;
+                    q.blocked_time += query.blocked_time;
+                    q.incremental_load_time += query.incremental_load_time;
+                }
+                None => self.query_data.push(query),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_self_profile {
+    use super::SelfProfile;
+
+    const SUMMARY: &str = r#"!self-profile-output:{"query_data":[{"label":"typeck","time":{"secs":0,"nanos":3000000},"self_time":{"secs":0,"nanos":2500000},"number_of_cache_misses":4,"number_of_cache_hits":1,"invocation_count":5,"blocked_time":{"secs":0,"nanos":0},"incremental_load_time":{"secs":0,"nanos":500000},"incremental_hashing_time":{"secs":0,"nanos":0}},{"label":"LLVM_passes","time":{"secs":1,"nanos":0},"self_time":{"secs":1,"nanos":0},"number_of_cache_misses":0,"number_of_cache_hits":0,"invocation_count":1,"blocked_time":{"secs":0,"nanos":0},"incremental_load_time":{"secs":0,"nanos":0},"incremental_hashing_time":{"secs":0,"nanos":0}}],"artifact_sizes":[],"total_time":{"secs":1,"nanos":3000000}}"#;
+
+    #[test]
+    fn test_parse_self_profile() {
+        let stdout = format!("1000;;instructions:u;1000;100.00;;\n{}\n", SUMMARY);
+        let profile = SelfProfile::from_output(&stdout).unwrap().unwrap();
+
+        assert_eq!(profile.query_data.len(), 2);
+        let typeck = profile.query("typeck").unwrap();
+        assert_eq!(typeck.self_time, 0.0025);
+        assert_eq!(typeck.invocation_count, 5);
+        assert_eq!(typeck.number_of_cache_hits, 1);
+        assert_eq!(typeck.incremental_load_time, 0.0005);
+
+        // Results written by the collector read back the same.
+        let json = serde_json::to_string(&profile).unwrap();
+        assert_eq!(serde_json::from_str::<SelfProfile>(&json).unwrap(), profile);
+
+        assert_eq!(SelfProfile::from_output("Hello, world!\n").unwrap(), None);
+        assert!(SelfProfile::from_output("!self-profile-output:{").is_err());
+    }
+
+    #[test]
+    fn test_merge_self_profile() {
+        let mut profile = SelfProfile::from_output(SUMMARY).unwrap().unwrap();
+        profile += profile.clone();
+
+        assert_eq!(profile.query_data.len(), 2);
+        assert_eq!(profile.query("typeck").unwrap().invocation_count, 10);
+        assert_eq!(profile.query("LLVM_passes").unwrap().self_time, 2.);
+    }
+}
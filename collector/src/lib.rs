@@ -1,6 +1,7 @@
 pub mod benchmark;
 mod compile_time;
 mod csv_transfer;
+pub mod db;
 pub mod execute;
 pub mod mir_analyze;
 mod morpheme_miner;
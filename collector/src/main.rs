@@ -1,12 +1,11 @@
 use std::{
-    env::current_dir,
-    fs::create_dir_all,
-    path::PathBuf,
+    collections::HashMap,
+    fs::{self, create_dir_all},
+    path::{Path, PathBuf},
     process::{self, Command},
 };
 
 use anyhow::{Context, Ok};
-use benchmark::scenario::Scenario;
 use clap::Parser;
 use compile_time::{
     bench_compile_time,
@@ -14,21 +13,34 @@ use compile_time::{
         compare::compare_binary_size,
         plotter::{plot, plot_compare},
     },
-    discover_benchmark_suit,
+    CompileTimeRun,
 };
 use mir_analyze::mir_generate::generate_mir;
-use runtime::bench_runtime;
-use toolchain::{Cli, Commands, ResultWriter};
+use runtime::{bench_runtime, RuntimeRun};
+use toolchain::{Cli, Commands, DbCommands, InterleaveOptions, ResultWriter};
 
 use crate::{
-    benchmark::benchmark::BenchmarkSuit, compile_time::binary_size::bench_binary_size,
-    csv_transfer::sheduler, morpheme_miner::run_miners, perf_analyze::perf_analyzer,
-    statistics::compile_time_stat::CompileTimeResultSet, toolchain::get_local_toolchain,
+    benchmark::{filter::BenchmarkFilter, suit::BenchmarkSuit},
+    bisect::{BisectReport, BisectTarget},
+    compile_time::binary_size::bench_binary_size,
+    csv_transfer::sheduler,
+    db::Database,
+    execute::events::EventSet,
+    morpheme_miner::run_miners,
+    perf_analyze::perf_analyzer,
+    statistics::{
+        compile_time_stat::CompileTimeResultSet, metadata::Metadata, paired::PairedComparison,
+        summary::Summary,
+    },
+    toolchain::get_local_toolchain,
+    utils::rng::SplitMix64,
 };
 
 mod benchmark;
+mod bisect;
 mod compile_time;
 mod csv_transfer;
+mod db;
 mod execute;
 mod mir_analyze;
 mod morpheme_miner;
@@ -58,149 +70,186 @@ fn main_result() -> anyhow::Result<i32> {
     match args.command {
         Commands::BenchLocal {
             local,
+            interleave,
             iterations,
+            sampling,
+            host,
+            warmup,
             perf_tool,
-            event_filter_file,
+            event_set_file,
             bench_dir,
             profiles,
+            scenarios,
+            build_configs,
             out_dir,
             flamegraph,
+            timeout,
+            resume,
         } => {
-            perf_check();
+            if perf_tool.get_bencher().needs_perf() {
+                perf_check()?;
+            }
             if flamegraph > 0 {
-                flamegraph_check();
+                flamegraph_check()?;
             }
+            let events = EventSet::from_file(&event_set_file)?;
+            let filter = local.filter()?;
+            let host = host.prepare()?;
 
             println!("profiles: {:?}", profiles.profiles);
-            let default_scenarios = vec![Scenario::Full];
-            let toolch = get_local_toolchain(
-                &local.rustc,
-                local.cargo.as_deref(),
-                local.id.as_deref(),
-                "",
-            )?;
-
-            println!("{}", toolch);
+            println!("scenarios: {:?}", scenarios.scenarios);
+            println!("build configs: {:?}", build_configs.names);
+            let toolchains = interleave.toolchains(&local)?;
+            let rng = &mut interleaved_rng(&toolchains, interleave.seed());
 
             create_output_dir(&out_dir)?;
+            let mut runs = vec![];
+            let mut writers = vec![];
+            for (id, toolch) in &toolchains {
+                println!("{}", toolch);
+                let dir = toolchain_dir(&out_dir, id, toolchains.len());
+                runs.push(CompileTimeRun::new(
+                    toolch,
+                    id.clone(),
+                    dir.clone(),
+                    resume,
+                )?);
+                writers.push((
+                    Metadata::start(&toolch.rustc, &toolch.cargo, host.clone())
+                        .with_toolchain(toolch.identity.clone()),
+                    result_writer(&dir, "compile_time_results.json", "results")?,
+                    result_writer(&dir, "compile_time_statistics.json", "statistics")?,
+                ));
+            }
 
-            let flame_graph_dir = out_dir.join("flamegraphs");
-            create_output_dir(&flame_graph_dir)?;
-
-            let cwd = current_dir()?;
-            let flame_graph_path = cwd.join(flame_graph_dir);
-            log::debug!("{:?}", flame_graph_path);
-
-            let mut result_writer =
-                ResultWriter::new(out_dir.clone(), PathBuf::from("compile_time_results.json"))
-                    .with_context(|| {
-                        format!(
-                            "Fail to open {} to record results!",
-                            out_dir.to_str().unwrap_or("?")
-                        )
-                    })?;
-
-            let mut statistics_writer = ResultWriter::new(
-                out_dir.clone(),
-                PathBuf::from("compile_time_statistics.json"),
-            )
-            .with_context(|| {
-                format!(
-                    "Fail to open {} to record statistics!",
-                    out_dir.to_str().unwrap_or("?")
-                )
-            })?;
-
-            let results = bench_compile_time(
-                &toolch,
+            bench_compile_time(
+                &mut runs,
                 &perf_tool,
-                &event_filter_file,
+                &events,
                 &profiles.profiles,
-                &default_scenarios,
+                &build_configs.names,
+                &scenarios.scenarios,
                 bench_dir,
-                iterations,
-                flame_graph_path.as_path().as_ref(),
+                &filter,
+                sampling.iterations(iterations),
                 flamegraph,
-                out_dir.as_path(),
+                timeout,
+                sampling.stop_rule().as_ref(),
+                warmup,
+                rng,
             )?;
 
             match perf_tool.get_bencher() {
-                toolchain::Bencher::PerfStat => {
-                    let result_set =
-                        CompileTimeResultSet::new(local.id.unwrap_or_default(), results);
-                    result_writer.write(serde_json::to_string(&result_set)?)?;
-
-                    let result_statistics = result_set.calculate_statistics();
-                    statistics_writer.write(serde_json::to_string(&result_statistics)?)?;
+                toolchain::Bencher::PerfStat
+                | toolchain::Bencher::PerfStatSelfProfile
+                | toolchain::Bencher::Rusage => {
+                    for (run, (metadata, mut result_writer, mut statistics_writer)) in
+                        runs.iter().zip(writers)
+                    {
+                        let result_set =
+                            CompileTimeResultSet::new(run.id.clone(), run.journal.results())
+                                .with_metadata(metadata.finish());
+                        result_writer.write(serde_json::to_string(&result_set)?)?;
+
+                        let result_statistics = result_set.calculate_statistics();
+                        statistics_writer.write(serde_json::to_string(&result_statistics)?)?;
+                    }
+
+                    if runs.len() > 1 {
+                        let comparisons = PairedComparison::compile_time(
+                            &runs
+                                .iter()
+                                .map(|run| (run.id.clone(), run.journal.results()))
+                                .collect::<Vec<_>>(),
+                            interleave.test,
+                        );
+                        write_paired_comparison(&out_dir, &comparisons)?;
+                    }
                 }
-                toolchain::Bencher::PerfRecord => (),
+                _ => (),
             }
 
             Ok(0)
         }
         Commands::BenchRuntimeLocal {
             local,
+            interleave,
             iterations,
+            sampling,
+            host,
+            warmup,
             perf_tool,
-            event_filter_file,
+            event_set_file,
+            build_configs,
             bench_dir,
             out_dir,
             flamegraph,
+            timeout,
         } => {
-            perf_check();
+            if !perf_tool.get_bencher().supports_runtime() {
+                anyhow::bail!(
+                    "{} can not be used for runtime benchmarks",
+                    perf_tool.name()
+                );
+            }
+            if perf_tool.get_bencher().needs_perf() {
+                perf_check()?;
+            }
             if flamegraph > 0 {
-                flamegraph_check();
+                flamegraph_check()?;
             }
+            let events = EventSet::from_file(&event_set_file)?;
+            let filter = local.filter()?;
+            let host = host.prepare()?;
 
-            let ltc = get_local_toolchain(
-                &local.rustc,
-                local.cargo.as_deref(),
-                local.id.as_deref(),
-                "",
-            )?;
+            let toolchains = interleave.toolchains(&local)?;
+            let rng = &mut interleaved_rng(&toolchains, interleave.seed());
 
             create_output_dir(&out_dir)?;
-
-            let flame_graph_dir = out_dir.join("flamegraphs");
-            create_output_dir(&flame_graph_dir)?;
-
-            let cwd = current_dir()?;
-            let flame_graph_path = cwd.join(flame_graph_dir);
-            log::debug!("{:?}", flame_graph_path);
-
-            let mut result_writer =
-                ResultWriter::new(out_dir.clone(), PathBuf::from("runtime_results.json"))
-                    .with_context(|| {
-                        format!(
-                            "Fail to open {} to record results!",
-                            out_dir.to_str().unwrap_or("?")
-                        )
-                    })?;
-
-            let mut statistics_writer =
-                ResultWriter::new(out_dir.clone(), PathBuf::from("runtime_statistics.json"))
-                    .with_context(|| {
-                        format!(
-                            "Fail to open {} to record statistics!",
-                            out_dir.to_str().unwrap_or("?")
-                        )
-                    })?;
+            let mut runs = vec![];
+            let mut writers = vec![];
+            for (id, ltc) in &toolchains {
+                let dir = toolchain_dir(&out_dir, id, toolchains.len());
+                runs.push(RuntimeRun::new(ltc, id.clone(), dir.clone())?);
+                writers.push((
+                    Metadata::start(&ltc.rustc, &ltc.cargo, host.clone())
+                        .with_toolchain(ltc.identity.clone()),
+                    result_writer(&dir, "runtime_results.json", "results")?,
+                    result_writer(&dir, "runtime_statistics.json", "statistics")?,
+                ));
+            }
 
             let results = bench_runtime(
-                &ltc,
+                &runs,
                 bench_dir,
-                iterations,
+                &filter,
+                &build_configs.names,
+                sampling.iterations(iterations as usize) as u32,
                 &perf_tool,
-                &event_filter_file,
-                &flame_graph_path,
+                &events,
                 flamegraph,
-                &out_dir,
+                timeout,
+                sampling.stop_rule().as_ref(),
+                warmup,
+                rng,
             )?;
 
-            result_writer.write(serde_json::to_string(&results)?)?;
+            let mut paired = vec![];
+            for ((run, mut results), (metadata, mut result_writer, mut statistics_writer)) in
+                runs.iter().zip(results).zip(writers)
+            {
+                results.metadata = Some(metadata.finish());
+                result_writer.write(serde_json::to_string(&results)?)?;
 
-            let statistics = results.calculate_statistics();
-            statistics_writer.write(serde_json::to_string(&statistics)?)?;
+                let statistics = results.calculate_statistics();
+                statistics_writer.write(serde_json::to_string(&statistics)?)?;
+                paired.push((run.id.clone(), results.results));
+            }
+
+            if paired.len() > 1 {
+                let comparisons = PairedComparison::runtime(&paired, interleave.test);
+                write_paired_comparison(&out_dir, &comparisons)?;
+            }
             Ok(0)
         }
         Commands::TransferCsvOutput {
@@ -240,11 +289,13 @@ fn main_result() -> anyhow::Result<i32> {
         Commands::BinaryLocal {
             local,
             profiles,
+            build_configs,
             bench_dir,
             out_dir,
         } => {
+            let filter = local.filter()?;
             let toolch = get_local_toolchain(
-                &local.rustc,
+                &local.rustc()?,
                 local.cargo.as_deref(),
                 local.id.as_deref(),
                 "",
@@ -260,7 +311,13 @@ fn main_result() -> anyhow::Result<i32> {
                         )
                     })?;
 
-            let results = bench_binary_size(&toolch, &profiles.profiles, bench_dir)?;
+            let results = bench_binary_size(
+                &toolch,
+                &profiles.profiles,
+                &build_configs.names,
+                bench_dir,
+                &filter,
+            )?;
 
             result_writer.write(serde_json::to_string(&results)?)?;
 
@@ -284,21 +341,190 @@ fn main_result() -> anyhow::Result<i32> {
                 Ok(0)
             }
         },
+        Commands::Summarize {
+            stats,
+            baseline,
+            bench_dir,
+            epsilon,
+            out_path,
+        } => {
+            let weights = match bench_dir {
+                Some(dir) => BenchmarkSuit::load(&dir, &BenchmarkFilter::new(None, None, None)?)?
+                    .benchmarks
+                    .into_iter()
+                    .map(|b| (b.name, b.config.weight))
+                    .collect(),
+                None => HashMap::new(),
+            };
+            let summary = Summary::from_files(&stats, &baseline, &weights, epsilon)?;
+            print!("{}", summary);
+            fs::write(&out_path, serde_json::to_string(&summary)?)
+                .with_context(|| format!("failed to write {:?}", out_path))?;
+            Ok(0)
+        }
+        Commands::Bisect {
+            bisect,
+            iterations,
+            warmup,
+            seed,
+            test,
+            host,
+            perf_tool,
+            event_set_file,
+            bench_dir,
+            out_dir,
+            timeout,
+        } => {
+            let bencher = perf_tool.get_bencher();
+            if !bencher.records_stats() || (bisect.runtime && !bencher.supports_runtime()) {
+                anyhow::bail!("{} can not be used to bisect", perf_tool.name());
+            }
+            if bencher.needs_perf() {
+                perf_check()?;
+            }
+            let events = EventSet::from_file(&event_set_file)?;
+            let filter = BenchmarkFilter::only(&bisect.benchmark);
+            host.prepare()?;
+
+            let target = BisectTarget {
+                benchmark: bisect.benchmark.clone(),
+                profile: (!bisect.runtime).then_some(bisect.profile),
+                build_config: bisect.build_config.clone(),
+                scenario: (!bisect.runtime).then_some(bisect.scenario),
+                patch: bisect.patch.clone(),
+                metric: bisect.metric.clone(),
+                threshold: bisect.threshold,
+            };
+            let toolchains = bisect.toolchains()?;
+            for (id, toolch) in &toolchains {
+                println!("{}: {}", id, toolch.rustc.display());
+            }
+            let seed = seed.unwrap_or_else(InterleaveOptions::default_seed);
+            println!(
+                "Interleaving every step in an order shuffled with seed {}",
+                seed
+            );
+            let rng = &mut SplitMix64::new(seed);
+            create_output_dir(&out_dir)?;
+
+            // Every step measures the baseline again, interleaved with the
+            // toolchain it is compared with.
+            let report = BisectReport::run(target, &toolchains, |i| {
+                let pair = [&toolchains[0], &toolchains[i]];
+                let dir = out_dir.join(&toolchains[i].0);
+                if bisect.runtime {
+                    let runs = pair
+                        .iter()
+                        .map(|(id, ltc)| RuntimeRun::new(ltc, id.clone(), dir.join(id)))
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    let results = bench_runtime(
+                        &runs,
+                        bench_dir.clone(),
+                        &filter,
+                        &[bisect.build_config.clone()],
+                        iterations as u32,
+                        &perf_tool,
+                        &events,
+                        0,
+                        timeout,
+                        None,
+                        warmup,
+                        rng,
+                    )?;
+                    let results: Vec<_> = runs
+                        .iter()
+                        .zip(results)
+                        .map(|(run, results)| (run.id.clone(), results.results))
+                        .collect();
+                    Ok(PairedComparison::runtime(&results, test))
+                } else {
+                    let mut runs = pair
+                        .iter()
+                        .map(|(id, ltc)| CompileTimeRun::new(ltc, id.clone(), dir.join(id), false))
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    bench_compile_time(
+                        &mut runs,
+                        &perf_tool,
+                        &events,
+                        &[bisect.profile],
+                        &[bisect.build_config.clone()],
+                        &[bisect.scenario],
+                        bench_dir.clone(),
+                        &filter,
+                        iterations,
+                        0,
+                        timeout,
+                        None,
+                        warmup,
+                        rng,
+                    )?;
+                    let results: Vec<_> = runs
+                        .iter()
+                        .map(|run| (run.id.clone(), run.journal.results()))
+                        .collect();
+                    Ok(PairedComparison::compile_time(&results, test))
+                }
+            })?;
+
+            match (&report.first_crossing, &report.last_before) {
+                (Some(first), Some(before)) => println!(
+                    "First toolchain past the threshold: {}, after {}",
+                    first, before
+                ),
+                _ => println!(
+                    "{} does not cross the threshold",
+                    toolchains[toolchains.len() - 1].0
+                ),
+            }
+            let path = out_dir.join("bisect.json");
+            fs::write(&path, serde_json::to_string(&report)?)
+                .with_context(|| format!("failed to write {:?}", path))?;
+            println!("Bisection written to {:?}", path);
+            Ok(0)
+        }
+        Commands::Db { db, command } => {
+            let mut db = Database::open(&db)?;
+            match command {
+                DbCommands::Ingest { id, files } => {
+                    for file in files {
+                        let (kind, count) = db.ingest(&file, id.as_deref())?;
+                        println!("Ingested {} {} values from {:?}", count, kind, file);
+                    }
+                }
+                DbCommands::History { metric, slice } => {
+                    let mut writer = csv::Writer::from_writer(std::io::stdout());
+                    for entry in db.history(&metric, &slice.slice(None))? {
+                        writer.serialize(entry)?;
+                    }
+                    writer.flush()?;
+                }
+                DbCommands::Export {
+                    slice,
+                    metric,
+                    format,
+                    out_dir,
+                } => {
+                    for dir in db.export(&slice.slice(metric), format, &out_dir)? {
+                        println!("Exported to {:?}", dir);
+                    }
+                }
+            }
+            Ok(0)
+        }
         Commands::GenerateMir {
             local,
             bench_dir,
             out_dir,
         } => {
+            let filter = local.filter()?;
             let ltc = get_local_toolchain(
-                &local.rustc,
+                &local.rustc()?,
                 local.cargo.as_deref(),
                 local.id.as_deref(),
                 "",
             )?;
 
-            let benchmark_suit = BenchmarkSuit {
-                benchmarks: discover_benchmark_suit(&bench_dir)?,
-            };
+            let benchmark_suit = BenchmarkSuit::load(&bench_dir, &filter)?;
             println!("{}", benchmark_suit.display_benchmarks());
 
             for b in benchmark_suit.benchmarks {
@@ -320,14 +546,63 @@ fn create_output_dir(out_dir: &PathBuf) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn perf_check() {
+/// Directory of the results of toolchain `id`, the out dir itself unless
+/// several toolchains are measured.
+fn toolchain_dir(out_dir: &Path, id: &str, toolchains: usize) -> PathBuf {
+    if toolchains > 1 {
+        out_dir.join(id)
+    } else {
+        out_dir.to_path_buf()
+    }
+}
+
+/// The generator shuffling the order of the toolchains of an interleaved run.
+fn interleaved_rng<T>(toolchains: &[T], seed: u64) -> SplitMix64 {
+    if toolchains.len() > 1 {
+        println!(
+            "Interleaving {} toolchains in an order shuffled with seed {}",
+            toolchains.len(),
+            seed
+        );
+    }
+    SplitMix64::new(seed)
+}
+
+fn result_writer(dir: &Path, file_name: &str, what: &str) -> anyhow::Result<ResultWriter> {
+    ResultWriter::new(dir.to_path_buf(), PathBuf::from(file_name)).with_context(|| {
+        format!(
+            "Fail to open {} to record {}!",
+            dir.to_str().unwrap_or("?"),
+            what
+        )
+    })
+}
+
+/// Write the comparison of the toolchains of an interleaved run with the
+/// first one to `out_dir`, and print how many changed significantly.
+fn write_paired_comparison(out_dir: &Path, comparisons: &[PairedComparison]) -> anyhow::Result<()> {
+    let path = out_dir.join("paired_comparison.json");
+    fs::write(&path, serde_json::to_string(comparisons)?)
+        .with_context(|| format!("failed to write {:?}", path))?;
+    for ((toolchain, verdict), count) in PairedComparison::tally(comparisons) {
+        println!("{}: {} {}", toolchain, count, verdict);
+    }
+    println!("Paired comparison written to {:?}", path);
+    Ok(())
+}
+
+fn perf_check() -> anyhow::Result<()> {
     let mut cmd = Command::new("perf");
-    let has_perf = cmd.output().is_ok();
-    assert!(has_perf);
+    if cmd.output().is_err() {
+        anyhow::bail!("`perf` is not available, use `--perf-tool rusage` to bench without it");
+    }
+    Ok(())
 }
 
-fn flamegraph_check() {
+fn flamegraph_check() -> anyhow::Result<()> {
     let mut cmd = Command::new("flamegraph");
-    let has_flamegraph = cmd.output().is_ok();
-    assert!(has_flamegraph);
+    if cmd.output().is_err() {
+        anyhow::bail!("`flamegraph` is not available, install it or use `--flamegraph 0`");
+    }
+    Ok(())
 }
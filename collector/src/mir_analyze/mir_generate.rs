@@ -155,12 +155,14 @@ fn package_generate_mir(
 #[cfg(test)]
 mod test {
     use std::{
+        collections::BTreeMap,
         fs::remove_dir_all,
         path::{Path, PathBuf},
     };
 
     use crate::{
         benchmark::benchmark::{Benchamrk, BenchmarkConfig},
+        statistics::metadata::ToolchainIdentity,
         toolchain::LocalToolchain,
     };
 
@@ -187,6 +189,12 @@ mod test {
                 target_path: None,
                 runtime_cargo_toml: None,
                 runtime_test_packages: None,
+                patches: vec![],
+                tags: vec![],
+                timeout_secs: None,
+                warmup: None,
+                weight: 1.0,
+                build_configs: BTreeMap::new(),
             },
         };
         let out_dir = Path::new("test/mir_analyze/run_analyze/out");
@@ -196,8 +204,8 @@ mod test {
             &LocalToolchain {
                 rustc: PathBuf::from("rustc"),
                 cargo: PathBuf::from("cargo"),
-                flame_graph: PathBuf::from(""),
                 id: 0.to_string(),
+                identity: ToolchainIdentity::default(),
             },
             &out_dir,
         )
@@ -227,8 +235,8 @@ mod test {
             &LocalToolchain {
                 rustc: PathBuf::from("rustc"),
                 cargo: PathBuf::from("cargo"),
-                flame_graph: PathBuf::from(""),
                 id: 0.to_string(),
+                identity: ToolchainIdentity::default(),
             },
             out_dir,
         )
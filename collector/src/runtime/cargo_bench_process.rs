@@ -1,16 +1,13 @@
-use std::{
-    path::{Path, PathBuf},
-    process::Command,
-};
+use std::{path::Path, process::Command, time::Duration};
 
 use crate::{
-    execute::process_benchmark_output,
-    statistics::runtime_stat::RuntimeResult,
+    execute::events::EventSet,
+    statistics::{runtime_stat::RuntimeResult, stability::StopRule},
     toolchain::{Compiler, PerfTool},
-    utils::command::{command_discard_output, command_output},
+    utils::command::command_discard_output,
 };
 
-use super::{Runtime, FAKE_FLAMEGRAPH, FAKE_RUNTIME};
+use super::{Runtime, TestExecutables, FAKE_FLAMEGRAPH};
 
 pub struct CargoBenchProcess<'a> {
     pub compiler: Compiler<'a>,
@@ -19,6 +16,13 @@ pub struct CargoBenchProcess<'a> {
     pub manifest_path: String,
     pub iterations: u32,
     args: Vec<String>,
+    timeout: Option<Duration>,
+    /// Environment of the build config, set on the cargo commands only.
+    cargo_env: Vec<(String, String)>,
+    pub stop_rule: Option<StopRule>,
+    pub warmup: u32,
+    /// Bench executables built by `Runtime::prepare`.
+    executables: TestExecutables,
 }
 
 impl<'a> CargoBenchProcess<'a> {
@@ -29,29 +33,31 @@ impl<'a> CargoBenchProcess<'a> {
         manifest_path: String,
         iterations: u32,
         args: Vec<String>,
+        timeout: Option<Duration>,
+        cargo_env: Vec<(String, String)>,
     ) -> Self {
-        let process = Self {
+        Self {
             compiler,
             processor_name,
             cwd,
             manifest_path,
             iterations,
             args,
-        };
-        match process.build() {
-            Ok(_) => (),
-            Err(_) => eprintln!("Fail to compile examples for {}.", process.processor_name),
-        };
-
-        process
+            timeout,
+            cargo_env,
+            stop_rule: None,
+            warmup: 0,
+            executables: TestExecutables::default(),
+        }
     }
 
-    fn base_command(&self) -> Command {
-        let mut cmd = Command::new(&*FAKE_RUNTIME);
-        cmd.env("RUNTIME_ELF", self.compiler.cargo)
-            .env("RUSTC", self.compiler.rustc)
-            // .env("CARGO_INCREMENTAL", "0")
+    /// `cargo bench --no-run` of the benchmark.
+    fn build_command(&self) -> Command {
+        let mut cmd = Command::new(self.compiler.cargo);
+        cmd.env("RUSTC", self.compiler.rustc)
+            .env("CARGO_INCREMENTAL", "0")
             .env("RUSTC_BOOTSTRAP", "1")
+            .envs(self.cargo_env.iter().cloned())
             .current_dir(self.cwd)
             .arg("bench")
             .args(self.args.clone())
@@ -59,7 +65,8 @@ impl<'a> CargoBenchProcess<'a> {
             .arg(&self.manifest_path)
             .arg("--profile")
             .arg("release")
-            .arg("--no-fail-fast");
+            .arg("--no-fail-fast")
+            .arg("--no-run");
         cmd
     }
 
@@ -72,6 +79,7 @@ impl<'a> CargoBenchProcess<'a> {
             .env("RUSTC", self.compiler.rustc)
             .env("CARGO_INCREMENTAL", "1")
             .env("RUSTC_BOOTSTRAP", "1")
+            .envs(self.cargo_env.iter().cloned())
             .arg("--output")
             .arg(out.join(flame_graph_file_name))
             .arg("--")
@@ -85,73 +93,57 @@ impl<'a> CargoBenchProcess<'a> {
             .arg("--no-fail-fast");
         cmd
     }
+}
 
-    fn build(&self) -> anyhow::Result<()> {
+impl<'a> Runtime for CargoBenchProcess<'a> {
+    fn prepare(&mut self) -> anyhow::Result<()> {
         eprintln!("Building bench for {}...", self.processor_name);
-        let mut cmd = self.base_command();
-        cmd.arg("--no-run").env("CARGO_INCREMENTAL", "0");
-
-        match command_discard_output(&mut cmd) {
-            Ok(_) => Ok(()),
+        // Bench executables only run their benchmarks with `--bench`.
+        match TestExecutables::build(&mut self.build_command(), vec![String::from("--bench")]) {
+            Ok(executables) => {
+                self.executables = executables;
+                Ok(())
+            }
             Err(err) => {
                 eprintln!(
                     "Fail to compile bench {}, will be skipped.",
                     self.processor_name
                 );
                 eprintln!("{:?}", err);
-                return Err(err);
+                Err(err)
             }
         }
     }
-}
 
-impl<'a> Runtime for CargoBenchProcess<'a> {
     fn measure(
         &self,
         perf_tool: &PerfTool,
-        _event_filter_file: &PathBuf,
+        events: &EventSet,
     ) -> anyhow::Result<Option<crate::statistics::runtime_stat::RuntimeResult>> {
-        let mut result = RuntimeResult::new(self.processor_name.clone());
+        let mut result =
+            RuntimeResult::new(self.processor_name.clone()).with_warmup(self.warmup as usize);
 
-        for iteration in 0..self.iterations {
+        for iteration in 0..self.warmup + self.iterations {
             eprintln!(
                 "running '{}' Runtime Bench iteration {}/{}",
                 self.processor_name.clone(),
                 iteration + 1,
-                self.iterations
+                self.warmup + self.iterations
             );
-            let mut cmd = self.base_command();
-
-            match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => (),
-                crate::toolchain::Bencher::PerfRecord => {
-                    cmd.arg("--output")
-                        .arg(self.cwd.join(format!("{:02}_perf.data", iteration)));
-                }
+            if let Some(stats) =
+                self.executables
+                    .measure(self.cwd, perf_tool, events, self.timeout, iteration)?
+            {
+                result.append(stats);
             }
-
-            let output = command_output(&mut cmd)?;
-
-            match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => {
-                    let output = process_benchmark_output(output)?;
-                    result.append(output);
-                }
-                crate::toolchain::Bencher::PerfRecord => (),
+            if result.is_stable(self.stop_rule.as_ref()) {
+                break;
             }
         }
         Ok(Some(result))
     }
 
     fn draw_flame_graph(&self, out_path: &Path) -> anyhow::Result<()> {
-        match self.build() {
-            Ok(_) => (),
-            Err(err) => {
-                eprintln!("Fail to compile test for {}.", self.processor_name);
-                eprintln!("{:?}", err);
-                return Err(err);
-            }
-        }
         eprintln!("drawing flamegraph for '{}' Bench", self.processor_name);
         let mut cmd = self.base_flame_graph_command(out_path);
         command_discard_output(&mut cmd)?;
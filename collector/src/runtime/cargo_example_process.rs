@@ -2,18 +2,22 @@ use std::{
     io::BufReader,
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    time::Duration,
 };
 
 use cargo_metadata::Message;
 
 use crate::{
-    execute::{process_benchmark_output, Stats},
-    statistics::runtime_stat::RuntimeResult,
+    execute::{
+        events::{EventSet, PERF_EVENTS_ENV},
+        process_benchmark_output, Stats,
+    },
+    statistics::{runtime_stat::RuntimeResult, stability::StopRule},
     toolchain::{Compiler, PerfTool},
-    utils::command::{command_discard_output, command_output},
+    utils::command::command_discard_output,
 };
 
-use super::{Runtime, FAKE_FLAMEGRAPH, FAKE_RUNTIME};
+use super::{run_measured, Runtime, FAKE_FLAMEGRAPH, FAKE_RUNTIME};
 
 pub struct CargoExampleProcess<'a> {
     pub compiler: Compiler<'a>,
@@ -24,6 +28,11 @@ pub struct CargoExampleProcess<'a> {
     pub examples: Vec<String>,
     pub example_elf: Vec<(String, PathBuf)>,
     pub args: Vec<String>,
+    pub timeout: Option<Duration>,
+    /// Environment of the build config, set on the cargo commands only.
+    pub cargo_env: Vec<(String, String)>,
+    pub stop_rule: Option<StopRule>,
+    pub warmup: u32,
 }
 
 impl<'a> CargoExampleProcess<'a> {
@@ -35,6 +44,7 @@ impl<'a> CargoExampleProcess<'a> {
         iterations: u32,
         examples: Vec<String>,
         args: Vec<String>,
+        cargo_env: Vec<(String, String)>,
     ) -> Self {
         let mut process = Self {
             compiler,
@@ -45,6 +55,10 @@ impl<'a> CargoExampleProcess<'a> {
             examples,
             example_elf: vec![],
             args,
+            timeout: None,
+            cargo_env,
+            stop_rule: None,
+            warmup: 0,
         };
         match process.build() {
             Ok(_) => (),
@@ -57,6 +71,7 @@ impl<'a> CargoExampleProcess<'a> {
     fn build_command(&self, example_name: &String) -> Command {
         let mut cmd = Command::new(self.compiler.cargo);
         cmd.env("RUSTC", self.compiler.rustc)
+            .envs(self.cargo_env.iter().cloned())
             .current_dir(self.cwd)
             .arg("rustc")
             .args(self.args.clone())
@@ -149,13 +164,14 @@ impl<'a> Runtime for CargoExampleProcess<'a> {
     fn measure(
         &self,
         perf_tool: &PerfTool,
-        _event_filter_file: &PathBuf,
+        events: &EventSet,
     ) -> anyhow::Result<Option<RuntimeResult>> {
         log::info!("{:?}", self.examples);
 
-        let mut result = RuntimeResult::new(self.processor_name.clone());
+        let mut result =
+            RuntimeResult::new(self.processor_name.clone()).with_warmup(self.warmup as usize);
 
-        for iteration in 0..self.iterations {
+        for iteration in 0..self.warmup + self.iterations {
             let mut output_sum = Stats::new();
             for (example, elf) in &self.example_elf {
                 eprintln!(
@@ -163,32 +179,43 @@ impl<'a> Runtime for CargoExampleProcess<'a> {
                     self.processor_name.clone(),
                     example,
                     iteration + 1,
-                    self.iterations
+                    self.warmup + self.iterations
                 );
 
                 let mut cmd = self.base_command(elf);
+                cmd.env(PERF_EVENTS_ENV, events.to_arg());
 
-                match perf_tool.get_bencher() {
-                    crate::toolchain::Bencher::PerfStat => (),
-                    crate::toolchain::Bencher::PerfRecord => {
-                        cmd.arg("--output").arg(
-                            self.cwd
-                                .join(format!("{}_{:02}_perf.data", example, iteration)),
-                        );
-                    }
+                if perf_tool.get_bencher() == crate::toolchain::Bencher::PerfRecord {
+                    cmd.arg("--output").arg(
+                        self.cwd
+                            .join(format!("{}_{:02}_perf.data", example, iteration)),
+                    );
                 }
 
-                let output = command_output(&mut cmd)?;
+                // An iteration with a timed-out example is recorded as
+                // timed out as a whole.
+                let output = match run_measured(&mut cmd, self.timeout, example)? {
+                    Some(output) => output,
+                    None => {
+                        output_sum = Stats::timed_out();
+                        break;
+                    }
+                };
 
-                let output = process_benchmark_output(output)?;
+                let output = process_benchmark_output(output, events.min_coverage)?;
 
                 match perf_tool.get_bencher() {
-                    crate::toolchain::Bencher::PerfStat => output_sum += output,
-                    crate::toolchain::Bencher::PerfRecord => (),
+                    crate::toolchain::Bencher::PerfStat | crate::toolchain::Bencher::Rusage => {
+                        output_sum += output
+                    }
+                    _ => (),
                 }
             }
 
             result.append(output_sum);
+            if result.is_stable(self.stop_rule.as_ref()) {
+                break;
+            }
         }
         Ok(Some(result))
     }
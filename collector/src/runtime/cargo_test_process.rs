@@ -1,16 +1,15 @@
-use std::{
-    path::{Path, PathBuf},
-    process::Command,
-};
+use std::{path::Path, process::Command, time::Duration};
 
 use crate::{
-    execute::process_benchmark_output,
+    execute::events::EventSet,
     toolchain::{Compiler, PerfTool},
-    utils::command::{command_discard_output, command_output},
+    utils::command::command_discard_output,
 };
 
-use super::{Runtime, FAKE_FLAMEGRAPH, FAKE_RUNTIME};
-use crate::statistics::runtime_stat::RuntimeResult;
+use super::{Runtime, TestExecutables, FAKE_FLAMEGRAPH};
+use crate::statistics::{runtime_stat::RuntimeResult, stability::StopRule};
+#[cfg(test)]
+use crate::{execute::process_benchmark_output, utils::command::command_output};
 
 pub struct CargoTestProcess<'a> {
     pub compiler: Compiler<'a>,
@@ -19,15 +18,23 @@ pub struct CargoTestProcess<'a> {
     pub manifest_path: String,
     pub iterations: u32,
     pub args: Vec<String>,
+    pub timeout: Option<Duration>,
+    /// Environment of the build config, set on the cargo commands only.
+    pub cargo_env: Vec<(String, String)>,
+    pub stop_rule: Option<StopRule>,
+    pub warmup: u32,
+    /// Test executables built by `Runtime::prepare`.
+    pub executables: TestExecutables,
 }
 
 impl<'a> CargoTestProcess<'a> {
-    fn base_command(&self) -> Command {
-        let mut cmd = Command::new(&*FAKE_RUNTIME);
-        cmd.env("RUNTIME_ELF", self.compiler.cargo)
-            .env("RUSTC", self.compiler.rustc)
-            .env("CARGO_INCREMENTAL", "1")
+    /// `cargo test --no-run` of the benchmark.
+    fn build_command(&self) -> Command {
+        let mut cmd = Command::new(self.compiler.cargo);
+        cmd.env("RUSTC", self.compiler.rustc)
+            .env("CARGO_INCREMENTAL", "0")
             .env("RUSTC_BOOTSTRAP", "1")
+            .envs(self.cargo_env.iter().cloned())
             .current_dir(self.cwd)
             .arg("test")
             .arg("--all")
@@ -35,7 +42,8 @@ impl<'a> CargoTestProcess<'a> {
             .arg("--manifest-path")
             .arg(&self.manifest_path)
             .arg("release")
-            .arg("--no-fail-fast");
+            .arg("--no-fail-fast")
+            .arg("--no-run");
         cmd
     }
 
@@ -48,6 +56,7 @@ impl<'a> CargoTestProcess<'a> {
             .env("RUSTC", self.compiler.rustc)
             .env("CARGO_INCREMENTAL", "1")
             .env("RUSTC_BOOTSTRAP", "1")
+            .envs(self.cargo_env.iter().cloned())
             .arg("--output")
             .arg(out.join(flame_graph_file_name))
             .arg("--")
@@ -61,75 +70,48 @@ impl<'a> CargoTestProcess<'a> {
             .arg("--no-fail-fast");
         cmd
     }
-
-    fn compile_test(&self) -> anyhow::Result<()> {
-        let mut cmd = self.base_command();
-        cmd.arg("--no-run").env("CARGO_INCREMENTAL", "0");
-
-        command_discard_output(&mut cmd)
-    }
 }
 
 impl<'a> Runtime for CargoTestProcess<'a> {
-    fn measure(
-        &self,
-        perf_tool: &PerfTool,
-        _event_filter_file: &PathBuf,
-    ) -> anyhow::Result<Option<RuntimeResult>> {
+    fn prepare(&mut self) -> anyhow::Result<()> {
         eprintln!("compiling test for {}...", self.processor_name);
-        match self.compile_test() {
-            Ok(_) => (),
+        match TestExecutables::build(&mut self.build_command(), vec![]) {
+            Ok(executables) => {
+                self.executables = executables;
+                Ok(())
+            }
             Err(err) => {
                 eprintln!("Fail to compile test for {}.", self.processor_name);
                 eprintln!("{:?}", err);
-                return Err(err);
+                Err(err)
             }
         }
+    }
 
-        let mut result = RuntimeResult::new(self.processor_name.clone());
-
-        for iteration in 0..self.iterations + 1 {
-            if iteration > 0 {
-                eprintln!(
-                    "running '{}' Runtime iteration {}/{}...",
-                    self.processor_name.clone(),
-                    iteration,
-                    self.iterations
-                );
-            }
-
-            let mut cmd = self.base_command();
-
-            let perf_tool_name = perf_tool.name();
-            cmd.arg("--wrap-rustc-with");
-            cmd.arg(perf_tool_name);
-
-            match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => (),
-                crate::toolchain::Bencher::PerfRecord => {
-                    cmd.arg("--output")
-                        .arg(self.cwd.join(format!("{:02}_perf.data", iteration)));
-                }
-            }
-
-            let output = command_output(&mut cmd)?;
-
-            if iteration == 0 {
-                continue;
+    fn measure(
+        &self,
+        perf_tool: &PerfTool,
+        events: &EventSet,
+    ) -> anyhow::Result<Option<RuntimeResult>> {
+        let mut result =
+            RuntimeResult::new(self.processor_name.clone()).with_warmup(self.warmup as usize);
+
+        for iteration in 0..self.warmup + self.iterations {
+            eprintln!(
+                "running '{}' Runtime iteration {}/{}...",
+                self.processor_name.clone(),
+                iteration + 1,
+                self.warmup + self.iterations
+            );
+
+            if let Some(stats) =
+                self.executables
+                    .measure(self.cwd, perf_tool, events, self.timeout, iteration)?
+            {
+                result.append(stats);
             }
-
-            match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => match process_benchmark_output(output) {
-                    Ok(output) => {
-                        result.append(output);
-                    }
-                    Err(err) => {
-                        eprintln!("Fail to test {}. Err msg:", self.processor_name);
-                        eprintln!("{:?}", err);
-                        return Err(err.into());
-                    }
-                },
-                crate::toolchain::Bencher::PerfRecord => (),
+            if result.is_stable(self.stop_rule.as_ref()) {
+                break;
             }
         }
 
@@ -137,15 +119,6 @@ impl<'a> Runtime for CargoTestProcess<'a> {
     }
 
     fn draw_flame_graph(&self, out_path: &Path) -> anyhow::Result<()> {
-        eprintln!("compiling test for {}...", self.processor_name);
-        match self.compile_test() {
-            Ok(_) => (),
-            Err(err) => {
-                eprintln!("Fail to compile test for {}.", self.processor_name);
-                eprintln!("{:?}", err);
-                return Err(err);
-            }
-        }
         eprintln!("drawing flamegraph for '{}'...", self.processor_name);
         let mut cmd = self.base_flame_graph_command(out_path);
         command_discard_output(&mut cmd)?;
@@ -193,7 +166,10 @@ fn test_cargo_test_process() {
     match output {
         Ok(output) => {
             eprintln!("{:?}", output);
-            eprintln!("{:?}", process_benchmark_output(output));
+            eprintln!(
+                "{:?}",
+                process_benchmark_output(output, EventSet::default().min_coverage)
+            );
         }
         _ => eprintln!("{:?}", output),
     }
@@ -2,19 +2,23 @@ use std::{
     io::BufReader,
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    time::Duration,
 };
 
 use anyhow::Context;
 use cargo_metadata::Message;
 
 use crate::{
-    execute::process_benchmark_output,
+    execute::{
+        events::{EventSet, PERF_EVENTS_ENV},
+        process_benchmark_output, Stats,
+    },
     toolchain::{Compiler, PerfTool},
-    utils::command::{command_discard_output, command_output},
+    utils::command::command_discard_output,
 };
 
-use super::{Runtime, FAKE_FLAMEGRAPH, FAKE_RUNTIME};
-use crate::statistics::runtime_stat::RuntimeResult;
+use super::{run_measured, Runtime, FAKE_FLAMEGRAPH, FAKE_RUNTIME};
+use crate::statistics::{runtime_stat::RuntimeResult, stability::StopRule};
 
 pub struct RuntimeProcess<'a> {
     compiler: Compiler<'a>,
@@ -24,6 +28,11 @@ pub struct RuntimeProcess<'a> {
     args: Vec<String>,
     manifest_path: String,
     iterations: u32,
+    timeout: Option<Duration>,
+    /// Environment of the build config, set on the cargo commands only.
+    cargo_env: Vec<(String, String)>,
+    pub stop_rule: Option<StopRule>,
+    pub warmup: u32,
 }
 
 impl<'a> RuntimeProcess<'a> {
@@ -34,6 +43,8 @@ impl<'a> RuntimeProcess<'a> {
         args: Vec<String>,
         manifest_path: String,
         iterations: u32,
+        timeout: Option<Duration>,
+        cargo_env: Vec<(String, String)>,
     ) -> Self {
         let mut process = RuntimeProcess {
             compiler,
@@ -43,6 +54,10 @@ impl<'a> RuntimeProcess<'a> {
             args,
             manifest_path,
             iterations,
+            timeout,
+            cargo_env,
+            stop_rule: None,
+            warmup: 0,
         };
 
         match process.build() {
@@ -82,6 +97,7 @@ impl<'a> RuntimeProcess<'a> {
         let mut cmd = Command::new(Path::new(self.compiler.cargo));
         cmd.current_dir(self.cwd)
             .env("RUSTC", self.compiler.rustc)
+            .envs(self.cargo_env.iter().cloned())
             .arg("build")
             .arg("--release")
             .arg("--message-format")
@@ -124,19 +140,20 @@ impl<'a> Runtime for RuntimeProcess<'a> {
         &self,
 
         perf_tool: &PerfTool,
-        _event_filter_file: &PathBuf,
+        events: &EventSet,
     ) -> anyhow::Result<Option<RuntimeResult>> {
-        let mut result = RuntimeResult::new(self.name.clone());
+        let mut result = RuntimeResult::new(self.name.clone()).with_warmup(self.warmup as usize);
 
-        for iteration in 0..self.iterations {
+        for iteration in 0..self.warmup + self.iterations {
             eprintln!(
                 "running '{}' Runtime iteration {}/{}",
                 self.name,
                 iteration + 1,
-                self.iterations
+                self.warmup + self.iterations
             );
 
             let mut cmd = self.base_command();
+            cmd.env(PERF_EVENTS_ENV, events.to_arg());
             log::info!("{:?}", cmd);
             log::info!("{:?}", cmd.get_args());
 
@@ -144,23 +161,30 @@ impl<'a> Runtime for RuntimeProcess<'a> {
             cmd.arg("--wrap-rustc-with");
             cmd.arg(perf_tool_name);
 
-            match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => (),
-                crate::toolchain::Bencher::PerfRecord => {
-                    cmd.arg("--output")
-                        .arg(self.cwd.join(format!("{}_perf.data", iteration)));
-                }
+            if perf_tool.get_bencher() == crate::toolchain::Bencher::PerfRecord {
+                cmd.arg("--output")
+                    .arg(self.cwd.join(format!("{}_perf.data", iteration)));
             }
 
             match perf_tool.get_bencher() {
-                crate::toolchain::Bencher::PerfStat => {
-                    let output = command_output(&mut cmd)
-                        .with_context(|| format!("fail to start benchmark process."))?;
-                    let stats = process_benchmark_output(output)?;
+                crate::toolchain::Bencher::PerfStat | crate::toolchain::Bencher::Rusage => {
+                    let output = match run_measured(&mut cmd, self.timeout, &self.name)
+                        .with_context(|| format!("fail to start benchmark process."))?
+                    {
+                        Some(output) => output,
+                        None => {
+                            result.append(Stats::timed_out());
+                            continue;
+                        }
+                    };
+                    let stats = process_benchmark_output(output, events.min_coverage)?;
                     log::info!("stats:{:?}", stats);
                     result.append(stats);
                 }
-                crate::toolchain::Bencher::PerfRecord => (),
+                _ => (),
+            }
+            if result.is_stable(self.stop_rule.as_ref()) {
+                break;
             }
         }
 
@@ -1,16 +1,31 @@
 use std::{
+    env::current_dir,
     fs::{create_dir_all, read_dir},
+    io::BufReader,
     mem::ManuallyDrop,
     path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    time::Duration,
 };
 
 use anyhow::{bail, Context};
+use cargo_metadata::Message;
 
 use crate::{
     benchmark::benchmark::Benchamrk,
-    benchmark::benchmark::BenchmarkSuit,
-    statistics::runtime_stat::RuntimeResultVec,
-    toolchain::{Compiler, LocalToolchain, PerfTool},
+    benchmark::build_config::BuildConfig,
+    benchmark::filter::BenchmarkFilter,
+    benchmark::suit::BenchmarkSuit,
+    execute::{
+        events::{EventSet, PERF_EVENTS_ENV},
+        process_benchmark_output, Stats,
+    },
+    statistics::{runtime_stat::RuntimeResultVec, stability::StopRule},
+    toolchain::{Bencher, Compiler, LocalToolchain, PerfTool},
+    utils::{
+        command::{command_output_with_timeout, is_timeout},
+        rng::SplitMix64,
+    },
 };
 
 use crate::statistics::runtime_stat::RuntimeResult;
@@ -22,123 +37,414 @@ pub mod cargo_test_process;
 pub mod measure;
 
 pub trait Runtime {
+    /// Build the benchmark, so that `measure` only runs it. Processes built
+    /// when they are created have nothing left to do.
+    fn prepare(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
     fn measure(
         &self,
         perf_tool: &PerfTool,
-        event_filter_file: &PathBuf,
+        events: &EventSet,
     ) -> anyhow::Result<Option<RuntimeResult>>;
     fn draw_flame_graph(&self, out_path: &Path) -> anyhow::Result<()>;
 }
 
+/// Run one measured iteration of `name`. Returns `None` if it was killed
+/// after running into `timeout`, to be recorded as a timed-out iteration.
+pub(crate) fn run_measured(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+    name: &str,
+) -> anyhow::Result<Option<Output>> {
+    match command_output_with_timeout(cmd, timeout) {
+        Ok(output) => Ok(Some(output)),
+        Err(err) if is_timeout(&err) => {
+            eprintln!("'{}' {}, killed.", name, err.root_cause());
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Executables built by `cargo test` or `cargo bench`, measured directly
+/// so that cargo is neither rebuilding nor timed in every iteration.
+#[derive(Default)]
+pub struct TestExecutables {
+    paths: Vec<PathBuf>,
+    /// Arguments of every executable, e.g. `--bench`.
+    args: Vec<String>,
+}
+
+impl TestExecutables {
+    /// Build with `cmd`, a `cargo test --no-run` or `cargo bench --no-run`
+    /// command, and collect the executables of its test targets.
+    pub fn build(cmd: &mut Command, args: Vec<String>) -> anyhow::Result<Self> {
+        let mut child = cmd
+            .arg("--message-format")
+            .arg("json-diagnostic-rendered-ansi")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| anyhow::anyhow!("Failed to start cargo: {:?}", error))?;
+
+        let mut paths = vec![];
+        let stream = BufReader::new(child.stdout.take().unwrap());
+        for message in Message::parse_stream(stream) {
+            if let Message::CompilerArtifact(artifact) = message? {
+                if let (true, Some(executable)) = (artifact.profile.test, artifact.executable) {
+                    log::info!("Compiled {}", executable);
+                    paths.push(executable.into_std_path_buf());
+                }
+            }
+        }
+        if !child.wait()?.success() {
+            bail!("fail to build {:?}", cmd);
+        }
+        if paths.is_empty() {
+            bail!("no executable built by {:?}", cmd);
+        }
+        Ok(TestExecutables { paths, args })
+    }
+
+    /// Run every executable once in `cwd` as iteration `iteration` and sum
+    /// their stats. An iteration with a timed-out executable is recorded as
+    /// timed out as a whole. `None` if `perf_tool` records no stats.
+    pub fn measure(
+        &self,
+        cwd: &Path,
+        perf_tool: &PerfTool,
+        events: &EventSet,
+        timeout: Option<Duration>,
+        iteration: u32,
+    ) -> anyhow::Result<Option<Stats>> {
+        let bencher = perf_tool.get_bencher();
+        let mut stats_sum = Stats::new();
+        for (i, path) in self.paths.iter().enumerate() {
+            let mut cmd = Command::new(&*FAKE_RUNTIME);
+            cmd.env("RUNTIME_ELF", path)
+                .env(PERF_EVENTS_ENV, events.to_arg())
+                .current_dir(cwd)
+                .arg("--wrap-rustc-with")
+                .arg(perf_tool.name());
+            if bencher == Bencher::PerfRecord {
+                cmd.arg("--output")
+                    .arg(cwd.join(format!("{:02}_{}_perf.data", iteration, i)));
+            }
+            cmd.args(&self.args);
+
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let output = match run_measured(&mut cmd, timeout, &name)? {
+                Some(output) => output,
+                None => return Ok(Some(Stats::timed_out())),
+            };
+            if let Bencher::PerfStat | Bencher::Rusage = bencher {
+                stats_sum += process_benchmark_output(output, events.min_coverage)?;
+            }
+        }
+        match bencher {
+            Bencher::PerfStat | Bencher::Rusage => Ok(Some(stats_sum)),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// A toolchain measured by `bench_runtime_local`, with the place of its
+/// results.
+pub struct RuntimeRun<'a> {
+    pub id: String,
+    pub compiler: Compiler<'a>,
+    pub out_dir: PathBuf,
+    pub flame_graph_path: PathBuf,
+}
+
+impl<'a> RuntimeRun<'a> {
+    /// Prepare `out_dir` to hold the results of `ltc`.
+    pub fn new(ltc: &'a LocalToolchain, id: String, out_dir: PathBuf) -> anyhow::Result<Self> {
+        let flame_graph_path = current_dir()?.join(out_dir.join("flamegraphs"));
+        create_dir_all(&flame_graph_path)
+            .with_context(|| format!("Fail to create output dir {:?}", flame_graph_path))?;
+        log::debug!("{:?}", flame_graph_path);
+        Ok(RuntimeRun {
+            id,
+            compiler: Compiler::from_toolchain(ltc),
+            out_dir,
+            flame_graph_path,
+        })
+    }
+}
+
+/// Measure the benchmarks in `benchmark_dir` with every toolchain of
+/// `runs` and every build config of `build_configs`, see
+/// `Benchamrk::build_config`. Returns the results of each run.
 pub fn bench_runtime(
-    ltc: &LocalToolchain,
+    runs: &[RuntimeRun<'_>],
     benchmark_dir: PathBuf,
+    filter: &BenchmarkFilter,
+    build_configs: &[Option<String>],
     iterations: u32,
     perf_tool: &PerfTool,
-    event_filter_file: &PathBuf,
-    flame_graph_out_path: &Path,
+    events: &EventSet,
     flamegraph_flag: i32,
-    out_dir: &PathBuf,
-) -> anyhow::Result<RuntimeResultVec> {
-    let benchmark_suit = BenchmarkSuit {
-        benchmarks: discover_benchmark_suit(&benchmark_dir)?,
-    };
+    timeout: Option<u64>,
+    stop_rule: Option<&StopRule>,
+    warmup: usize,
+    rng: &mut SplitMix64,
+) -> anyhow::Result<Vec<RuntimeResultVec>> {
+    let interleaved = runs.len() > 1;
+    if interleaved && perf_tool.get_bencher() == Bencher::PerfRecord {
+        bail!("{} can not interleave several toolchains", perf_tool.name());
+    }
+
+    let benchmark_suit = BenchmarkSuit::load(&benchmark_dir, filter)?;
     println!("{}", benchmark_suit.display_benchmarks());
 
-    let mut results = RuntimeResultVec(vec![]);
+    let mut results: Vec<RuntimeResultVec> =
+        runs.iter().map(|_| RuntimeResultVec::default()).collect();
 
     for benchmark in benchmark_suit.benchmarks {
-        let timing_dir = ManuallyDrop::new(benchmark.make_temp_dir(&benchmark.path)?);
-        let cwd = timing_dir.path();
-
-        let process =
-            benchmark.make_runtime_process(Compiler::from_toolchain(ltc), cwd, iterations);
-
-        match &process {
-            Ok(process) => {
-                let result = process.measure(perf_tool, event_filter_file);
-
-                match result {
-                    Ok(result) => {
-                        match perf_tool.get_bencher() {
-                            crate::toolchain::Bencher::PerfStat => {
-                                if result.is_some() {
-                                    results.0.push(result.unwrap())
+        for build_config in build_configs.iter().map(|c| c.as_deref()) {
+            let Some(config) = benchmark.build_config(build_config) else {
+                println!(
+                    "Skipping {}: no build config `{}`",
+                    benchmark.name,
+                    build_config.unwrap_or_default()
+                );
+                continue;
+            };
+            if interleaved {
+                bench_interleaved(
+                    &benchmark,
+                    build_config,
+                    &config,
+                    runs,
+                    &mut results,
+                    iterations,
+                    perf_tool,
+                    events,
+                    flamegraph_flag,
+                    timeout,
+                    stop_rule,
+                    warmup,
+                    rng,
+                )?;
+                continue;
+            }
+            let (run, results) = (&runs[0], &mut results[0]);
+            let timing_dir = ManuallyDrop::new(benchmark.make_temp_dir(&benchmark.path)?);
+            let cwd = timing_dir.path();
+
+            let mut process = benchmark.make_runtime_process(
+                run.compiler,
+                cwd,
+                &config,
+                iterations,
+                benchmark.timeout(timeout),
+                stop_rule.cloned(),
+                benchmark.warmup(warmup) as u32,
+            );
+
+            match &mut process {
+                Ok(process) => {
+                    let result = process
+                        .prepare()
+                        .and_then(|_| process.measure(perf_tool, events));
+
+                    match result {
+                        Ok(result) => {
+                            match perf_tool.get_bencher() {
+                                crate::toolchain::Bencher::PerfStat
+                                | crate::toolchain::Bencher::Rusage => {
+                                    if let Some(result) = result {
+                                        results.results.push(result.with_build_config(build_config))
+                                    }
                                 }
-                            }
-                            crate::toolchain::Bencher::PerfRecord => {
-                                // 将PerfRecord产生的数据文件拷贝至目标文件夹
-                                let cwd = timing_dir.path();
-                                let dst_dir = out_dir.join(benchmark.name.clone());
-
-                                let _ = create_dir_all(dst_dir.clone());
-
-                                // Find file whoose name contains "perf.analyze" in directory cwd,
-                                // and copy it into directory dst_dir.
-                                for entry in read_dir(cwd)? {
-                                    let entry = entry?;
-                                    if entry.file_name().to_str().unwrap().contains("perf.data") {
-                                        let src_path = PathBuf::from(entry.file_name());
-                                        let dst_path =
-                                            dst_dir.join(src_path.as_os_str().to_str().unwrap());
-                                        if let Err(err) =
-                                            std::fs::copy(cwd.join(src_path), &dst_path)
+                                crate::toolchain::Bencher::PerfRecord => {
+                                    // 将PerfRecord产生的数据文件拷贝至目标文件夹
+                                    let cwd = timing_dir.path();
+                                    let mut dst_dir = run.out_dir.join(benchmark.name.clone());
+                                    if let Some(config) = build_config {
+                                        dst_dir.push(config);
+                                    }
+
+                                    let _ = create_dir_all(dst_dir.clone());
+
+                                    // Find file whoose name contains "perf.analyze" in directory cwd,
+                                    // and copy it into directory dst_dir.
+                                    for entry in read_dir(cwd)? {
+                                        let entry = entry?;
+                                        if entry.file_name().to_str().unwrap().contains("perf.data")
                                         {
-                                            eprintln!("Failed to copy 'perf.data' file: {}", err);
+                                            let src_path = PathBuf::from(entry.file_name());
+                                            let dst_path = dst_dir
+                                                .join(src_path.as_os_str().to_str().unwrap());
+                                            if let Err(err) =
+                                                std::fs::copy(cwd.join(src_path), &dst_path)
+                                            {
+                                                eprintln!(
+                                                    "Failed to copy 'perf.data' file: {}",
+                                                    err
+                                                );
+                                            }
                                         }
                                     }
                                 }
+                                // Profilers are rejected for runtime benchmarks.
+                                _ => (),
                             }
                         }
+                        Err(_) => {
+                            eprintln!("Fail to bench {}. Skip.", benchmark.name);
+                            continue;
+                        }
                     }
-                    Err(_) => {
-                        eprintln!("Fail to bench {}. Skip.", benchmark.name);
-                        continue;
+                    //draw flamegraph
+                    if flamegraph_flag > 0 {
+                        process.draw_flame_graph(&run.flame_graph_path)?;
                     }
                 }
-                //draw flamegraph
-                if flamegraph_flag > 0 {
-                    process.draw_flame_graph(flame_graph_out_path)?;
+                Err(msg) => {
+                    eprintln!("{}", msg);
+                    eprintln!(
+                        "Benchmark {} skipped due to previous error.",
+                        benchmark.name
+                    );
                 }
             }
-            Err(msg) => {
-                eprintln!("{}", msg);
-                eprintln!(
-                    "Benchmark {} skipped due to previous error.",
-                    benchmark.name
-                );
-            }
-        }
 
-        // process need to be dropped before we drop timing_dir
-        // but why?
-        drop(process);
-        drop(ManuallyDrop::into_inner(timing_dir));
+            // process need to be dropped before we drop timing_dir
+            // but why?
+            drop(process);
+            drop(ManuallyDrop::into_inner(timing_dir));
+        }
     }
 
     Ok(results)
 }
 
-fn discover_benchmark_suit(dir: &PathBuf) -> anyhow::Result<Vec<Benchamrk>> {
-    let mut benchmarks = vec![];
-
-    for entry in read_dir(dir)
-        .with_context(|| format!("failed to list benchmark dir '{}'", dir.display()))?
+/// Measure `benchmark` built with `build_config` with several toolchains
+/// in rounds of one iteration each, in an order shuffled by `rng` every
+/// round.
+fn bench_interleaved(
+    benchmark: &Benchamrk,
+    build_config: Option<&str>,
+    config: &BuildConfig,
+    runs: &[RuntimeRun<'_>],
+    results: &mut [RuntimeResultVec],
+    iterations: u32,
+    perf_tool: &PerfTool,
+    events: &EventSet,
+    flamegraph_flag: i32,
+    timeout: Option<u64>,
+    stop_rule: Option<&StopRule>,
+    warmup: usize,
+    rng: &mut SplitMix64,
+) -> anyhow::Result<()> {
+    let warmup = benchmark.warmup(warmup);
+    let timing_dirs = runs
+        .iter()
+        .map(|_| Ok(ManuallyDrop::new(benchmark.make_temp_dir(&benchmark.path)?)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    // Each process measures a single iteration, the rounds count the
+    // warm-up runs and stop early.
+    let mut processes = match runs
+        .iter()
+        .zip(timing_dirs.iter())
+        .map(|(run, dir)| {
+            benchmark.make_runtime_process(
+                run.compiler,
+                dir.path(),
+                config,
+                1,
+                benchmark.timeout(timeout),
+                None,
+                0,
+            )
+        })
+        .collect::<Result<Vec<_>, String>>()
     {
-        let entry = entry?;
-        let path = entry.path();
-        let name = match entry.file_name().into_string() {
-            core::result::Result::Ok(s) => s,
-            Err(e) => bail!("non-utf8 benchmark name: {:?}", e),
-        };
-        if !entry.file_type()?.is_dir() {
-            println!("benchmark '{}' ignored", name);
+        Ok(processes) => processes,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            eprintln!(
+                "Benchmark {} skipped due to previous error.",
+                benchmark.name
+            );
+            return Ok(());
+        }
+    };
+
+    let mut measured: Vec<RuntimeResult> = runs
+        .iter()
+        .map(|_| {
+            RuntimeResult::new(benchmark.name.clone())
+                .with_warmup(warmup)
+                .with_build_config(build_config)
+        })
+        .collect();
+    // Every toolchain builds the benchmark once, the rounds only run it.
+    let mut failed = vec![false; runs.len()];
+    for (r, process) in processes.iter_mut().enumerate() {
+        if let Err(e) = process.prepare() {
+            eprintln!(
+                "Fail to build {} with {}: {:?}. Skip.",
+                benchmark.name, runs[r].id, e
+            );
+            failed[r] = true;
+        }
+    }
+    let rounds = warmup + iterations as usize;
+    for round in 0..rounds {
+        println!(
+            "running '{}' Runtime round {}/{}",
+            benchmark.name,
+            round + 1,
+            rounds
+        );
+        let mut order: Vec<usize> = (0..runs.len()).collect();
+        rng.shuffle(&mut order);
+        for r in order {
+            if failed[r] {
+                continue;
+            }
+            match processes[r].measure(perf_tool, events) {
+                Ok(result) => result
+                    .into_iter()
+                    .flat_map(|result| result.warmup.into_iter().chain(result.stats))
+                    .for_each(|stats| {
+                        measured[r].append(stats);
+                    }),
+                Err(_) => {
+                    eprintln!(
+                        "Fail to bench {} with {}. Skip.",
+                        benchmark.name, runs[r].id
+                    );
+                    failed[r] = true;
+                }
+            }
+        }
+        if round >= warmup && measured.iter().all(|m| m.is_stable(stop_rule)) {
+            break;
+        }
+    }
+
+    for (r, result) in measured.into_iter().enumerate() {
+        if failed[r] {
             continue;
         }
-        benchmarks.push(Benchamrk::new(name, path)?);
+        results[r].results.push(result);
+        if flamegraph_flag > 0 {
+            processes[r].draw_flame_graph(&runs[r].flame_graph_path)?;
+        }
     }
 
-    Ok(benchmarks)
+    // The processes need to be dropped before the timing dirs.
+    drop(processes);
+    timing_dirs
+        .into_iter()
+        .for_each(|dir| drop(ManuallyDrop::into_inner(dir)));
+    Ok(())
 }
 
 lazy_static::lazy_static! {
@@ -0,0 +1,204 @@
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+/// Why a benchmark was left out of an [`Aggregate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Exclusion {
+    /// The benchmark was only measured by one of the two toolchains.
+    Missing,
+    /// The geometric mean of the benchmark is undefined, as a sample was not
+    /// positive.
+    Undefined,
+    /// The value of the benchmark, shifted by the epsilon, is not positive,
+    /// so it has no ratio or logarithm.
+    NotPositive,
+    /// The benchmark has a weight of zero.
+    ZeroWeight,
+}
+
+impl Display for Exclusion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Exclusion::Missing => f.write_str("measured by one toolchain only"),
+            Exclusion::Undefined => f.write_str("undefined geometric mean"),
+            Exclusion::NotPositive => f.write_str("value not positive"),
+            Exclusion::ZeroWeight => f.write_str("weight of zero"),
+        }
+    }
+}
+
+/// Suite-level change between two toolchains: the geometric mean of the
+/// ratios of the new to the old value of every benchmark, with the
+/// benchmarks that had to be left out.
+#[derive(Debug, Default)]
+pub struct Aggregate {
+    /// Ratio of every included benchmark, sorted by benchmark.
+    pub ratios: Vec<(String, f64)>,
+    /// Geometric mean of `ratios`, `None` if no benchmark is left.
+    pub geometric_mean: Option<f64>,
+    /// Benchmarks left out of `ratios`, sorted by benchmark.
+    pub excluded: Vec<(String, Exclusion)>,
+}
+
+impl Aggregate {
+    /// Aggregate the values of `new` on the ones of `old`, where `None`
+    /// stands for an undefined value. Both values are shifted by `epsilon`
+    /// before taking their ratio, so a positive `epsilon` keeps benchmarks
+    /// with a value of zero.
+    pub fn new(
+        new: &HashMap<String, Option<f64>>,
+        old: &HashMap<String, Option<f64>>,
+        epsilon: f64,
+    ) -> Self {
+        Self::weighted(new, old, epsilon, &HashMap::new())
+    }
+
+    /// Like [`Aggregate::new`], with the ratio of each benchmark counting
+    /// `weights[benchmark]` times, or once if it has no weight.
+    pub fn weighted(
+        new: &HashMap<String, Option<f64>>,
+        old: &HashMap<String, Option<f64>>,
+        epsilon: f64,
+        weights: &HashMap<String, f64>,
+    ) -> Self {
+        let weight = |benchmark: &str| weights.get(benchmark).copied().unwrap_or(1.0);
+        let mut ratios = vec![];
+        let mut excluded = vec![];
+        for (benchmark, a) in new {
+            let ratio = match (a, old.get(benchmark)) {
+                (_, None) => Err(Exclusion::Missing),
+                _ if weight(benchmark) <= 0.0 => Err(Exclusion::ZeroWeight),
+                (None, _) | (_, Some(None)) => Err(Exclusion::Undefined),
+                (Some(a), Some(Some(b))) if a + epsilon > 0.0 && b + epsilon > 0.0 => {
+                    Ok((a + epsilon) / (b + epsilon))
+                }
+                _ => Err(Exclusion::NotPositive),
+            };
+            match ratio {
+                Ok(ratio) => ratios.push((benchmark.clone(), ratio)),
+                Err(reason) => excluded.push((benchmark.clone(), reason)),
+            }
+        }
+        old.keys()
+            .filter(|b| !new.contains_key(*b))
+            .for_each(|b| excluded.push((b.clone(), Exclusion::Missing)));
+        ratios.sort_by(|a, b| a.0.cmp(&b.0));
+        excluded.sort_by(|a, b| a.0.cmp(&b.0));
+
+        // exp(Sum(wi * ln(ri)) / Sum(wi))
+        let total: f64 = ratios.iter().map(|(b, _)| weight(b)).sum();
+        let geometric_mean = match ratios.len() {
+            0 => None,
+            _ => Some((ratios.iter().map(|(b, r)| weight(b) * r.ln()).sum::<f64>() / total).exp()),
+        };
+        Aggregate {
+            ratios,
+            geometric_mean,
+            excluded,
+        }
+    }
+
+    /// Change rate of every included benchmark in percent.
+    pub fn change_rates(&self) -> HashMap<String, f64> {
+        self.ratios
+            .iter()
+            .map(|(b, r)| (b.clone(), (r - 1.0) * 100.0))
+            .collect()
+    }
+}
+
+impl Display for Aggregate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.geometric_mean {
+            Some(mean) => write!(
+                f,
+                "Geometric mean of the change over {} benchmarks: {:.2}%",
+                self.ratios.len(),
+                (mean - 1.0) * 100.0
+            )?,
+            None => write!(f, "No benchmark to aggregate")?,
+        }
+        if !self.excluded.is_empty() {
+            write!(f, "\n{} benchmarks excluded:", self.excluded.len())?;
+            for (benchmark, reason) in &self.excluded {
+                write!(f, "\n  {}: {}", benchmark, reason)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_aggregate {
+    use std::collections::HashMap;
+
+    use super::{Aggregate, Exclusion};
+
+    /// Test for Aggregate
+    ///
+    /// Step1. Aggregate benchmarks with undefined, zero and missing values
+    /// and check which are excluded and why.
+    ///
+    /// Step2. Shift the values by an epsilon to keep the zero values.
+    ///
+    /// Step3. Weight the benchmarks.
+    #[test]
+    fn test_aggregate() {
+        let values = |v: &[(&str, Option<f64>)]| -> HashMap<String, Option<f64>> {
+            v.iter().map(|(b, x)| (b.to_string(), *x)).collect()
+        };
+        let new = values(&[
+            ("a", Some(2.0)),
+            ("b", Some(8.0)),
+            ("c", None),
+            ("d", Some(0.0)),
+            ("e", Some(1.0)),
+        ]);
+        let old = values(&[
+            ("a", Some(1.0)),
+            ("b", Some(2.0)),
+            ("c", Some(1.0)),
+            ("d", Some(3.0)),
+            ("f", Some(1.0)),
+        ]);
+
+        let aggregate = Aggregate::new(&new, &old, 0.0);
+        assert_eq!(
+            aggregate.ratios,
+            vec![("a".to_string(), 2.0), ("b".to_string(), 4.0)]
+        );
+        assert!((aggregate.geometric_mean.unwrap() - 8f64.sqrt()).abs() < 1e-12);
+        assert_eq!(
+            aggregate.excluded,
+            vec![
+                ("c".to_string(), Exclusion::Undefined),
+                ("d".to_string(), Exclusion::NotPositive),
+                ("e".to_string(), Exclusion::Missing),
+                ("f".to_string(), Exclusion::Missing),
+            ]
+        );
+        assert_eq!(aggregate.change_rates()["b"], 300.0);
+
+        let aggregate = Aggregate::new(&new, &old, 1.0);
+        assert_eq!(aggregate.ratios[2], ("d".to_string(), 0.25));
+        assert_eq!(aggregate.excluded.len(), 3);
+
+        let weights = [("a".to_string(), 3.0), ("b".to_string(), 0.0)]
+            .into_iter()
+            .collect();
+        let aggregate = Aggregate::weighted(&new, &old, 1.0, &weights);
+        // (1.5^3 * 0.25)^(1/4)
+        assert!((aggregate.geometric_mean.unwrap() - 0.958415).abs() < 1e-6);
+        assert_eq!(
+            aggregate.excluded[0],
+            ("b".to_string(), Exclusion::ZeroWeight)
+        );
+
+        assert_eq!(
+            Aggregate::new(&HashMap::new(), &HashMap::new(), 0.0).geometric_mean,
+            None
+        );
+    }
+}
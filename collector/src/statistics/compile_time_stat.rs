@@ -4,17 +4,38 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     benchmark::{profile::Profile, scenario::Scenario},
-    execute::Stats,
-    statistics::statistic::Statistics,
+    execute::{self_profile::SelfProfile, Stats},
+    statistics::{
+        is_zero, metadata::Metadata, missing_events, statistic::Statistics, timed_out_count,
+    },
 };
 
+/// Profile, build config, scenario and patch of a measured build.
+pub type StatsKey = (Profile, Option<String>, Scenario, Option<String>);
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CompileTimeResult {
     pub benchmark: String,
     pub iteration: usize,
     pub profile: Profile,
+    /// Name of the build config of the suite the benchmark was built with,
+    /// missing for the build without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_config: Option<String>,
     pub scenario: Scenario,
+    /// Name of the patch applied before this build, only set for
+    /// `Scenario::IncrPatched`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
     pub stats: Stats,
+    /// Per-query data of the measured rustc, only set by the
+    /// `perf-stat-self-profile` bencher.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_profile: Option<SelfProfile>,
+    /// A warm-up run, numbered apart from the measured iterations and left
+    /// out of the statistics.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub warmup: bool,
 }
 
 impl CompileTimeResult {
@@ -29,10 +50,34 @@ impl CompileTimeResult {
             benchmark,
             iteration,
             profile,
+            build_config: None,
             scenario,
+            patch: None,
             stats,
+            self_profile: None,
+            warmup: false,
         }
     }
+
+    pub fn with_patch(mut self, patch: String) -> Self {
+        self.patch = Some(patch);
+        self
+    }
+
+    pub fn with_build_config(mut self, build_config: Option<&str>) -> Self {
+        self.build_config = build_config.map(String::from);
+        self
+    }
+
+    pub fn with_self_profile(mut self, self_profile: Option<SelfProfile>) -> Self {
+        self.self_profile = self_profile;
+        self
+    }
+
+    pub fn with_warmup(mut self, warmup: bool) -> Self {
+        self.warmup = warmup;
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -63,9 +108,13 @@ impl CompileTimeBenchResult {
         self.iterations
     }
 
+    /// Results of the measured iterations, without warm-up runs.
+    pub fn measured(&self) -> impl Iterator<Item = &CompileTimeResult> {
+        self.result_vec.iter().filter(|r| !r.warmup)
+    }
+
     pub fn get_stats_ref_by_profile(&self, profile: &Profile) -> Vec<&Stats> {
-        self.result_vec
-            .iter()
+        self.measured()
             .filter_map(|r| {
                 if r.profile == *profile {
                     Some(&r.stats)
@@ -76,15 +125,38 @@ impl CompileTimeBenchResult {
             .collect()
     }
 
-    pub fn get_stats_with_profile_scenario(&self) -> HashMap<(Profile, Scenario), Vec<Stats>> {
-        let mut map = HashMap::<(Profile, Scenario), Vec<Stats>>::new();
-        self.result_vec.iter().for_each(|r| {
-            if map.contains_key(&(r.profile, r.scenario)) {
-                let stats = map.get_mut(&(r.profile, r.scenario)).unwrap();
-                stats.push(r.stats.clone());
-            } else {
-                map.insert((r.profile, r.scenario), vec![r.stats.clone()]);
-            }
+    pub fn get_stats_ref_by_profile_scenario(
+        &self,
+        profile: &Profile,
+        build_config: Option<&str>,
+        scenario: &Scenario,
+        patch: &Option<String>,
+    ) -> Vec<&Stats> {
+        self.measured()
+            .filter(|r| {
+                r.profile == *profile
+                    && r.build_config.as_deref() == build_config
+                    && r.scenario == *scenario
+                    && r.patch == *patch
+            })
+            .map(|r| &r.stats)
+            .collect()
+    }
+
+    /// Group stats of the measured iterations by profile, build config,
+    /// scenario and patch. Results of different patches are kept apart since
+    /// each patch triggers a different rebuild.
+    pub fn get_stats_with_profile_scenario(&self) -> HashMap<StatsKey, Vec<Stats>> {
+        let mut map = HashMap::<StatsKey, Vec<Stats>>::new();
+        self.measured().for_each(|r| {
+            map.entry((
+                r.profile,
+                r.build_config.clone(),
+                r.scenario,
+                r.patch.clone(),
+            ))
+            .or_default()
+            .push(r.stats.clone());
         });
         map
     }
@@ -94,44 +166,98 @@ impl CompileTimeBenchResult {
 pub struct CompileTimeResultSet {
     pub id: String,
     pub results: Vec<CompileTimeBenchResult>,
+    /// Environment the results were measured in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
 }
 
 impl CompileTimeResultSet {
     pub fn new(id: String, results: Vec<CompileTimeBenchResult>) -> Self {
-        CompileTimeResultSet { id, results }
+        CompileTimeResultSet {
+            id,
+            results,
+            metadata: None,
+        }
+    }
+
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = Some(metadata);
+        self
     }
 
     pub fn calculate_statistics(&self) -> CompileTimeStatistics {
+        self.calculate_shifted_statistics(0.0)
+    }
+
+    /// Statistics of the results with geometric means of the samples
+    /// shifted by `epsilon`, see [`Statistics::shifted`].
+    pub fn calculate_shifted_statistics(&self, epsilon: f64) -> CompileTimeStatistics {
         let mut statistics = CompileTimeStatistics::new();
         self.results.iter().for_each(|result| {
             let stats_map = result.get_stats_with_profile_scenario();
 
-            stats_map.iter().for_each(|((profile, scenario), stats)| {
-                let mut statistic_vec = Vec::<(String, Statistics)>::new();
-                let mut data_map = HashMap::<String, Vec<f64>>::new();
-
-                stats.iter().for_each(|stat| {
-                    stat.stats.iter().for_each(|(label, value)| {
-                        if data_map.contains_key(label) {
-                            data_map.get_mut(label).unwrap().push(value.clone());
-                        } else {
-                            data_map.insert(label.clone(), vec![value.clone()]);
-                        }
+            stats_map
+                .iter()
+                .for_each(|((profile, build_config, scenario, patch), stats)| {
+                    let measured: Vec<&CompileTimeResult> = result
+                        .measured()
+                        .filter(|r| {
+                            r.profile == *profile
+                                && r.build_config == *build_config
+                                && r.scenario == *scenario
+                                && r.patch == *patch
+                        })
+                        .collect();
+                    // Samples by label, with the iteration of each sample,
+                    // which skip the timed-out iterations.
+                    let statistics_of = |map: HashMap<String, (Vec<usize>, Vec<f64>)>| {
+                        map.into_iter()
+                            .map(|(label, (iterations, vals))| {
+                                let statistics =
+                                    Statistics::shifted(vals, epsilon).with_iterations(&iterations);
+                                (label, statistics)
+                            })
+                            .collect()
+                    };
+
+                    let mut data_map = HashMap::<String, (Vec<usize>, Vec<f64>)>::new();
+                    measured.iter().for_each(|r| {
+                        r.stats.stats.iter().for_each(|(label, value)| {
+                            let (iterations, vals) = data_map.entry(label.clone()).or_default();
+                            iterations.push(r.iteration);
+                            vals.push(*value);
+                        });
                     });
-                });
+                    let statistic_vec = statistics_of(data_map);
 
-                data_map.iter().for_each(|(label, vals)| {
-                    statistic_vec.push((label.clone(), Statistics::from(vals.clone())));
-                });
+                    let mut query_map = HashMap::<String, (Vec<usize>, Vec<f64>)>::new();
+                    measured.iter().for_each(|r| {
+                        r.self_profile
+                            .iter()
+                            .flat_map(|p| p.query_data.iter())
+                            .for_each(|q| {
+                                let (iterations, vals) =
+                                    query_map.entry(q.label.clone()).or_default();
+                                iterations.push(r.iteration);
+                                vals.push(q.self_time);
+                            });
+                    });
+                    let query_statistic_vec = statistics_of(query_map);
 
-                statistics.push(CompileTimeStatistic {
-                    name: result.benchmark.clone(),
-                    profile: profile.clone(),
-                    scenario: scenario.clone(),
-                    iterations: result.iterations as u32,
-                    statistic_vec,
+                    statistics.push(CompileTimeStatistic {
+                        name: result.benchmark.clone(),
+                        profile: profile.clone(),
+                        build_config: build_config.clone(),
+                        scenario: scenario.clone(),
+                        patch: patch.clone(),
+                        iterations: result.iterations as u32,
+                        iterations_used: stats.len() as u32,
+                        statistic_vec,
+                        missing: missing_events(stats.iter()),
+                        timed_out: timed_out_count(stats.iter()),
+                        query_statistic_vec,
+                    });
                 });
-            });
         });
         statistics
     }
@@ -145,9 +271,71 @@ impl CompileTimeResultSet {
 pub struct CompileTimeStatistic {
     pub name: String,
     pub profile: Profile,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_config: Option<String>,
     pub scenario: Scenario,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
     pub iterations: u32,
+    /// Iterations actually run, less than `iterations` if sampling stopped
+    /// once the results were stable.
+    #[serde(default)]
+    pub iterations_used: u32,
     pub statistic_vec: Vec<(String, Statistics)>,
+    /// Events that could not be counted in at least one iteration.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+    /// Iterations killed after running into their timeout, they are not
+    /// part of `statistic_vec`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub timed_out: usize,
+    /// Statistics of the self time of each rustc query, in seconds.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub query_statistic_vec: Vec<(String, Statistics)>,
 }
 
 pub type CompileTimeStatistics = Vec<CompileTimeStatistic>;
+
+#[cfg(test)]
+mod test_compile_time_stat {
+    use crate::{
+        benchmark::{profile::Profile, scenario::Scenario},
+        execute::Stats,
+    };
+
+    use super::{CompileTimeBenchResult, CompileTimeResult, CompileTimeResultSet};
+
+    /// Test for CompileTimeResultSet::calculate_statistics
+    ///
+    /// Step1. Record iterations with an outlier after a timed-out iteration.
+    ///
+    /// Step2. Verify the outlier is reported by its iteration, not by its
+    /// position among the samples.
+    #[test]
+    fn test_outlier_iterations() {
+        let mut result = CompileTimeBenchResult::new(String::from("a"), 7);
+        for (i, x) in [10.0, 12.0, 0.0, 11.0, 13.0, 40.0, 12.0].iter().enumerate() {
+            let stats = if i == 2 {
+                Stats::timed_out()
+            } else {
+                let mut stats = Stats::new();
+                stats.insert(String::from("wall-time"), *x);
+                stats
+            };
+            result.add_result(CompileTimeResult::new(
+                String::from("a"),
+                i + 1,
+                Profile::Debug,
+                Scenario::Full,
+                stats,
+            ));
+        }
+
+        let statistics =
+            CompileTimeResultSet::new(String::from("new"), vec![result]).calculate_statistics();
+        assert_eq!(statistics[0].timed_out, 1);
+        let (_, wall_time) = &statistics[0].statistic_vec[0];
+        assert_eq!(wall_time.count, 6);
+        assert_eq!(wall_time.outliers, vec![6]);
+    }
+}
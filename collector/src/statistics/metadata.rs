@@ -0,0 +1,303 @@
+use std::{
+    fs,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::execute::host::HostConditions;
+
+/// Which rustc measured a result set, from the output of `rustc -vV`, so
+/// that tools reading the results need not guess it from directory names.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolchainIdentity {
+    /// Release of rustc, like `1.76.0-nightly`.
+    pub release: String,
+    /// `stable`, `beta`, `nightly` or `dev`.
+    pub channel: String,
+    /// Hash of the commit rustc was built from, empty for builds without
+    /// git information.
+    pub commit_hash: String,
+    /// Date of that commit, like `2023-12-11`.
+    pub commit_date: String,
+    /// Name given to the toolchain by the user.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl ToolchainIdentity {
+    /// Identify `rustc` by running `rustc -vV`.
+    pub fn detect(rustc: &Path) -> anyhow::Result<Self> {
+        let output = Command::new(rustc)
+            .arg("-vV")
+            .output()
+            .with_context(|| format!("failed to run `{} -vV`", rustc.display()))?;
+        if !output.status.success() {
+            bail!(
+                "`{} -vV` exited with status {}",
+                rustc.display(),
+                output.status
+            );
+        }
+        Ok(Self::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Parse the output of `rustc -vV`. Fields rustc reports as `unknown`
+    /// are left empty.
+    pub fn parse(version: &str) -> Self {
+        let field = |name: &str| {
+            version
+                .lines()
+                .find_map(|l| l.split_once(':').filter(|(key, _)| key.trim() == name))
+                .map(|(_, value)| value.trim())
+                .filter(|value| *value != "unknown")
+                .unwrap_or_default()
+                .to_string()
+        };
+        let release = field("release");
+        // Pre-releases are named like `1.76.0-beta.2`.
+        let channel = match release.split_once('-') {
+            Some((_, pre)) => pre.split('.').next().unwrap_or(pre).to_string(),
+            None if release.is_empty() => String::new(),
+            None => String::from("stable"),
+        };
+        ToolchainIdentity {
+            release,
+            channel,
+            commit_hash: field("commit-hash"),
+            commit_date: field("commit-date"),
+            label: None,
+        }
+    }
+
+    pub fn with_label(mut self, label: Option<String>) -> Self {
+        self.label = label;
+        self
+    }
+
+    /// The label of the toolchain, or else its commit hash, or else its
+    /// release. Empty if nothing identifies it.
+    pub fn name(&self) -> &str {
+        match &self.label {
+            Some(label) => label,
+            None if !self.commit_hash.is_empty() => &self.commit_hash,
+            None => &self.release,
+        }
+    }
+
+    /// Whether `name` is the label of the toolchain, its release or its
+    /// commit hash, which can be shortened to 7 digits.
+    pub fn matches(&self, name: &str) -> bool {
+        !name.is_empty()
+            && (self.label.as_deref() == Some(name)
+                || self.release == name
+                || (name.len() >= 7 && self.commit_hash.starts_with(name)))
+    }
+}
+
+/// Where and how a result set was measured, to tell apart results compared
+/// long after they were collected.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    /// Output of `rustc -vV`.
+    pub rustc_version: String,
+    /// Output of `cargo -V`.
+    pub cargo_version: String,
+    pub hostname: String,
+    pub cpu_model: String,
+    /// Logical CPUs listed in `/proc/cpuinfo`.
+    pub cpu_cores: usize,
+    pub kernel: String,
+    /// Git commit the collector was built from.
+    pub collector_version: String,
+    /// Command line of the collector.
+    pub args: Vec<String>,
+    /// Seconds since the Unix epoch.
+    pub start_time: u64,
+    pub end_time: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host_conditions: Option<HostConditions>,
+    /// Empty in files written before toolchains were identified.
+    pub toolchain: ToolchainIdentity,
+}
+
+impl Metadata {
+    /// Collect the metadata of a run starting now with the given toolchain.
+    /// Values that cannot be read are left empty.
+    pub fn start(rustc: &Path, cargo: &Path, host_conditions: HostConditions) -> Self {
+        let version = |tool: &Path, arg: &str| {
+            Command::new(tool)
+                .arg(arg)
+                .output()
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+                .unwrap_or_default()
+        };
+        let (cpu_model, cpu_cores) = fs::read_to_string("/proc/cpuinfo")
+            .map(|s| parse_cpuinfo(&s))
+            .unwrap_or_default();
+        let read = |path: &str| {
+            fs::read_to_string(path)
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let rustc_version = version(rustc, "-vV");
+        Metadata {
+            toolchain: ToolchainIdentity::parse(&rustc_version),
+            rustc_version,
+            cargo_version: version(cargo, "-V"),
+            hostname: read("/proc/sys/kernel/hostname"),
+            cpu_model,
+            cpu_cores,
+            kernel: read("/proc/sys/kernel/osrelease"),
+            collector_version: env!("COLLECTOR_GIT_HASH").to_string(),
+            args: std::env::args().collect(),
+            start_time: now(),
+            end_time: 0,
+            host_conditions: Some(host_conditions),
+        }
+    }
+
+    /// Record `toolchain` as the toolchain of the run, with its label.
+    pub fn with_toolchain(mut self, toolchain: ToolchainIdentity) -> Self {
+        self.toolchain = toolchain;
+        self
+    }
+
+    /// Set the end of the run to now.
+    pub fn finish(mut self) -> Self {
+        self.end_time = now();
+        self
+    }
+
+    /// How the machines of two result sets differ, empty if they were
+    /// measured on the same host.
+    pub fn host_differences(&self, other: &Metadata) -> Vec<String> {
+        let mut differences = vec![];
+        let mut compare = |name: &str, a: &dyn ToString, b: &dyn ToString| {
+            let (a, b) = (a.to_string(), b.to_string());
+            if a != b {
+                differences.push(format!("{}: {:?} != {:?}", name, a, b));
+            }
+        };
+        compare("hostname", &self.hostname, &other.hostname);
+        compare("cpu model", &self.cpu_model, &other.cpu_model);
+        compare("cpu cores", &self.cpu_cores, &other.cpu_cores);
+        compare("kernel", &self.kernel, &other.kernel);
+        differences
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// The model name of the first CPU and the number of logical CPUs.
+fn parse_cpuinfo(cpuinfo: &str) -> (String, usize) {
+    let field = |line: &str, name: &str| {
+        line.split_once(':')
+            .filter(|(key, _)| key.trim() == name)
+            .map(|(_, value)| value.trim().to_string())
+    };
+    let model = cpuinfo
+        .lines()
+        .find_map(|l| field(l, "model name"))
+        .unwrap_or_default();
+    let cores = cpuinfo
+        .lines()
+        .filter(|l| field(l, "processor").is_some())
+        .count();
+    (model, cores)
+}
+
+#[cfg(test)]
+mod test_metadata {
+    use super::{parse_cpuinfo, Metadata, ToolchainIdentity};
+
+    #[test]
+    fn test_parse_cpuinfo() {
+        let cpuinfo = "processor\t: 0\nvendor_id\t: GenuineIntel\nmodel\t\t: 207\n\
+                       model name\t: Intel(R) Xeon(R) Processor\n\n\
+                       processor\t: 1\nmodel name\t: Intel(R) Xeon(R) Processor\n";
+        assert_eq!(
+            parse_cpuinfo(cpuinfo),
+            ("Intel(R) Xeon(R) Processor".to_string(), 2)
+        );
+        assert_eq!(parse_cpuinfo(""), (String::new(), 0));
+    }
+
+    #[test]
+    fn test_host_differences() {
+        let a = Metadata {
+            hostname: "bench-1".to_string(),
+            cpu_model: "Intel(R) Xeon(R) Processor".to_string(),
+            cpu_cores: 8,
+            kernel: "6.1.0".to_string(),
+            rustc_version: "rustc 1.75.0".to_string(),
+            ..Default::default()
+        };
+        let mut b = Metadata {
+            rustc_version: "rustc 1.76.0".to_string(),
+            ..a.clone()
+        };
+        assert!(a.host_differences(&b).is_empty());
+
+        b.hostname = "bench-2".to_string();
+        b.cpu_cores = 16;
+        assert_eq!(
+            a.host_differences(&b),
+            vec![
+                "hostname: \"bench-1\" != \"bench-2\"",
+                "cpu cores: \"8\" != \"16\""
+            ]
+        );
+
+        // Files written before metadata was collected have none of it.
+        let old: Metadata = serde_json::from_str("{}").unwrap();
+        assert_eq!(old, Metadata::default());
+    }
+
+    /// Test for ToolchainIdentity
+    ///
+    /// Step1. Parse the `rustc -vV` of a nightly and check its name and
+    /// the names it matches.
+    ///
+    /// Step2. Parse the ones of a beta and of a dev build without git
+    /// information.
+    #[test]
+    fn test_toolchain_identity() {
+        let nightly = ToolchainIdentity::parse(
+            "rustc 1.76.0-nightly (a1a37735c 2023-12-11)\nbinary: rustc\n\
+             commit-hash: a1a37735cbc3db359d0b24ba9085c9fcbe1bc274\n\
+             commit-date: 2023-12-11\nhost: x86_64-unknown-linux-gnu\n\
+             release: 1.76.0-nightly\nLLVM version: 17.0.5\n",
+        );
+        assert_eq!(nightly.channel, "nightly");
+        assert_eq!(nightly.commit_date, "2023-12-11");
+        assert_eq!(nightly.name(), "a1a37735cbc3db359d0b24ba9085c9fcbe1bc274");
+        assert!(nightly.matches("a1a3773"));
+        assert!(!nightly.matches("a1a37"));
+        assert!(nightly.matches("1.76.0-nightly"));
+
+        let nightly = nightly.with_label(Some(String::from("patched")));
+        assert_eq!(nightly.name(), "patched");
+        assert!(nightly.matches("patched"));
+
+        let beta = ToolchainIdentity::parse("release: 1.75.0-beta.2\ncommit-hash: 0123456\n");
+        assert_eq!(beta.channel, "beta");
+
+        let dev = ToolchainIdentity::parse(
+            "commit-hash: unknown\ncommit-date: unknown\nrelease: 1.77.0-dev\n",
+        );
+        assert_eq!((dev.channel.as_str(), dev.name()), ("dev", "1.77.0-dev"));
+        assert_eq!(ToolchainIdentity::parse(""), ToolchainIdentity::default());
+    }
+}
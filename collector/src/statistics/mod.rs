@@ -1,3 +1,31 @@
+pub mod aggregate;
 pub mod compile_time_stat;
+pub mod metadata;
+pub mod paired;
 pub mod runtime_stat;
+pub mod significance;
+pub mod stability;
 pub mod statistic;
+pub mod summary;
+
+use crate::execute::Stats;
+
+/// Union of the events reported missing by any of `stats`.
+pub fn missing_events<'a>(stats: impl Iterator<Item = &'a Stats>) -> Vec<String> {
+    let mut missing = vec![];
+    stats.flat_map(|s| s.missing.iter()).for_each(|m| {
+        if !missing.contains(m) {
+            missing.push(m.clone());
+        }
+    });
+    missing
+}
+
+/// Number of iterations killed after running into their timeout.
+pub fn timed_out_count<'a>(stats: impl Iterator<Item = &'a Stats>) -> usize {
+    stats.filter(|s| s.timed_out).count()
+}
+
+pub(crate) fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
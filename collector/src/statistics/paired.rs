@@ -0,0 +1,250 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::benchmark::{profile::Profile, scenario::Scenario};
+
+use super::{
+    compile_time_stat::CompileTimeBenchResult,
+    runtime_stat::RuntimeResult,
+    significance::{Comparison, SignificanceTest},
+    statistic::geometric_mean,
+};
+
+/// Change of a metric of one benchmark configuration between the baseline
+/// and another toolchain measured in the same interleaved run, where
+/// iteration `i` of one toolchain ran right next to iteration `i` of the
+/// other.
+#[derive(Debug, Serialize)]
+pub struct PairedComparison {
+    pub benchmark: String,
+    /// Missing for runtime benchmarks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_config: Option<String>,
+    /// Missing for runtime benchmarks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<Scenario>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
+    pub metric: String,
+    /// Id of the baseline toolchain.
+    pub baseline: String,
+    pub toolchain: String,
+    /// Number of iterations measured by both toolchains.
+    pub pairs: usize,
+    /// Geometric mean of the ratios of the toolchain to the baseline, pair
+    /// by pair. `None` without pairs or with a value that is not positive.
+    pub ratio: Option<f64>,
+    /// Comparison of all samples of both toolchains, `None` with too few.
+    pub comparison: Option<Comparison>,
+}
+
+/// Benchmark, profile, build config, scenario, patch and metric of a sample.
+type Key = (
+    String,
+    Option<Profile>,
+    Option<String>,
+    Option<Scenario>,
+    Option<String>,
+    String,
+);
+
+/// Measured samples of one toolchain by iteration.
+type Samples = HashMap<Key, BTreeMap<usize, f64>>;
+
+impl PairedComparison {
+    /// Compare the compile-time results of every toolchain in `runs` with
+    /// the first one. Warm-up runs and timed-out iterations are left out.
+    pub fn compile_time(
+        runs: &[(String, Vec<CompileTimeBenchResult>)],
+        test: SignificanceTest,
+    ) -> Vec<Self> {
+        let samples = |results: &[CompileTimeBenchResult]| {
+            let mut samples = Samples::new();
+            results
+                .iter()
+                .flat_map(|r| r.result_vec.iter())
+                .filter(|r| !r.warmup && !r.stats.timed_out)
+                .for_each(|r| {
+                    for (metric, value) in r.stats.iter() {
+                        let key = (
+                            r.benchmark.clone(),
+                            Some(r.profile),
+                            r.build_config.clone(),
+                            Some(r.scenario),
+                            r.patch.clone(),
+                            metric.to_string(),
+                        );
+                        samples.entry(key).or_default().insert(r.iteration, value);
+                    }
+                });
+            samples
+        };
+        Self::compare(
+            &runs
+                .iter()
+                .map(|(id, results)| (id.clone(), samples(results)))
+                .collect::<Vec<_>>(),
+            test,
+        )
+    }
+
+    /// Compare the runtime results of every toolchain in `runs` with the
+    /// first one, see [`PairedComparison::compile_time`].
+    pub fn runtime(runs: &[(String, Vec<RuntimeResult>)], test: SignificanceTest) -> Vec<Self> {
+        let samples = |results: &[RuntimeResult]| {
+            let mut samples = Samples::new();
+            for r in results {
+                for (iteration, stats) in r.stats.iter().enumerate() {
+                    if stats.timed_out {
+                        continue;
+                    }
+                    for (metric, value) in stats.iter() {
+                        let key = (
+                            r.name.clone(),
+                            None,
+                            r.build_config.clone(),
+                            None,
+                            None,
+                            metric.to_string(),
+                        );
+                        samples.entry(key).or_default().insert(iteration, value);
+                    }
+                }
+            }
+            samples
+        };
+        Self::compare(
+            &runs
+                .iter()
+                .map(|(id, results)| (id.clone(), samples(results)))
+                .collect::<Vec<_>>(),
+            test,
+        )
+    }
+
+    fn compare(runs: &[(String, Samples)], test: SignificanceTest) -> Vec<Self> {
+        let Some(((baseline, base), others)) = runs.split_first() else {
+            return vec![];
+        };
+        let mut comparisons = vec![];
+        for (toolchain, samples) in others {
+            for (key, old) in base {
+                let Some(new) = samples.get(key) else {
+                    continue;
+                };
+                let ratios: Vec<f64> = old
+                    .iter()
+                    .filter_map(|(i, o)| new.get(i).map(|n| n / o))
+                    .collect();
+                // A baseline value of zero leaves no ratio.
+                let ratio = if ratios.iter().all(|r| r.is_finite()) {
+                    geometric_mean(&ratios, 0.0)
+                } else {
+                    None
+                };
+                let (old, new): (Vec<f64>, Vec<f64>) = (
+                    old.values().copied().collect(),
+                    new.values().copied().collect(),
+                );
+                let (benchmark, profile, build_config, scenario, patch, metric) = key.clone();
+                comparisons.push(PairedComparison {
+                    benchmark,
+                    profile,
+                    build_config,
+                    scenario,
+                    patch,
+                    metric,
+                    baseline: baseline.clone(),
+                    toolchain: toolchain.clone(),
+                    pairs: ratios.len(),
+                    ratio,
+                    comparison: Comparison::new(&old, &new, test),
+                });
+            }
+        }
+        comparisons.sort_by_key(|c| {
+            (
+                c.toolchain.clone(),
+                c.benchmark.clone(),
+                c.profile.map(|p| p.to_string()),
+                c.build_config.clone(),
+                c.scenario.map(|s| s.to_string()),
+                c.patch.clone(),
+                c.metric.clone(),
+            )
+        });
+        comparisons
+    }
+
+    /// Number of comparisons by toolchain and verdict, with `none` for the
+    /// ones without enough samples.
+    pub fn tally(comparisons: &[Self]) -> BTreeMap<(String, String), usize> {
+        let mut counts = BTreeMap::new();
+        comparisons.iter().for_each(|c| {
+            let verdict = match &c.comparison {
+                Some(comparison) => comparison.verdict.to_string(),
+                None => String::from("none"),
+            };
+            *counts.entry((c.toolchain.clone(), verdict)).or_default() += 1;
+        });
+        counts
+    }
+}
+
+#[cfg(test)]
+mod test_paired {
+    use crate::{
+        execute::Stats,
+        statistics::{runtime_stat::RuntimeResult, significance::SignificanceTest},
+    };
+
+    use super::PairedComparison;
+
+    fn result(name: &str, values: &[f64]) -> RuntimeResult {
+        let mut result = RuntimeResult::new(name.to_string());
+        for v in values {
+            let mut stats = Stats::new();
+            stats.insert(String::from("wall-time"), *v);
+            result.append(stats);
+        }
+        result
+    }
+
+    /// Test for PairedComparison
+    ///
+    /// Step1. Pair the runtime results of a baseline and another toolchain
+    /// whose iterations drift together, and check the paired ratio.
+    ///
+    /// Step2. Check a benchmark measured by the baseline only is left out of
+    /// the comparisons and their tally.
+    #[test]
+    fn test_paired_comparison() {
+        let baseline = vec![
+            result("a", &[10.0, 20.0, 30.0, 40.0, 50.0, 60.0]),
+            result("b", &[1.0, 2.0]),
+        ];
+        let other = vec![result("a", &[11.0, 22.0, 33.0, 44.0, 55.0, 66.0])];
+        let comparisons = PairedComparison::runtime(
+            &[
+                (String::from("old"), baseline),
+                (String::from("new"), other),
+            ],
+            SignificanceTest::default(),
+        );
+
+        assert_eq!(comparisons.len(), 1);
+        let c = &comparisons[0];
+        assert_eq!((c.benchmark.as_str(), c.baseline.as_str()), ("a", "old"));
+        assert_eq!(c.pairs, 6);
+        // Every pair differs by 10%, though the samples overlap.
+        assert!((c.ratio.unwrap() - 1.1).abs() < 1e-12);
+        assert!(c.comparison.is_some());
+
+        let tally = PairedComparison::tally(&comparisons);
+        assert_eq!(tally.keys().next().unwrap().0, "new");
+        assert_eq!(tally.values().sum::<usize>(), 1);
+    }
+}
@@ -2,37 +2,90 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{execute::Stats, statistics::statistic::Statistics};
+use crate::{
+    execute::Stats,
+    statistics::{
+        is_zero, metadata::Metadata, missing_events, stability::StopRule, statistic::Statistics,
+        timed_out_count,
+    },
+};
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct RuntimeResultVec(pub Vec<RuntimeResult>);
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(from = "RuntimeResultFile")]
+pub struct RuntimeResultVec {
+    pub results: Vec<RuntimeResult>,
+    /// Environment the results were measured in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+}
+
+/// Runtime results files were a bare array before they held metadata.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RuntimeResultFile {
+    Set {
+        results: Vec<RuntimeResult>,
+        #[serde(default)]
+        metadata: Option<Box<Metadata>>,
+    },
+    Bare(Vec<RuntimeResult>),
+}
+
+impl From<RuntimeResultFile> for RuntimeResultVec {
+    fn from(file: RuntimeResultFile) -> Self {
+        match file {
+            RuntimeResultFile::Set { results, metadata } => RuntimeResultVec {
+                results,
+                metadata: metadata.map(|m| *m),
+            },
+            RuntimeResultFile::Bare(results) => RuntimeResultVec {
+                results,
+                metadata: None,
+            },
+        }
+    }
+}
 
 impl RuntimeResultVec {
     pub fn calculate_statistics(&self) -> RuntimeStatistics {
+        self.calculate_shifted_statistics(0.0)
+    }
+
+    /// Statistics of the results with geometric means of the samples
+    /// shifted by `epsilon`, see [`Statistics::shifted`].
+    pub fn calculate_shifted_statistics(&self, epsilon: f64) -> RuntimeStatistics {
         let mut statistics = RuntimeStatistics::new();
-        if self.0.len() == 0 {
+        if self.results.len() == 0 {
             return statistics;
         }
-        self.0.iter().for_each(|result| {
-            let mut stat_map = HashMap::<String, Vec<f64>>::new();
+        self.results.iter().for_each(|result| {
+            // Samples by label, with the iteration of each sample, which
+            // skip the timed-out iterations.
+            let mut stat_map = HashMap::<String, (Vec<usize>, Vec<f64>)>::new();
 
-            result.stats.iter().for_each(|stat| {
+            result.stats.iter().enumerate().for_each(|(i, stat)| {
                 stat.stats.iter().for_each(|(label, val)| {
-                    if stat_map.contains_key(label) {
-                        stat_map.get_mut(label).unwrap().push(val.clone());
-                    } else {
-                        stat_map.insert(label.clone(), vec![val.clone()]);
-                    }
+                    let (iterations, vals) = stat_map.entry(label.clone()).or_default();
+                    iterations.push(i + 1);
+                    vals.push(*val);
                 });
             });
 
-            let mut statistic_vec = vec![];
-            stat_map.iter().for_each(|(label, vals)| {
-                statistic_vec.push((label.clone(), Statistics::from(vals.clone())));
-            });
+            let statistic_vec = stat_map
+                .into_iter()
+                .map(|(label, (iterations, vals))| {
+                    let statistics =
+                        Statistics::shifted(vals, epsilon).with_iterations(&iterations);
+                    (label, statistics)
+                })
+                .collect();
             statistics.push(RuntimeStatistic {
                 name: result.name.clone(),
+                build_config: result.build_config.clone(),
+                iterations_used: result.stats.len() as u32,
                 statistic_vec,
+                missing: missing_events(result.stats.iter()),
+                timed_out: timed_out_count(result.stats.iter()),
             });
         });
         statistics
@@ -43,27 +96,80 @@ impl RuntimeResultVec {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RuntimeResult {
     pub name: String,
+    /// Name of the build config of the suite the benchmark was built with,
+    /// missing for the build without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_config: Option<String>,
     pub stats: Vec<Stats>,
+    /// Stats of the warm-up runs before the measured iterations, left out
+    /// of the statistics.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warmup: Vec<Stats>,
+    #[serde(skip)]
+    warmup_runs: usize,
 }
 
 impl RuntimeResult {
     pub fn new(name: String) -> Self {
         RuntimeResult {
             name,
+            build_config: None,
             stats: vec![],
+            warmup: vec![],
+            warmup_runs: 0,
         }
     }
 
+    /// Record the first `warmup_runs` appended stats as warm-up runs.
+    pub fn with_warmup(mut self, warmup_runs: usize) -> Self {
+        self.warmup_runs = warmup_runs;
+        self
+    }
+
+    pub fn with_build_config(mut self, build_config: Option<&str>) -> Self {
+        self.build_config = build_config.map(String::from);
+        self
+    }
+
     pub fn append(&mut self, stats: Stats) -> &Self {
-        self.stats.push(stats);
+        if self.warmup.len() < self.warmup_runs {
+            self.warmup.push(stats);
+        } else {
+            self.stats.push(stats);
+        }
         self
     }
+
+    /// Whether sampling can stop before the maximum iteration count.
+    pub fn is_stable(&self, stop_rule: Option<&StopRule>) -> bool {
+        let stable = stop_rule.is_some_and(|rule| rule.is_stable(self.stats.iter()));
+        if stable {
+            eprintln!(
+                "'{}' is stable after {} iterations",
+                self.name,
+                self.stats.len()
+            );
+        }
+        stable
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct RuntimeStatistic {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_config: Option<String>,
+    /// Iterations actually run, see `StopRule`.
+    #[serde(default)]
+    pub iterations_used: u32,
     pub statistic_vec: Vec<(String, Statistics)>,
+    /// Events that could not be counted in at least one iteration.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+    /// Iterations killed after running into their timeout, they are not
+    /// part of `statistic_vec`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub timed_out: usize,
 }
 
 pub type RuntimeStatistics = Vec<RuntimeStatistic>;
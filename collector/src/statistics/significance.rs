@@ -0,0 +1,453 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::rng::SplitMix64;
+
+/// Significance level below which a change is not considered noise.
+pub const ALPHA: f64 = 0.05;
+
+/// Resamples drawn for the bootstrap confidence interval of the ratio.
+const BOOTSTRAP_RESAMPLES: usize = 10_000;
+
+/// Exact p-values of the Mann-Whitney U test are computed up to this product
+/// of sample sizes, the normal approximation is used beyond.
+const EXACT_MANN_WHITNEY_LIMIT: usize = 400;
+
+/// Test deciding whether two sets of samples come from the same
+/// distribution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignificanceTest {
+    /// Rank-based, robust against outliers. Needs at least 4 samples on
+    /// each side to reach `ALPHA`.
+    #[default]
+    MannWhitney,
+    /// Welch's t-test, assumes normally distributed samples.
+    Welch,
+}
+
+impl FromStr for SignificanceTest {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mann-whitney" => Ok(Self::MannWhitney),
+            "welch" => Ok(Self::Welch),
+            _ => Err(format!("Unknown significance test {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Verdict {
+    SignificantImprovement,
+    SignificantRegression,
+    Noise,
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::SignificantImprovement => f.write_str("significant-improvement"),
+            Verdict::SignificantRegression => f.write_str("significant-regression"),
+            Verdict::Noise => f.write_str("noise"),
+        }
+    }
+}
+
+/// Comparison of the samples of a metric measured with an old and a new
+/// toolchain. Lower values are better, as for every metric the collector
+/// records.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comparison {
+    pub old_mean: f64,
+    pub new_mean: f64,
+    /// Change of the mean in percent.
+    pub change: f64,
+    pub p_value: f64,
+    /// 95% bootstrap confidence interval of `new_mean / old_mean`.
+    pub ratio_ci: (f64, f64),
+    /// Cliff's delta, from -1 when every new sample is smaller than every
+    /// old one to 1 when every new sample is larger.
+    pub effect_size: f64,
+    pub verdict: Verdict,
+}
+
+impl Comparison {
+    /// Compare `new` with `old`. A change is significant when the p-value of
+    /// `test` is below `ALPHA` and the confidence interval of the ratio
+    /// excludes 1. `None` with less than two samples on either side or an
+    /// old mean of zero.
+    pub fn new(old: &[f64], new: &[f64], test: SignificanceTest) -> Option<Self> {
+        if old.len() < 2 || new.len() < 2 {
+            return None;
+        }
+        let (old_mean, new_mean) = (mean(old), mean(new));
+        if old_mean == 0.0 {
+            return None;
+        }
+
+        let p_value = match test {
+            SignificanceTest::MannWhitney => mann_whitney_u(old, new),
+            SignificanceTest::Welch => welch_t_test(old, new),
+        };
+        let ratio_ci = bootstrap_ratio_ci(old, new);
+        let verdict = if p_value >= ALPHA || (ratio_ci.0 <= 1.0 && ratio_ci.1 >= 1.0) {
+            Verdict::Noise
+        } else if new_mean > old_mean {
+            Verdict::SignificantRegression
+        } else {
+            Verdict::SignificantImprovement
+        };
+
+        Some(Comparison {
+            old_mean,
+            new_mean,
+            change: (new_mean - old_mean) / old_mean * 100.0,
+            p_value,
+            ratio_ci,
+            effect_size: cliffs_delta(old, new),
+            verdict,
+        })
+    }
+}
+
+/// Write the comparison of every benchmark to the csv file `path`, one
+/// benchmark per line, sorted by name.
+pub fn write_comparisons_csv<'a>(
+    comparisons: impl IntoIterator<Item = (&'a String, &'a Comparison)>,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    let mut data_sorted = comparisons.into_iter().collect::<Vec<_>>();
+    data_sorted.sort_by(|a, b| a.0.cmp(b.0));
+
+    writer.write_all(
+        "benchmark,old_mean,new_mean,change,p_value,ratio_ci_low,ratio_ci_high,effect_size,verdict\n"
+            .as_bytes(),
+    )?;
+    for (n, c) in data_sorted {
+        writer.write_all(
+            format!(
+                "{},{:.2},{:.2},{:.2},{:.4},{:.4},{:.4},{:.2},{}\n",
+                n,
+                c.old_mean,
+                c.new_mean,
+                c.change,
+                c.p_value,
+                c.ratio_ci.0,
+                c.ratio_ci.1,
+                c.effect_size,
+                c.verdict
+            )
+            .as_bytes(),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn mean(data: &[f64]) -> f64 {
+    data.iter().sum::<f64>() / data.len() as f64
+}
+
+fn sample_variance(data: &[f64]) -> f64 {
+    let mean = mean(data);
+    data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (data.len() - 1) as f64
+}
+
+/// Two-sided p-value of the Mann-Whitney U test.
+pub fn mann_whitney_u(a: &[f64], b: &[f64]) -> f64 {
+    let (m, n) = (a.len(), b.len());
+    let mut all: Vec<(f64, bool)> = a
+        .iter()
+        .map(|x| (*x, true))
+        .chain(b.iter().map(|x| (*x, false)))
+        .collect();
+    all.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    // Ranks starting at 1, tied values get the mean of their ranks.
+    let mut rank_sum_a = 0.0;
+    let mut tie_correction = 0.0;
+    let mut i = 0;
+    while i < all.len() {
+        let j = (i..all.len())
+            .find(|&j| all[j].0 != all[i].0)
+            .unwrap_or(all.len());
+        let rank = (i + 1 + j) as f64 / 2.0;
+        rank_sum_a += rank * all[i..j].iter().filter(|x| x.1).count() as f64;
+        let t = (j - i) as f64;
+        tie_correction += t * t * t - t;
+        i = j;
+    }
+    let u = rank_sum_a - (m * (m + 1)) as f64 / 2.0;
+
+    if tie_correction == 0.0 && m * n <= EXACT_MANN_WHITNEY_LIMIT {
+        let counts = mann_whitney_distribution(m, n);
+        let total: f64 = counts.iter().sum();
+        let u = u as usize;
+        let lower: f64 = counts[..=u].iter().sum();
+        let upper: f64 = counts[u..].iter().sum();
+        return (2.0 * lower.min(upper) / total).min(1.0);
+    }
+
+    let (m, n) = (m as f64, n as f64);
+    let variance = m * n / 12.0 * ((m + n + 1.0) - tie_correction / ((m + n) * (m + n - 1.0)));
+    if variance == 0.0 {
+        return 1.0;
+    }
+    let z = ((u - m * n / 2.0).abs() - 0.5).max(0.0) / variance.sqrt();
+    erfc(z / std::f64::consts::SQRT_2).min(1.0)
+}
+
+/// Number of orderings of `m` and `n` distinct samples for every value of U.
+fn mann_whitney_distribution(m: usize, n: usize) -> Vec<f64> {
+    // counts[j][u] for `i` samples of the first and `j` of the second set.
+    let mut counts: Vec<Vec<f64>> = (0..=n).map(|_| vec![1.0]).collect();
+    for i in 1..=m {
+        let mut next: Vec<Vec<f64>> = vec![vec![1.0]];
+        for j in 1..=n {
+            // The largest sample is either of the first set, above all `j`
+            // samples of the second one, or of the second set.
+            let mut row = vec![0.0; i * j + 1];
+            for (u, c) in counts[j].iter().enumerate() {
+                row[u + j] += c;
+            }
+            for (u, c) in next[j - 1].iter().enumerate() {
+                row[u] += c;
+            }
+            next.push(row);
+        }
+        counts = next;
+    }
+    counts.swap_remove(n)
+}
+
+/// Two-sided p-value of Welch's t-test.
+pub fn welch_t_test(a: &[f64], b: &[f64]) -> f64 {
+    let (m, n) = (a.len() as f64, b.len() as f64);
+    let (va, vb) = (sample_variance(a) / m, sample_variance(b) / n);
+    let diff = mean(a) - mean(b);
+    if va + vb == 0.0 {
+        return if diff == 0.0 { 1.0 } else { 0.0 };
+    }
+    let t = diff / (va + vb).sqrt();
+    let df = (va + vb).powi(2) / (va * va / (m - 1.0) + vb * vb / (n - 1.0));
+    incomplete_beta(df / 2.0, 0.5, df / (df + t * t))
+}
+
+/// Cliff's delta of `new` over `old`.
+fn cliffs_delta(old: &[f64], new: &[f64]) -> f64 {
+    let dominance: i64 = new
+        .iter()
+        .flat_map(|y| old.iter().map(move |x| (y > x) as i64 - (y < x) as i64))
+        .sum();
+    dominance as f64 / (old.len() * new.len()) as f64
+}
+
+/// Percentile bootstrap of the ratio of the means. The generator is seeded
+/// so that a comparison always gives the same interval.
+fn bootstrap_ratio_ci(old: &[f64], new: &[f64]) -> (f64, f64) {
+    let mut rng = SplitMix64::new(0x5eed);
+    let mut resample_mean = |data: &[f64]| {
+        (0..data.len())
+            .map(|_| data[rng.below(data.len())])
+            .sum::<f64>()
+            / data.len() as f64
+    };
+    let mut ratios: Vec<f64> = (0..BOOTSTRAP_RESAMPLES)
+        .map(|_| {
+            let old_mean = resample_mean(old);
+            resample_mean(new) / old_mean
+        })
+        .filter(|r| r.is_finite())
+        .collect();
+    if ratios.is_empty() {
+        return (f64::NAN, f64::NAN);
+    }
+    ratios.sort_by(f64::total_cmp);
+    let at = |q: f64| ratios[((ratios.len() - 1) as f64 * q).round() as usize];
+    (at(0.025), at(0.975))
+}
+
+/// Complementary error function, with a relative error below 1.2e-7.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t
+        * (-z * z - 1.26551223
+            + t * (1.00002368
+                + t * (0.37409196
+                    + t * (0.09678418
+                        + t * (-0.18628806
+                            + t * (0.27886807
+                                + t * (-1.13520398
+                                    + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+            .exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .fold(1.000000000190015, |s, (i, c)| s + c / (x + 1.0 + i as f64));
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+/// Regularized incomplete beta function `I_x(a, b)`.
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // The continued fraction converges quickly below this point, use the
+    // symmetry of the function above it.
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+/// Continued fraction of the incomplete beta function by Lentz's method.
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let clamp = |v: f64| if v.abs() < TINY { TINY } else { v };
+    let mut c = 1.0;
+    let mut d = 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0));
+    let mut h = d;
+    for m in 1..300 {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / clamp(1.0 + even * d);
+        c = clamp(1.0 + even / c);
+        h *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / clamp(1.0 + odd * d);
+        c = clamp(1.0 + odd / c);
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    h
+}
+
+#[cfg(test)]
+mod test_significance {
+    use std::{
+        fs::{read_to_string, remove_file},
+        path::PathBuf,
+    };
+
+    use super::{
+        mann_whitney_u, welch_t_test, write_comparisons_csv, Comparison, SignificanceTest, Verdict,
+    };
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    /// Reference p-values computed by enumerating the orderings of the
+    /// samples and by integrating the density of the t distribution.
+    #[test]
+    fn test_p_values() {
+        let a = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let b = [6.0, 7.0, 8.0, 9.0, 10.0];
+        assert_close(mann_whitney_u(&a, &b), 0.0079365);
+        assert_close(mann_whitney_u(&a, &[2.5, 3.5, 4.5, 5.5]), 0.4126984);
+        // Tied samples use the normal approximation.
+        assert_close(
+            mann_whitney_u(&[1.0, 2.0, 2.0, 3.0], &[2.0, 3.0, 4.0, 4.0]),
+            0.1341692,
+        );
+        assert_close(mann_whitney_u(&[1.0, 1.0], &[1.0, 1.0]), 1.0);
+
+        assert_close(welch_t_test(&a, &b), 0.0010528);
+        assert_close(
+            welch_t_test(&[1.0, 2.0, 3.0], &[1.5, 4.0, 6.5, 2.0]),
+            0.3000957,
+        );
+        assert_close(welch_t_test(&[1.0, 1.0], &[1.0, 1.0]), 1.0);
+    }
+
+    #[test]
+    fn test_comparison() {
+        let old = [100.0, 101.0, 99.0, 100.5, 99.5];
+        let slower = [104.0, 105.0, 103.5, 104.5, 105.5];
+        let noisy = [95.0, 108.0, 99.0, 103.0, 97.0];
+
+        let c = Comparison::new(&old, &slower, SignificanceTest::MannWhitney).unwrap();
+        assert_eq!(c.verdict, Verdict::SignificantRegression);
+        assert_close(c.change, 4.5);
+        assert_eq!(c.effect_size, 1.0);
+        assert!(c.ratio_ci.0 > 1.0 && c.ratio_ci.1 < 1.1);
+
+        let c = Comparison::new(&slower, &old, SignificanceTest::Welch).unwrap();
+        assert_eq!(c.verdict, Verdict::SignificantImprovement);
+        assert_eq!(c.effect_size, -1.0);
+
+        let c = Comparison::new(&old, &noisy, SignificanceTest::MannWhitney).unwrap();
+        assert_eq!(c.verdict, Verdict::Noise);
+        assert!(c.ratio_ci.0 < 1.0 && c.ratio_ci.1 > 1.0);
+
+        assert!(Comparison::new(&old, &[104.0], SignificanceTest::Welch).is_none());
+    }
+
+    /// Test for write_comparisons_csv
+    ///
+    /// Step1. Write the comparisons of two benchmarks and check they are
+    /// sorted by name and rounded.
+    #[test]
+    fn test_write_comparisons_csv() {
+        let old = [100.0, 101.0, 99.0, 100.5, 99.5];
+        let new = [110.0, 111.0, 109.0, 110.5, 109.5];
+        let comparisons = vec![
+            (
+                String::from("b"),
+                Comparison::new(&old, &old, SignificanceTest::Welch).unwrap(),
+            ),
+            (
+                String::from("a"),
+                Comparison::new(&old, &new, SignificanceTest::Welch).unwrap(),
+            ),
+        ];
+        let path = PathBuf::from("test/csv_transfer/comparisons.csv");
+
+        write_comparisons_csv(comparisons.iter().map(|(n, c)| (n, c)), &path).unwrap();
+        let content = read_to_string(&path).unwrap();
+        remove_file(path).unwrap();
+
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("benchmark,old_mean,new_mean,change"));
+        assert!(lines[1].starts_with("a,100.00,110.00,10.00,"));
+        assert!(lines[2].starts_with("b,100.00,100.00,0.00,"));
+    }
+}
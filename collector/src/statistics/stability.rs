@@ -0,0 +1,93 @@
+use crate::execute::Stats;
+
+use super::statistic::relative_ci_width;
+
+/// When to stop sampling a benchmark before its maximum iteration count:
+/// once every chosen metric has a 95% confidence interval narrower than
+/// `target_ci_width` of its mean.
+#[derive(Debug, Clone)]
+pub struct StopRule {
+    pub min_iterations: usize,
+    /// Relative width of the confidence interval, e.g. `0.01` for 1%.
+    pub target_ci_width: f64,
+    pub metrics: Vec<String>,
+}
+
+impl StopRule {
+    /// Whether `stats`, the iterations of one benchmark configuration so
+    /// far, are stable. Timed-out iterations do not count.
+    pub fn is_stable<'a>(&self, stats: impl Iterator<Item = &'a Stats>) -> bool {
+        let measured: Vec<&Stats> = stats.filter(|s| !s.timed_out).collect();
+        if measured.len() < self.min_iterations.max(2) {
+            return false;
+        }
+        self.metrics.iter().all(|metric| {
+            let data: Vec<f64> = measured
+                .iter()
+                .filter_map(|s| s.stats.get(metric).copied())
+                .collect();
+            // Metrics the perf tool does not record do not hold sampling up.
+            data.is_empty()
+                || relative_ci_width(&data).is_some_and(|width| width <= self.target_ci_width)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_stability {
+    use crate::{
+        execute::Stats,
+        statistics::statistic::{relative_ci_width, Statistics},
+    };
+
+    use super::StopRule;
+
+    fn stats(instructions: f64, wall_time: f64) -> Stats {
+        let mut stats = Stats::new();
+        stats
+            .stats
+            .insert("instructions:u".to_string(), instructions);
+        stats.stats.insert("wall-time".to_string(), wall_time);
+        stats
+    }
+
+    #[test]
+    fn test_relative_ci_width() {
+        assert_eq!(relative_ci_width(&[1.0]), None);
+        assert_eq!(relative_ci_width(&[2.0, 2.0, 2.0]), Some(0.0));
+        // mean 2, sample sd 1, 2 * 4.303 * 1 / sqrt(3) / 2
+        let width = relative_ci_width(&[1.0, 2.0, 3.0]).unwrap();
+        assert!((width - 2.4843).abs() < 1e-3);
+
+        assert_eq!(Statistics::from(vec![1.0]).ci_width, None);
+        assert_eq!(Statistics::from(vec![1.0, 2.0, 3.0]).ci_width, Some(width));
+    }
+
+    #[test]
+    fn test_stop_rule() {
+        let rule = StopRule {
+            min_iterations: 3,
+            target_ci_width: 0.01,
+            metrics: vec!["instructions:u".to_string(), "wall-time".to_string()],
+        };
+        let stable = vec![stats(1000., 0.5), stats(1001., 0.5), stats(1000., 0.501)];
+        assert!(rule.is_stable(stable.iter()));
+        assert!(!rule.is_stable(stable[..2].iter()));
+
+        // Noisy wall-time holds sampling up.
+        let noisy = vec![stats(1000., 0.5), stats(1000., 0.9), stats(1000., 0.3)];
+        assert!(!rule.is_stable(noisy.iter()));
+
+        // Timed-out iterations do not count towards the minimum.
+        let mut timed_out = stable[..2].to_vec();
+        timed_out.push(Stats::timed_out());
+        assert!(!rule.is_stable(timed_out.iter()));
+
+        // Metrics that are not recorded are ignored.
+        let rule = StopRule {
+            metrics: vec!["cycles:u".to_string()],
+            ..rule
+        };
+        assert!(rule.is_stable(noisy.iter()));
+    }
+}
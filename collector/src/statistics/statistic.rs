@@ -4,7 +4,8 @@ use serde::{Deserialize, Serialize};
 pub struct Statistics {
     pub max: f64,
     pub min: f64,
-    pub geometric_mean: f64,
+    /// `None` if a sample is not positive, see [`geometric_mean`].
+    pub geometric_mean: Option<f64>,
     pub algebraic_mean: f64,
     pub variance: f64,
     pub standard_deviation: f64,
@@ -12,6 +13,35 @@ pub struct Statistics {
     pub q1: f64,
     /// 第三四分位点
     pub q3: f64,
+    #[serde(default)]
+    pub median: f64,
+    /// 95th percentile.
+    #[serde(default)]
+    pub p95: f64,
+    /// 99th percentile.
+    #[serde(default)]
+    pub p99: f64,
+    /// Variance with Bessel's correction, the unbiased estimate of the
+    /// variance of all runs from the measured ones.
+    #[serde(default)]
+    pub sample_variance: f64,
+    /// Median absolute deviation from the median.
+    #[serde(default)]
+    pub mad: f64,
+    /// Sample standard deviation relative to the mean, 0 if the mean is 0.
+    #[serde(default)]
+    pub coefficient_of_variation: f64,
+    /// Number of samples.
+    #[serde(default)]
+    pub count: usize,
+    /// Iterations, counted from 1, of the samples outside the Tukey fences,
+    /// `q1 - 1.5 * IQR` and `q3 + 1.5 * IQR`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outliers: Vec<usize>,
+    /// Width of the 95% confidence interval of the mean relative to the
+    /// mean, missing with less than two samples.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ci_width: Option<f64>,
 }
 
 impl Statistics {
@@ -19,36 +49,53 @@ impl Statistics {
         Statistics {
             max: 0.0,
             min: 0.0,
-            geometric_mean: 0.0,
+            geometric_mean: None,
             algebraic_mean: 0.0,
             variance: 0.0,
             standard_deviation: 0.0,
             q1: 0.0,
             q3: 0.0,
+            median: 0.0,
+            p95: 0.0,
+            p99: 0.0,
+            sample_variance: 0.0,
+            mad: 0.0,
+            coefficient_of_variation: 0.0,
+            count: 0,
+            outliers: vec![],
+            ci_width: None,
         }
     }
 
-    pub fn from(mut data: Vec<f64>) -> Self {
+    pub fn from(data: Vec<f64>) -> Self {
+        Self::shifted(data, 0.0)
+    }
+
+    /// Number the outliers by `iterations`, the iteration of each sample,
+    /// for samples that skip some iterations, e.g. timed-out ones.
+    pub fn with_iterations(mut self, iterations: &[usize]) -> Self {
+        self.outliers = self.outliers.iter().map(|i| iterations[i - 1]).collect();
+        self
+    }
+
+    /// Statistics of `data` whose geometric mean is taken of the samples
+    /// shifted by `epsilon`, see [`geometric_mean`].
+    pub fn shifted(mut data: Vec<f64>, epsilon: f64) -> Self {
         if data.len() == 0 {
             return Self::new();
         }
 
         let max;
         let min;
-        let mut geometric_mean = 1.0;
         let mut algebraic_mean = 0.0;
         let mut variance = 0.0;
         let standard_deviation;
 
+        let samples = data.clone();
         data.sort_by(|a, b| a.partial_cmp(b).unwrap());
         max = data.last().unwrap().clone();
         min = data.first().unwrap().clone();
 
-        //G_n=sqrt(PI(xi), n);
-        data.iter()
-            .for_each(|x| geometric_mean *= x.powf(1.0 / data.len() as f64));
-        // geometric_mean = geometric_mean.powf(1.0/data.len() as f64);
-
         //A_n=Sum(xi)/n;
         data.iter()
             .for_each(|x| algebraic_mean += x / data.len() as f64);
@@ -57,35 +104,179 @@ impl Statistics {
         //V=(Sum(xi-A)^2)/n
         data.iter()
             .for_each(|x| variance += (x - algebraic_mean).powi(2));
+
+        //Sv=(Sum(xi-A)^2)/(n-1)
+        let sample_variance = if data.len() > 1 {
+            variance / (data.len() - 1) as f64
+        } else {
+            0.0
+        };
         variance /= data.len() as f64;
 
         //Sd=sqrt(V)
         standard_deviation = variance.sqrt();
 
+        let median = quantile(&data, 0.5);
+        let mut deviations: Vec<f64> = data.iter().map(|x| (x - median).abs()).collect();
+        deviations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let q1 = quantile(&data, 0.25);
+        let q3 = quantile(&data, 0.75);
+        let iqr = q3 - q1;
+        let (low, high) = (q1 - 1.5 * iqr, q3 + 1.5 * iqr);
+
         Statistics {
             max,
             min,
-            geometric_mean,
+            geometric_mean: geometric_mean(&data, epsilon),
             algebraic_mean,
             variance,
             standard_deviation,
-            q1: quartile(&data, 0.25),
-            q3: quartile(&data, 0.75),
+            q1,
+            q3,
+            median,
+            p95: quantile(&data, 0.95),
+            p99: quantile(&data, 0.99),
+            sample_variance,
+            mad: quantile(&deviations, 0.5),
+            coefficient_of_variation: if algebraic_mean == 0.0 {
+                0.0
+            } else {
+                sample_variance.sqrt() / algebraic_mean.abs()
+            },
+            count: data.len(),
+            outliers: samples
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| **x < low || **x > high)
+                .map(|(i, _)| i + 1)
+                .collect(),
+            ci_width: relative_ci_width(&data),
         }
     }
 }
 
-/// calculate quartile_1 and quartile_3
-fn quartile(data: &Vec<f64>, percentile: f64) -> f64 {
+/// Geometric mean of `data`, the exponential of the mean logarithm, which
+/// does not overflow like the product of the samples. Every sample is
+/// shifted by `epsilon` and the mean is shifted back, so a positive
+/// `epsilon` admits zero samples of counters like `cache-misses`.
+///
+/// `None` if `data` is empty or a shifted sample is not positive, where the
+/// geometric mean is undefined.
+pub fn geometric_mean(data: &[f64], epsilon: f64) -> Option<f64> {
+    if data.is_empty() || !data.iter().all(|x| x + epsilon > 0.0) {
+        return None;
+    }
+    let log_sum: f64 = data.iter().map(|x| (x + epsilon).ln()).sum();
+    Some((log_sum / data.len() as f64).exp() - epsilon)
+}
+
+/// Two-sided 95% quantiles of Student's t-distribution for 1 to 30 degrees
+/// of freedom.
+const T_975: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// Width of the 95% confidence interval of the mean of `data`, relative to
+/// the mean. `None` with less than two samples.
+pub fn relative_ci_width(data: &[f64]) -> Option<f64> {
     let n = data.len();
-    let index = (percentile * (n - 1) as f64) as usize;
-    if n == 1 {
-        data[0]
-    } else if index == n - 1 {
+    if n < 2 {
+        return None;
+    }
+    let mean = data.iter().sum::<f64>() / n as f64;
+    // The sample variance, as `data` is only a sample of all runs.
+    let variance = data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+    let t = T_975.get(n - 2).copied().unwrap_or(1.96);
+    let width = 2.0 * t * (variance / n as f64).sqrt();
+    if width == 0.0 {
+        Some(0.0)
+    } else if mean == 0.0 {
+        None
+    } else {
+        Some(width / mean.abs())
+    }
+}
+
+/// The `p`th percentile of `data`, `p` in `[0, 100]`, interpolated
+/// linearly between the closest samples.
+pub fn percentile(data: &[f64], p: f64) -> f64 {
+    let mut data = data.to_vec();
+    data.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    quantile(&data, p / 100.0)
+}
+
+/// The `q` quantile of the sorted `data`, `q` in `[0, 1]`.
+fn quantile(data: &[f64], q: f64) -> f64 {
+    let n = data.len();
+    if n == 0 {
+        return 0.0;
+    }
+    let position = q.clamp(0.0, 1.0) * (n - 1) as f64;
+    let index = position.floor() as usize;
+    if index >= n - 1 {
         data[n - 1]
     } else {
         let lower = data[index];
         let upper = data[index + 1];
-        lower + (upper - lower) * (percentile * (n - 1) as f64 - index as f64)
+        lower + (upper - lower) * (position - index as f64)
+    }
+}
+
+#[cfg(test)]
+mod test_statistic {
+    use super::{geometric_mean, percentile, Statistics};
+
+    /// Test for Statistics
+    ///
+    /// Step1. Check the order statistics and spread of samples with an
+    /// outlier.
+    ///
+    /// Step2. Check the geometric mean is undefined for samples that are not
+    /// positive, unless shifted by an epsilon.
+    ///
+    /// Step3. Check statistics saved without the new fields still load.
+    #[test]
+    fn test_statistics() {
+        let stats = Statistics::from(vec![10.0, 12.0, 11.0, 13.0, 40.0, 12.0, 11.0]);
+        assert_eq!(stats.count, 7);
+        assert_eq!(stats.median, 12.0);
+        assert_eq!(stats.q1, 11.0);
+        assert_eq!(stats.q3, 12.5);
+        assert!((stats.p95 - 31.9).abs() < 1e-9);
+        assert_eq!(stats.mad, 1.0);
+        assert!((stats.sample_variance - stats.variance * 7.0 / 6.0).abs() < 1e-9);
+        assert!(
+            (stats.coefficient_of_variation - stats.sample_variance.sqrt() / stats.algebraic_mean)
+                .abs()
+                < 1e-12
+        );
+        assert_eq!(stats.outliers, vec![5]);
+        assert_eq!(percentile(&[3.0, 1.0, 2.0, 4.0], 50.0), 2.5);
+        assert_eq!(percentile(&[3.0, 1.0, 2.0, 4.0], 100.0), 4.0);
+
+        let one = Statistics::from(vec![5.0]);
+        assert_eq!((one.median, one.p99, one.sample_variance), (5.0, 5.0, 0.0));
+        assert!(one.outliers.is_empty());
+
+        assert!((stats.geometric_mean.unwrap() - 13.7005).abs() < 1e-4);
+        assert!((geometric_mean(&[1e300, 1e300], 0.0).unwrap() / 1e300 - 1.0).abs() < 1e-12);
+        assert_eq!(Statistics::from(vec![0.0, 4.0]).geometric_mean, None);
+        assert_eq!(Statistics::from(vec![-1.0, 4.0]).geometric_mean, None);
+        assert!((geometric_mean(&[0.0, 3.0], 1.0).unwrap() - 1.0).abs() < 1e-12);
+        let shifted = Statistics::shifted(vec![0.0, 3.0], 1.0);
+        assert!((shifted.geometric_mean.unwrap() - 1.0).abs() < 1e-12);
+        assert_eq!(geometric_mean(&[], 1.0), None);
+
+        let old: Statistics = serde_json::from_str(
+            r#"{"max":2.0,"min":1.0,"geometric_mean":1.4,"algebraic_mean":1.5,
+                "variance":0.25,"standard_deviation":0.5,"q1":1.25,"q3":1.75}"#,
+        )
+        .unwrap();
+        assert_eq!((old.count, old.median), (0, 0.0));
+        assert_eq!(old.geometric_mean, Some(1.4));
+        assert!(old.outliers.is_empty());
     }
 }
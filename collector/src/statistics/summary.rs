@@ -0,0 +1,371 @@
+use std::{collections::HashMap, fmt::Display, fs, path::Path};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::benchmark::{profile::Profile, scenario::Scenario};
+
+use super::{
+    aggregate::{Aggregate, Exclusion},
+    compile_time_stat::{CompileTimeResultSet, CompileTimeStatistics},
+    runtime_stat::{RuntimeResultVec, RuntimeStatistics},
+    statistic::Statistics,
+};
+
+/// Change of one metric over all benchmarks of a profile, build config and
+/// scenario.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SummaryEntry {
+    /// Missing for runtime benchmarks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_config: Option<String>,
+    /// Missing for runtime benchmarks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<Scenario>,
+    pub metric: String,
+    /// Weighted geometric mean of the ratios of the geometric mean of each
+    /// benchmark to the one of the baseline, missing if every benchmark was
+    /// excluded.
+    pub ratio: Option<f64>,
+    /// Number of benchmarks in `ratio`.
+    pub benchmarks: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded: Vec<(String, Exclusion)>,
+}
+
+/// Suite-wide score of a toolchain against a baseline, per profile, build
+/// config, scenario and metric.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Summary {
+    pub entries: Vec<SummaryEntry>,
+}
+
+/// The geometric means of one metric, by benchmark.
+type Values = HashMap<String, Option<f64>>;
+
+/// Statistics read by [`Summary::from_files`].
+enum StatisticsFile {
+    CompileTime(CompileTimeStatistics),
+    Runtime(RuntimeStatistics),
+}
+
+/// Read a `compile_time_statistics.json` or `runtime_statistics.json` file,
+/// or calculate the statistics of a `compile_time_results.json` or
+/// `runtime_results.json` file with the samples shifted by `epsilon`.
+fn read_statistics(file: &Path, epsilon: f64) -> anyhow::Result<StatisticsFile> {
+    let content = fs::read_to_string(file).with_context(|| format!("failed to read {:?}", file))?;
+    // Runtime statistics lack the profile and scenario of compile-time
+    // ones, so only compile-time statistics parse as such.
+    if let Ok(statistics) = serde_json::from_str(&content) {
+        return Ok(StatisticsFile::CompileTime(statistics));
+    }
+    if let Ok(statistics) = serde_json::from_str(&content) {
+        return Ok(StatisticsFile::Runtime(statistics));
+    }
+    if let Ok(results) = serde_json::from_str::<CompileTimeResultSet>(&content) {
+        return Ok(StatisticsFile::CompileTime(
+            results.calculate_shifted_statistics(epsilon),
+        ));
+    }
+    match serde_json::from_str::<RuntimeResultVec>(&content) {
+        Ok(results) => Ok(StatisticsFile::Runtime(
+            results.calculate_shifted_statistics(epsilon),
+        )),
+        Err(_) => bail!("{:?} is not a statistics or results file", file),
+    }
+}
+
+/// Profile, build config and scenario of a group of benchmarks.
+type GroupKey = (Option<Profile>, Option<String>, Option<Scenario>);
+
+/// Values of every metric of a group, of the new and the baseline
+/// statistics.
+type Groups = HashMap<(GroupKey, String), (Values, Values)>;
+
+fn add(
+    groups: &mut Groups,
+    key: GroupKey,
+    benchmark: &str,
+    statistic_vec: &[(String, Statistics)],
+    baseline: bool,
+) {
+    for (metric, stats) in statistic_vec {
+        let (new, old) = groups.entry((key.clone(), metric.clone())).or_default();
+        let values = if baseline { old } else { new };
+        values.insert(benchmark.to_string(), stats.geometric_mean);
+    }
+}
+
+impl Summary {
+    /// Summarize `stats` against `baseline`. Benchmarks count as often as
+    /// their weight in `weights`, once if they have none. Runs of the
+    /// `IncrPatched` scenario with different patches count as different
+    /// benchmarks.
+    pub fn compile_time(
+        stats: &CompileTimeStatistics,
+        baseline: &CompileTimeStatistics,
+        weights: &HashMap<String, f64>,
+        epsilon: f64,
+    ) -> Self {
+        let mut groups = Groups::new();
+        let mut names = HashMap::new();
+        for (statistics, is_baseline) in [(stats, false), (baseline, true)] {
+            for s in statistics {
+                let benchmark = match &s.patch {
+                    Some(patch) => format!("{}/{}", s.name, patch),
+                    None => s.name.clone(),
+                };
+                add(
+                    &mut groups,
+                    (Some(s.profile), s.build_config.clone(), Some(s.scenario)),
+                    &benchmark,
+                    &s.statistic_vec,
+                    is_baseline,
+                );
+                names.insert(benchmark, s.name.clone());
+            }
+        }
+        let weights = names
+            .into_iter()
+            .filter_map(|(benchmark, name)| weights.get(&name).map(|w| (benchmark, *w)))
+            .collect();
+        Self::from_groups(groups, &weights, epsilon)
+    }
+
+    /// Summarize runtime `stats` against `baseline`, see
+    /// [`Summary::compile_time`].
+    pub fn runtime(
+        stats: &RuntimeStatistics,
+        baseline: &RuntimeStatistics,
+        weights: &HashMap<String, f64>,
+        epsilon: f64,
+    ) -> Self {
+        let mut groups = Groups::new();
+        for (statistics, is_baseline) in [(stats, false), (baseline, true)] {
+            for s in statistics {
+                add(
+                    &mut groups,
+                    (None, s.build_config.clone(), None),
+                    &s.name,
+                    &s.statistic_vec,
+                    is_baseline,
+                );
+            }
+        }
+        Self::from_groups(groups, weights, epsilon)
+    }
+
+    /// Summarize the statistics file `stats` against the `baseline` file of
+    /// the same kind. Statistics are calculated from results files, with
+    /// the geometric means of their samples shifted by `epsilon`.
+    pub fn from_files(
+        stats: &Path,
+        baseline: &Path,
+        weights: &HashMap<String, f64>,
+        epsilon: f64,
+    ) -> anyhow::Result<Self> {
+        match (
+            read_statistics(stats, epsilon)?,
+            read_statistics(baseline, epsilon)?,
+        ) {
+            (StatisticsFile::CompileTime(new), StatisticsFile::CompileTime(old)) => {
+                Ok(Self::compile_time(&new, &old, weights, epsilon))
+            }
+            (StatisticsFile::Runtime(new), StatisticsFile::Runtime(old)) => {
+                Ok(Self::runtime(&new, &old, weights, epsilon))
+            }
+            _ => bail!("{:?} and {:?} are not of the same kind", stats, baseline),
+        }
+    }
+
+    fn from_groups(groups: Groups, weights: &HashMap<String, f64>, epsilon: f64) -> Self {
+        let mut entries: Vec<SummaryEntry> = groups
+            .into_iter()
+            .map(
+                |(((profile, build_config, scenario), metric), (new, old))| {
+                    let aggregate = Aggregate::weighted(&new, &old, epsilon, weights);
+                    SummaryEntry {
+                        profile,
+                        build_config,
+                        scenario,
+                        metric,
+                        ratio: aggregate.geometric_mean,
+                        benchmarks: aggregate.ratios.len(),
+                        excluded: aggregate.excluded,
+                    }
+                },
+            )
+            .collect();
+        entries.sort_by_key(|e| {
+            (
+                e.profile.map(|p| p.to_string()),
+                e.build_config.clone(),
+                e.scenario.map(|s| s.to_string()),
+                e.metric.clone(),
+            )
+        });
+        Summary { entries }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let show = |o: Option<String>| o.unwrap_or_else(|| String::from("-"));
+        writeln!(
+            f,
+            "{:<10} {:<16} {:<16} {:<30} {:>10} {:>10} {:>8}",
+            "profile", "build config", "scenario", "metric", "change", "benchmarks", "excluded"
+        )?;
+        for e in &self.entries {
+            writeln!(
+                f,
+                "{:<10} {:<16} {:<16} {:<30} {:>10} {:>10} {:>8}",
+                show(e.profile.map(|p| p.to_string())),
+                show(e.build_config.clone()),
+                show(e.scenario.map(|s| s.to_string())),
+                e.metric,
+                show(e.ratio.map(|r| format!("{:+.2}%", (r - 1.0) * 100.0))),
+                e.benchmarks,
+                e.excluded.len()
+            )?;
+        }
+        for e in self.entries.iter().filter(|e| !e.excluded.is_empty()) {
+            write!(f, "\nExcluded from {}", e.metric)?;
+            if let (Some(profile), Some(scenario)) = (e.profile, e.scenario) {
+                write!(f, " ({} {})", profile, scenario)?;
+            }
+            if let Some(build_config) = &e.build_config {
+                write!(f, " [{}]", build_config)?;
+            }
+            writeln!(f, ":")?;
+            for (benchmark, reason) in &e.excluded {
+                writeln!(f, "  {}: {}", benchmark, reason)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_summary {
+    use std::{
+        collections::HashMap,
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use tempfile::TempDir;
+
+    use crate::{
+        benchmark::{profile::Profile, scenario::Scenario},
+        execute::Stats,
+        statistics::{
+            aggregate::Exclusion,
+            compile_time_stat::{
+                CompileTimeBenchResult, CompileTimeResult, CompileTimeResultSet,
+                CompileTimeStatistics,
+            },
+            runtime_stat::RuntimeStatistics,
+        },
+    };
+
+    use super::Summary;
+
+    /// Test for Summary
+    ///
+    /// Step1. Summarize the compile-time statistics in `test/summary` against
+    /// a baseline, with and without weights.
+    ///
+    /// Step2. Summarize the runtime statistics.
+    ///
+    /// Step3. Summarize results files with zero samples and check an
+    /// epsilon shifting their samples keeps them.
+    #[test]
+    fn test_summary() {
+        let dir = Path::new("test/summary");
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+
+        let new: CompileTimeStatistics =
+            serde_json::from_str(&read("compile_time_statistics_new.json")).unwrap();
+        let old: CompileTimeStatistics =
+            serde_json::from_str(&read("compile_time_statistics_old.json")).unwrap();
+        let summary = Summary::compile_time(&new, &old, &HashMap::new(), 0.0);
+        let entry = summary
+            .entries
+            .iter()
+            .find(|e| {
+                e.profile == Some(Profile::Debug)
+                    && e.scenario == Some(Scenario::Full)
+                    && e.metric == "wall-time"
+            })
+            .unwrap();
+        // sqrt(0.9 * 1.1)
+        assert_eq!(entry.benchmarks, 2);
+        assert!((entry.ratio.unwrap() - 0.99499).abs() < 1e-5);
+        let entry = summary
+            .entries
+            .iter()
+            .find(|e| e.metric == "context-switches")
+            .unwrap();
+        assert_eq!(entry.benchmarks, 1);
+        assert_eq!(
+            entry.excluded,
+            vec![("b".to_string(), Exclusion::Undefined)]
+        );
+
+        let weights = [("a".to_string(), 3.0)].into_iter().collect();
+        let summary = Summary::compile_time(&new, &old, &weights, 0.0);
+        // (0.9^3 * 1.1)^(1/4)
+        assert!((summary.entries[1].ratio.unwrap() - 0.94630).abs() < 1e-5);
+        assert!(summary.to_string().contains("b: undefined geometric mean"));
+
+        let new: RuntimeStatistics =
+            serde_json::from_str(&read("runtime_statistics_new.json")).unwrap();
+        let old: RuntimeStatistics =
+            serde_json::from_str(&read("runtime_statistics_old.json")).unwrap();
+        let summary = Summary::runtime(&new, &old, &HashMap::new(), 0.0);
+        assert_eq!(summary.entries.len(), 1);
+        assert_eq!(summary.entries[0].profile, None);
+        assert!((summary.entries[0].ratio.unwrap() - 2.0).abs() < 1e-12);
+
+        let out_dir = TempDir::new().unwrap();
+        let results = |id: &str, samples: &[f64]| -> PathBuf {
+            let mut result = CompileTimeBenchResult::new(String::from("a"), samples.len());
+            for (i, x) in samples.iter().enumerate() {
+                let mut stats = Stats::new();
+                stats.insert(String::from("context-switches"), *x);
+                result.add_result(CompileTimeResult::new(
+                    String::from("a"),
+                    i + 1,
+                    Profile::Debug,
+                    Scenario::Full,
+                    stats,
+                ));
+            }
+            let path = out_dir.path().join(format!("{}.json", id));
+            let results = CompileTimeResultSet::new(id.to_string(), vec![result]);
+            fs::write(&path, serde_json::to_string(&results).unwrap()).unwrap();
+            path
+        };
+        let new = results("new", &[0.0, 0.0]);
+        let old = results("old", &[0.0, 3.0]);
+        let summary = Summary::from_files(&new, &old, &HashMap::new(), 0.0).unwrap();
+        assert_eq!(
+            summary.entries[0].excluded,
+            vec![("a".to_string(), Exclusion::Undefined)]
+        );
+        // (0 + 1) / sqrt((0 + 1) * (3 + 1))
+        let summary = Summary::from_files(&new, &old, &HashMap::new(), 1.0).unwrap();
+        assert_eq!(summary.entries[0].benchmarks, 1);
+        assert!((summary.entries[0].ratio.unwrap() - 0.5).abs() < 1e-12);
+        assert!(Summary::from_files(
+            &new,
+            &dir.join("runtime_statistics_old.json"),
+            &HashMap::new(),
+            1.0
+        )
+        .is_err());
+    }
+}
@@ -1,16 +1,31 @@
 use std::{
+    env,
     fmt::Display,
-    fs::{create_dir_all, File},
+    fs::{create_dir_all, read_dir, read_to_string, File},
     io::Write,
     path::{Path, PathBuf},
     process::Command,
     str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Context, Ok};
 use log::debug;
-
-use crate::benchmark::profile::{Profile, Profiles};
+use serde::Deserialize;
+
+use crate::{
+    benchmark::{
+        build_config::{BuildConfigs, DEFAULT_BUILD_CONFIG},
+        filter::BenchmarkFilter,
+        profile::{Profile, Profiles},
+        scenario::{Scenario, Scenarios},
+    },
+    db::{ExportFormat, ResultKind, Slice},
+    execute::host::{parse_cpu_set, HostConditions, CPU_SET_ENV},
+    statistics::{
+        metadata::ToolchainIdentity, significance::SignificanceTest, stability::StopRule,
+    },
+};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Compiler<'a> {
@@ -33,8 +48,8 @@ impl<'a> Compiler<'a> {
 pub struct LocalToolchain {
     pub rustc: PathBuf,
     pub cargo: PathBuf,
-    pub flame_graph: PathBuf,
     pub id: String,
+    pub identity: ToolchainIdentity,
 }
 
 impl Display for LocalToolchain {
@@ -42,16 +57,25 @@ impl Display for LocalToolchain {
         let rustc_s = String::from("rustc: ") + self.rustc.to_str().unwrap();
         let cargo_s = String::from("cargo: ") + self.cargo.to_str().unwrap();
         f.write_str((rustc_s + "\n" + &cargo_s).as_str())?;
+        if !self.identity.commit_hash.is_empty() {
+            write!(
+                f,
+                "\ncommit: {} {} ({})",
+                self.identity.commit_hash, self.identity.commit_date, self.identity.channel
+            )?;
+        }
         std::fmt::Result::Ok(())
     }
 }
 
 /// Get a toolchain from the input.
-/// - `rustc`: check if the given one is acceptable.
+/// - `rustc`: a `+`-prefixed rustup toolchain, a rustc executable, a
+///   sysroot directory or a [`ToolchainManifest`]. Check if it is acceptable
+///   and identify it with `rustc -vV`.
 /// - `rustdoc`: if one is given, check if it is acceptable. Otherwise, if
 ///   the `Doc` profile is requested, look for one next to the given `rustc`.
-/// - `cargo`: if one is given, check if it is acceptable. Otherwise, look
-///   for the nightly Cargo via `rustup`.
+/// - `cargo`: if one is given, or the manifest names one, check if it is
+///   acceptable. Otherwise, see [`find_cargo`].
 pub fn get_local_toolchain(
     rustc: &str,
     cargo: Option<&Path>,
@@ -61,6 +85,7 @@ pub fn get_local_toolchain(
     // `+`-prefixed rustc is an indicator to fetch the rustc of the toolchain
     // specified. This follows the similar pattern used by rustup's binaries
     // (e.g., `rustc +stage1`).
+    let mut manifest_cargo = None;
     let (rustc, id) = if let Some(toolchain) = rustc.strip_prefix('+') {
         let output = Command::new("rustup")
             .args(&["which", "rustc", "--toolchain", &toolchain])
@@ -118,7 +143,9 @@ pub fn get_local_toolchain(
         };
         (rustc, id)
     } else {
-        let rustc = PathBuf::from(rustc)
+        let (rustc, cargo) = resolve_rustc(Path::new(rustc))?;
+        manifest_cargo = cargo;
+        let rustc = rustc
             .canonicalize()
             .with_context(|| format!("failed to canonicalize rustc executable {:?}", rustc))?;
 
@@ -134,50 +161,95 @@ pub fn get_local_toolchain(
         (rustc, id)
     };
 
-    let cargo = if let Some(cargo) = &cargo {
-        cargo
+    let cargo = match cargo.map(Path::to_path_buf).or(manifest_cargo) {
+        Some(cargo) => cargo
             .canonicalize()
-            .with_context(|| format!("failed to canonicalize cargo executable {:?}", cargo))?
-    } else {
-        // Use the nightly cargo from `rustup`.
-        let output = Command::new("rustup")
-            .args(&["which", "cargo", "--toolchain=nightly"])
-            .output()
-            .context("failed to run `rustup which cargo --toolchain=nightly`")?;
-        if !output.status.success() {
-            anyhow::bail!(
-                "`rustup which cargo --toolchain=nightly` exited with status {}\nstderr={}",
-                output.status,
-                String::from_utf8_lossy(&output.stderr)
-            )
-        }
-        let s = String::from_utf8(output.stdout)
-            .context("failed to convert `rustup which cargo --toolchain=nightly` output to utf8")?;
-
-        let cargo = PathBuf::from(s.trim());
-        debug!("found cargo: {:?}", &cargo);
-        cargo
+            .with_context(|| format!("failed to canonicalize cargo executable {:?}", cargo))?,
+        None => find_cargo(&rustc)?,
     };
 
-    let flame_graph = {
-        let output = Command::new("which")
-            .arg("flamegraph")
-            .output()
-            .context("failed to run `which flamegraph")?;
-        let s = String::from_utf8(output.stdout)
-            .context("failed to convert `which flamegraph` output to utf8")?;
-
-        PathBuf::from(s.trim())
-    };
+    let identity = ToolchainIdentity::detect(&rustc)?;
 
     Ok(LocalToolchain {
         rustc,
         cargo,
-        flame_graph,
         id,
+        identity,
     })
 }
 
+/// A json file naming the executables of a toolchain, like
+/// `{"rustc": "bin/rustc", "cargo": "bin/cargo"}`, relative to the file.
+#[derive(Debug, Deserialize)]
+pub struct ToolchainManifest {
+    pub rustc: PathBuf,
+    pub cargo: Option<PathBuf>,
+}
+
+/// The rustc executable `path` stands for: the path itself, the `bin/rustc`
+/// of a sysroot directory or the rustc of a toolchain manifest, along with
+/// the cargo of the manifest.
+fn resolve_rustc(path: &Path) -> anyhow::Result<(PathBuf, Option<PathBuf>)> {
+    if path.is_dir() {
+        return Ok((path.join("bin").join(executable("rustc")), None));
+    }
+    if path.extension().is_some_and(|e| e == "json") {
+        let manifest: ToolchainManifest = serde_json::from_str(
+            &read_to_string(path).with_context(|| format!("failed to read {:?}", path))?,
+        )
+        .with_context(|| format!("{:?} is not a toolchain manifest", path))?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        return Ok((
+            dir.join(manifest.rustc),
+            manifest.cargo.map(|cargo| dir.join(cargo)),
+        ));
+    }
+    Ok((path.to_path_buf(), None))
+}
+
+/// The cargo of a toolchain without a given one: the cargo next to `rustc`,
+/// as in a sysroot, else `$CARGO`, else the nightly cargo of `rustup`.
+fn find_cargo(rustc: &Path) -> anyhow::Result<PathBuf> {
+    let sibling = rustc.with_file_name(executable("cargo"));
+    if sibling.is_file() {
+        debug!("found cargo: {:?}", &sibling);
+        return Ok(sibling);
+    }
+    if let Some(cargo) = env::var_os("CARGO") {
+        return PathBuf::from(&cargo)
+            .canonicalize()
+            .with_context(|| format!("failed to canonicalize $CARGO {:?}", cargo));
+    }
+
+    let output = Command::new("rustup")
+        .args(&["which", "cargo", "--toolchain=nightly"])
+        .output()
+        .with_context(|| {
+            format!(
+                "no cargo next to {:?}, pass one with `--cargo` or `$CARGO`",
+                rustc
+            )
+        })?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "`rustup which cargo --toolchain=nightly` exited with status {}\nstderr={}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        )
+    }
+    let s = String::from_utf8(output.stdout)
+        .context("failed to convert `rustup which cargo --toolchain=nightly` output to utf8")?;
+
+    let cargo = PathBuf::from(s.trim());
+    debug!("found cargo: {:?}", &cargo);
+    Ok(cargo)
+}
+
+/// The file name of executable `name` on this platform.
+fn executable(name: &str) -> String {
+    format!("{}{}", name, env::consts::EXE_SUFFIX)
+}
+
 #[derive(Debug, clap::Parser)]
 #[clap(about, version, author)]
 pub struct Cli {
@@ -187,25 +259,426 @@ pub struct Cli {
 
 #[derive(Debug, clap::Args)]
 pub struct LocalOptions {
-    /// The path to the local rustc to measure
+    /// The local rustc to measure: its path, its sysroot, a json toolchain
+    /// manifest or a `+`-prefixed rustup toolchain. `$RUSTC` by default
     // Not a `PathBuf` because it can be a file path *or* a `+`-prefixed
     // toolchain name, and `PathBuf` doesn't work well for the latter.
-    pub rustc: String,
+    pub rustc: Option<String>,
 
     /// Identifier to associate benchmark results with
     #[clap(long)]
     pub id: Option<String>,
 
+    /// Name of the toolchain recorded in the metadata of the results, next
+    /// to the commit, date and channel from `rustc -vV`
+    #[clap(long)]
+    pub label: Option<String>,
+
     /// The path to the local Cargo to use
     #[clap(long, parse(from_os_str))]
     pub cargo: Option<PathBuf>,
-    // /// Exclude all benchmarks matching a prefix in this comma-separated list
-    // #[clap(long)]
-    // pub exclude: Option<String>,
 
-    // /// Include only benchmarks matching a prefix in this comma-separated list
-    // #[clap(long)]
-    // pub include: Option<String>,
+    /// Exclude all benchmarks matching a prefix or glob in this
+    /// comma-separated list
+    #[clap(long)]
+    pub exclude: Option<String>,
+
+    /// Include only benchmarks matching a prefix or glob in this
+    /// comma-separated list
+    #[clap(long)]
+    pub include: Option<String>,
+
+    /// Include only benchmarks with one of the tags in this comma-separated
+    /// list
+    #[clap(long)]
+    pub tags: Option<String>,
+}
+
+impl LocalOptions {
+    /// The given rustc, or else `$RUSTC`.
+    pub fn rustc(&self) -> anyhow::Result<String> {
+        match &self.rustc {
+            Some(rustc) => Ok(rustc.clone()),
+            None => env::var("RUSTC").context("no rustc to measure, pass one or set `$RUSTC`"),
+        }
+    }
+
+    pub fn filter(&self) -> anyhow::Result<BenchmarkFilter> {
+        BenchmarkFilter::new(
+            self.include.as_deref(),
+            self.exclude.as_deref(),
+            self.tags.as_deref(),
+        )
+    }
+}
+
+/// Adaptive iteration count: with `--target-ci`, a benchmark is sampled
+/// until its metrics are stable instead of for a fixed `--iterations`.
+#[derive(Debug, clap::Args)]
+pub struct SamplingOptions {
+    /// Stop sampling once the 95% confidence interval of every stable metric
+    /// is narrower than this fraction of its mean, e.g. 0.01
+    #[clap(long = "target-ci")]
+    pub target_ci: Option<f64>,
+
+    /// The least number of iterations with `--target-ci`
+    #[clap(long = "min-iterations", default_value = "3")]
+    pub min_iterations: usize,
+
+    /// The most iterations with `--target-ci`, replacing `--iterations`
+    #[clap(long = "max-iterations", default_value = "30")]
+    pub max_iterations: usize,
+
+    /// Comma-separated metrics that need to be stable with `--target-ci`
+    #[clap(long = "stable-metrics", default_value = "instructions:u,wall-time")]
+    pub stable_metrics: String,
+}
+
+impl SamplingOptions {
+    pub fn stop_rule(&self) -> Option<StopRule> {
+        self.target_ci.map(|target_ci_width| StopRule {
+            min_iterations: self.min_iterations,
+            target_ci_width,
+            metrics: self
+                .stable_metrics
+                .split(',')
+                .map(str::trim)
+                .filter(|m| !m.is_empty())
+                .map(String::from)
+                .collect(),
+        })
+    }
+
+    /// The maximum number of iterations, `iterations` unless sampling is
+    /// adaptive.
+    pub fn iterations(&self, iterations: usize) -> usize {
+        match self.target_ci {
+            Some(_) => self.max_iterations.max(self.min_iterations),
+            None => iterations,
+        }
+    }
+}
+
+/// Controls for the noise of the machine running the benchmarks.
+#[derive(Debug, clap::Args)]
+pub struct HostOptions {
+    /// Pin the measured processes to this CPU list, like `2-3,6`
+    #[clap(long = "cpu-set")]
+    pub cpu_set: Option<String>,
+
+    /// Refuse to run unless the cpufreq governor is `performance` and turbo
+    /// boost and SMT are disabled
+    #[clap(long)]
+    pub strict: bool,
+}
+
+impl HostOptions {
+    /// Check the machine is ready for benchmarking and pass the CPU set on
+    /// to `rustc-fake` and `runtime-fake`. Returns the host conditions to
+    /// record with the results.
+    pub fn prepare(&self) -> anyhow::Result<HostConditions> {
+        if let Some(cpu_set) = &self.cpu_set {
+            parse_cpu_set(cpu_set)?;
+            std::env::set_var(CPU_SET_ENV, cpu_set);
+        }
+        let host = HostConditions::detect().with_cpu_set(self.cpu_set.clone());
+        host.check(self.strict)?;
+        Ok(host)
+    }
+}
+
+/// Several toolchains measured by one run, their iterations interleaved in
+/// a shuffled order.
+#[derive(Debug, clap::Args)]
+pub struct InterleaveOptions {
+    /// Another rustc to measure along with the first one, as `RUSTC` or
+    /// `ID=RUSTC`, the id defaulting to its commit hash. Each toolchain
+    /// writes its results to `<out-dir>/<ID>`, and their comparison with
+    /// the first one to `<out-dir>`
+    #[clap(long = "with")]
+    pub with: Vec<String>,
+
+    /// Seed of the order the toolchains are measured in, from the clock by
+    /// default
+    #[clap(long)]
+    pub seed: Option<u64>,
+
+    /// Test of the comparison with the first toolchain: `mann-whitney` or
+    /// `welch`
+    #[clap(long, default_value = "mann-whitney")]
+    pub test: SignificanceTest,
+}
+
+impl InterleaveOptions {
+    /// The toolchains to measure with their ids, `local` first. A single
+    /// toolchain keeps the id of `--id`. Several ones are labeled with
+    /// their ids unless `--label` names the first one.
+    pub fn toolchains(
+        &self,
+        local: &LocalOptions,
+    ) -> anyhow::Result<Vec<(String, LocalToolchain)>> {
+        let mut first = get_local_toolchain(
+            &local.rustc()?,
+            local.cargo.as_deref(),
+            local.id.as_deref(),
+            "",
+        )?;
+        first.identity.label = local.label.clone();
+        if self.with.is_empty() {
+            return Ok(vec![(local.id.clone().unwrap_or_default(), first)]);
+        }
+
+        let mut toolchains = vec![(local.id.clone(), first)];
+        for with in &self.with {
+            toolchains.push(get_named_toolchain(with, local.cargo.as_deref())?);
+        }
+
+        with_ids(toolchains)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed.unwrap_or_else(Self::default_seed)
+    }
+
+    /// A seed from the clock.
+    pub fn default_seed() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default()
+    }
+}
+
+/// The toolchains a bisection searches and the metric it watches.
+#[derive(Debug, clap::Args)]
+pub struct BisectOptions {
+    /// The rustcs to search, oldest first, as `RUSTC` or `ID=RUSTC`. The
+    /// first one is the baseline the others are compared with
+    pub toolchains: Vec<String>,
+
+    /// Directory of toolchains installed by `rustup-toolchain-install-master`,
+    /// each with a `bin/rustc`, searched in the order of their commit dates
+    #[clap(long = "toolchain-dir", conflicts_with = "toolchains")]
+    pub toolchain_dir: Option<PathBuf>,
+
+    /// The path to the local Cargo to use
+    #[clap(long, parse(from_os_str))]
+    pub cargo: Option<PathBuf>,
+
+    /// Name of the benchmark to measure
+    #[clap(long = "bench")]
+    pub benchmark: String,
+
+    /// Ignored for runtime benchmarks
+    #[clap(long, default_value = "debug")]
+    pub profile: Profile,
+
+    /// Ignored for runtime benchmarks
+    #[clap(long, default_value = "full")]
+    pub scenario: Scenario,
+
+    /// Name of the build config to build the benchmark with, the build
+    /// without one if missing
+    #[clap(long = "build-config")]
+    pub build_config: Option<String>,
+
+    /// Name of the patch to watch in the `incr-patched` scenario, needed if
+    /// the benchmark has more than one
+    #[clap(long)]
+    pub patch: Option<String>,
+
+    #[clap(long, default_value = "instructions:u")]
+    pub metric: String,
+
+    /// Change of the mean of the metric against the baseline in percent
+    /// that has to be reached, a negative one to look for an improvement
+    #[clap(long, allow_hyphen_values = true)]
+    pub threshold: f64,
+
+    /// Measure a runtime benchmark instead of the compile time
+    #[clap(long)]
+    pub runtime: bool,
+}
+
+impl BisectOptions {
+    /// The toolchains to search with their ids, oldest first.
+    pub fn toolchains(&self) -> anyhow::Result<Vec<(String, LocalToolchain)>> {
+        let cargo = self.cargo.as_deref();
+        let toolchains = match &self.toolchain_dir {
+            // Each toolchain is named after its directory, which
+            // `rustup-toolchain-install-master` names after the commit.
+            Some(dir) => {
+                let mut toolchains = vec![];
+                for entry in read_dir(dir).with_context(|| format!("failed to read {:?}", dir))? {
+                    let entry = entry?;
+                    let rustc = entry.path().join("bin").join("rustc");
+                    if rustc.is_file() {
+                        let name = entry.file_name().to_string_lossy().to_string();
+                        let toolchain =
+                            get_local_toolchain(&rustc.to_string_lossy(), cargo, Some(&name), "")?;
+                        toolchains.push((Some(name), toolchain));
+                    }
+                }
+                toolchains.sort_by(|(a, x), (b, y)| {
+                    (&x.identity.commit_date, a).cmp(&(&y.identity.commit_date, b))
+                });
+                for pair in toolchains.windows(2) {
+                    let ((a, x), (b, y)) = (&pair[0], &pair[1]);
+                    if x.identity.commit_date == y.identity.commit_date {
+                        eprintln!(
+                            "warning: {} and {} share the commit date {:?}, pass the toolchains in order to bisect them reliably",
+                            a.as_deref().unwrap_or_default(),
+                            b.as_deref().unwrap_or_default(),
+                            x.identity.commit_date
+                        );
+                    }
+                }
+                toolchains
+            }
+            None => self
+                .toolchains
+                .iter()
+                .map(|rustc| get_named_toolchain(rustc, cargo))
+                .collect::<anyhow::Result<_>>()?,
+        };
+        if toolchains.len() < 2 {
+            anyhow::bail!("bisecting needs at least two toolchains");
+        }
+        with_ids(toolchains)
+    }
+}
+
+/// Get the toolchain of `RUSTC` or `ID=RUSTC`, with the given id if any.
+fn get_named_toolchain(
+    rustc: &str,
+    cargo: Option<&Path>,
+) -> anyhow::Result<(Option<String>, LocalToolchain)> {
+    let (id, rustc) = match rustc.split_once('=') {
+        Some((id, rustc)) => (Some(id), rustc),
+        None => (None, rustc),
+    };
+    let toolchain = get_local_toolchain(rustc, cargo, id, "")?;
+    Ok((id.map(String::from), toolchain))
+}
+
+/// Give every toolchain its id, or else the name from its identity, and
+/// label it with its id unless it has a label. Ids must be unique.
+fn with_ids(
+    toolchains: Vec<(Option<String>, LocalToolchain)>,
+) -> anyhow::Result<Vec<(String, LocalToolchain)>> {
+    let mut ids: Vec<String> = vec![];
+    toolchains
+        .into_iter()
+        .map(|(id, mut toolchain)| {
+            let id = id.unwrap_or_else(|| toolchain.identity.name().to_string());
+            if id.is_empty() {
+                anyhow::bail!(
+                    "{:?} reports no commit or release, set its id with `ID=RUSTC`",
+                    toolchain.rustc
+                );
+            }
+            if ids.contains(&id) {
+                anyhow::bail!(
+                    "toolchain id `{}` is given twice, set another one with `ID=RUSTC`",
+                    id
+                );
+            }
+            ids.push(id.clone());
+            if toolchain.identity.label.is_none() {
+                toolchain.identity.label = Some(id.clone());
+            }
+            Ok((id, toolchain))
+        })
+        .collect()
+}
+
+/// Selects the results of a `db` query.
+#[derive(Debug, clap::Args)]
+pub struct SliceOptions {
+    /// Toolchain id the results were ingested under
+    #[clap(long)]
+    pub id: Option<String>,
+
+    /// One of `compile_time`, `runtime` or `binary_size`
+    #[clap(long)]
+    pub kind: Option<ResultKind>,
+
+    #[clap(long)]
+    pub benchmark: Option<String>,
+
+    /// Runtime results are stored as `release`
+    #[clap(long)]
+    pub profile: Option<Profile>,
+
+    /// Runtime results are stored as `full`
+    #[clap(long)]
+    pub scenario: Option<Scenario>,
+
+    /// Name of a build config, `default` for the build without one
+    #[clap(long = "build-config")]
+    pub build_config: Option<String>,
+}
+
+impl SliceOptions {
+    pub fn slice(&self, metric: Option<String>) -> Slice {
+        Slice {
+            toolchain: self.id.clone(),
+            kind: self.kind,
+            benchmark: self.benchmark.clone(),
+            profile: self.profile,
+            build_config: self.build_config.as_deref().map(|c| match c {
+                DEFAULT_BUILD_CONFIG => String::new(),
+                _ => c.to_string(),
+            }),
+            scenario: self.scenario,
+            metric,
+        }
+    }
+}
+
+#[derive(Debug, clap::Subcommand)]
+#[clap(rename_all = "snake_case")]
+pub enum DbCommands {
+    /// Add result files to the database.
+    Ingest {
+        /// Toolchain id to store the results under. Required for files that do
+        /// not record one, i.e. runtime and binary size results
+        #[clap(long)]
+        id: Option<String>,
+
+        /// `compile_time_results.json`, `runtime_results.json` or
+        /// `compiled_binary_size.json` files
+        #[clap(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Print the mean, minimum and maximum of a metric in every run as csv,
+    /// oldest run first.
+    History {
+        /// The metric, like `instructions:u`
+        #[clap(long)]
+        metric: String,
+
+        #[clap(flatten)]
+        slice: SliceOptions,
+    },
+    /// Write results back into the json or csv files of the collector, to a
+    /// directory per kind of results and toolchain.
+    Export {
+        #[clap(flatten)]
+        slice: SliceOptions,
+
+        /// Only export this metric
+        #[clap(long)]
+        metric: Option<String>,
+
+        /// `json` or `csv`
+        #[clap(long, default_value = "json")]
+        format: ExportFormat,
+
+        /// The path of output dir
+        #[clap(long = "out-dir", default_value = "results")]
+        out_dir: PathBuf,
+    },
 }
 
 #[derive(Debug, clap::Subcommand)]
@@ -220,15 +693,49 @@ pub enum Commands {
         #[clap(long, default_value = "1")]
         iterations: usize,
 
+        #[clap(flatten)]
+        interleave: InterleaveOptions,
+
+        #[clap(flatten)]
+        sampling: SamplingOptions,
+
+        #[clap(flatten)]
+        host: HostOptions,
+
+        /// Warm-up runs before the measured iterations of each benchmark, left
+        /// out of the statistics. A `warmup` in `perf-config.json` overrides it
+        #[clap(long, default_value = "0")]
+        warmup: usize,
+
+        /// One of `perf-stat`, `perf-stat-self-profile`, `perf-record`,
+        /// `rusage`, or a profiler whose output is collected into the output
+        /// dir: `cachegrind`, `callgrind`, `dhat`, `dhat-copy`, `massif`,
+        /// `bytehound`, `llvm-lines`, `mono-items` and `self-profile`.
         #[clap(long = "perf-tool", default_value = "perf-stat")]
         perf_tool: PerfTool,
 
-        #[clap(long = "event-filter-file", default_value = "")]
-        event_filter_file: PathBuf,
+        /// Json file listing the perf-stat events to record, like
+        /// `{"events": ["instructions:u", "cycles:u"]}`. Empty for the default set.
+        #[clap(
+            long = "event-set-file",
+            alias = "event-filter-file",
+            default_value = ""
+        )]
+        event_set_file: PathBuf,
 
         #[clap(long = "profile", default_value = "debug,release")]
         profiles: Profiles,
 
+        /// Comma-separated scenarios to measure: `full`, `incr-full`,
+        /// `incr-unchanged` and `incr-patched`.
+        #[clap(long = "scenarios", default_value = "full")]
+        scenarios: Scenarios,
+
+        /// Comma-separated `build_configs` of the suite config to sweep the
+        /// benchmarks across, `default` for the build without one.
+        #[clap(long = "build-configs", default_value = "default")]
+        build_configs: BuildConfigs,
+
         /// The path of benchmark dir
         #[clap(long = "bench-dir", default_value = "../benchmarks/compile-time")]
         bench_dir: PathBuf,
@@ -240,6 +747,16 @@ pub enum Commands {
         /// Flag of Flamegraph. Make $flamegraph$ > 0 if flamegraph is needed.
         #[clap(long, default_value = "1")]
         flamegraph: i32,
+
+        /// Seconds after which a build or run is killed and recorded as timed
+        /// out, unless the benchmark sets `timeout_secs`.
+        #[clap(long)]
+        timeout: Option<u64>,
+
+        /// Continue an interrupted run in the same out dir, skipping the
+        /// iterations already recorded in its journal.
+        #[clap(long)]
+        resume: bool,
     },
     /// Benchmarks the performance of programs generated by a local rustc.
     BenchRuntimeLocal {
@@ -250,11 +767,37 @@ pub enum Commands {
         #[clap(long, default_value = "5")]
         iterations: u32,
 
+        #[clap(flatten)]
+        interleave: InterleaveOptions,
+
+        #[clap(flatten)]
+        sampling: SamplingOptions,
+
+        #[clap(flatten)]
+        host: HostOptions,
+
+        /// Warm-up runs before the measured iterations of each benchmark, left
+        /// out of the statistics. A `warmup` in `perf-config.json` overrides it
+        #[clap(long, default_value = "0")]
+        warmup: usize,
+
+        /// One of `perf-stat`, `perf-record` or `rusage`.
         #[clap(long = "perf-tool", default_value = "perf-stat")]
         perf_tool: PerfTool,
 
-        #[clap(long = "event-filter-file", default_value = "")]
-        event_filter_file: PathBuf,
+        /// Json file listing the perf-stat events to record, like
+        /// `{"events": ["instructions:u", "cycles:u"]}`. Empty for the default set.
+        #[clap(
+            long = "event-set-file",
+            alias = "event-filter-file",
+            default_value = ""
+        )]
+        event_set_file: PathBuf,
+
+        /// Comma-separated `build_configs` of the suite config to sweep the
+        /// benchmarks across, `default` for the build without one.
+        #[clap(long = "build-configs", default_value = "default")]
+        build_configs: BuildConfigs,
 
         /// The path of benchmark dir
         #[clap(long = "bench-dir", default_value = "../benchmarks/runtime/")]
@@ -267,6 +810,11 @@ pub enum Commands {
         /// Flag of Flamegraph. Make $flamegraph$ > 0 if flamegraph is needed.
         #[clap(long, default_value = "1")]
         flamegraph: i32,
+
+        /// Seconds after which a build or run is killed and recorded as timed
+        /// out, unless the benchmark sets `timeout_secs`.
+        #[clap(long)]
+        timeout: Option<u64>,
     },
 
     /// Generate MIR with a local rustc.
@@ -291,6 +839,11 @@ pub enum Commands {
         #[clap(long = "profile", default_value = "debug,release")]
         profiles: Profiles,
 
+        /// Comma-separated `build_configs` of the suite config to sweep the
+        /// benchmarks across, `default` for the build without one.
+        #[clap(long = "build-configs", default_value = "default")]
+        build_configs: BuildConfigs,
+
         /// The path of benchmark dir
         #[clap(long = "bench-dir", default_value = "../benchmarks/compile-time")]
         bench_dir: PathBuf,
@@ -379,6 +932,97 @@ pub enum Commands {
         #[clap(long = "out-path", default_value = "results")]
         out_path: PathBuf,
     },
+
+    /// Summarize the change of every metric over all benchmarks against a
+    /// baseline, as the geometric mean of the per-benchmark ratios.
+    Summarize {
+        /// `compile_time_statistics.json` or `runtime_statistics.json` of
+        /// the toolchain to summarize, or the results file of the same kind
+        #[clap(long = "stats")]
+        stats: PathBuf,
+
+        /// The statistics or results of the same kind of the baseline
+        /// toolchain
+        #[clap(long = "baseline")]
+        baseline: PathBuf,
+
+        /// The benchmark dir whose `perf-config.json` files give the `weight`
+        /// of each benchmark. Without it, every benchmark counts once.
+        #[clap(long = "bench-dir")]
+        bench_dir: Option<PathBuf>,
+
+        /// Shift added to every sample of results files before taking
+        /// geometric means, and to every geometric mean before taking
+        /// ratios, so benchmarks with values of zero are not excluded
+        #[clap(long = "epsilon", default_value = "0")]
+        epsilon: f64,
+
+        /// The path of output file
+        #[clap(long = "out", default_value = "summary.json")]
+        out_path: PathBuf,
+    },
+
+    /// Find the first of an ordered range of toolchains where a metric of a
+    /// benchmark changes past a threshold, by binary search.
+    Bisect {
+        #[clap(flatten)]
+        bisect: BisectOptions,
+
+        /// The number of iterations of each toolchain at every step
+        #[clap(long, default_value = "5")]
+        iterations: usize,
+
+        /// Warm-up runs before the measured iterations, left out of the
+        /// comparison
+        #[clap(long, default_value = "0")]
+        warmup: usize,
+
+        /// Seed of the order the toolchains are measured in, from the clock by
+        /// default
+        #[clap(long)]
+        seed: Option<u64>,
+
+        /// Test deciding whether a change is significant: `mann-whitney` or
+        /// `welch`
+        #[clap(long, default_value = "mann-whitney")]
+        test: SignificanceTest,
+
+        #[clap(flatten)]
+        host: HostOptions,
+
+        /// One of `perf-stat`, `perf-stat-self-profile` or `rusage`.
+        #[clap(long = "perf-tool", default_value = "perf-stat")]
+        perf_tool: PerfTool,
+
+        /// Json file listing the perf-stat events to record, like
+        /// `{"events": ["instructions:u", "cycles:u"]}`. Empty for the default set.
+        #[clap(long = "event-set-file", default_value = "")]
+        event_set_file: PathBuf,
+
+        /// The path of benchmark dir
+        #[clap(long = "bench-dir", default_value = "../benchmarks/compile-time")]
+        bench_dir: PathBuf,
+
+        /// The path of output dir, holding the results of every step in a
+        /// directory named after the measured toolchain
+        #[clap(long = "out-dir", default_value = "results")]
+        out_dir: PathBuf,
+
+        /// Seconds after which a build or run is killed and recorded as timed
+        /// out, unless the benchmark sets `timeout_secs`.
+        #[clap(long)]
+        timeout: Option<u64>,
+    },
+
+    /// Store results in a local SQLite database and query them.
+    Db {
+        /// The path of the database file
+        #[clap(long = "db", default_value = "results.db")]
+        db: PathBuf,
+
+        #[clap(subcommand)]
+        command: DbCommands,
+    },
 }
 
 #[derive(Debug)]
@@ -390,7 +1034,22 @@ pub enum PerfTool {
 pub enum Bencher {
     PerfStat,
     PerfRecord,
-    // PerfStatSelfProfile,
+    /// Resource usage reported by the kernel (`wait4` and `/proc/<pid>/io`),
+    /// for machines where perf is not available.
+    Rusage,
+    // Profilers wrapped by `rustc-fake`, which leave an artifact in the
+    // working directory instead of printing stats.
+    Cachegrind,
+    Callgrind,
+    Dhat,
+    DhatCopy,
+    Massif,
+    Bytehound,
+    LlvmLines,
+    MonoItems,
+    SelfProfile,
+    /// `PerfStat` with the per-query self-profile of the measured rustc.
+    PerfStatSelfProfile,
     // XperfStat,
     // XperfStatSelfProfile,
 }
@@ -409,12 +1068,78 @@ impl PerfTool {
     }
 }
 
+impl Bencher {
+    pub fn needs_perf(&self) -> bool {
+        matches!(
+            self,
+            Bencher::PerfStat | Bencher::PerfStatSelfProfile | Bencher::PerfRecord
+        )
+    }
+
+    /// Whether the bencher records the stats of every iteration, rather than
+    /// only an artifact.
+    pub fn records_stats(&self) -> bool {
+        matches!(
+            self,
+            Bencher::PerfStat | Bencher::PerfStatSelfProfile | Bencher::Rusage
+        )
+    }
+
+    /// Whether `runtime-fake` can wrap the benchmark with this bencher.
+    pub fn supports_runtime(&self) -> bool {
+        matches!(
+            self,
+            Bencher::PerfStat | Bencher::PerfRecord | Bencher::Rusage
+        )
+    }
+
+    /// The cargo subcommand used to build the benchmark.
+    pub fn cargo_subcommand(&self) -> &'static str {
+        match self {
+            Bencher::LlvmLines => "llvm-lines",
+            _ => "rustc",
+        }
+    }
+
+    /// The file (or directory) the profiler leaves in the working directory
+    /// of the measured build. `perf.data` is collected separately, in the
+    /// layout `analyze_local` reads.
+    pub fn artifact(&self) -> Option<&'static str> {
+        match self {
+            Bencher::PerfStat | Bencher::PerfRecord | Bencher::Rusage => None,
+            Bencher::Cachegrind => Some("cgout"),
+            Bencher::Callgrind => Some("clgout"),
+            Bencher::Dhat => Some("dhout"),
+            Bencher::DhatCopy => Some("dhcopy"),
+            Bencher::Massif => Some("msout"),
+            Bencher::Bytehound => Some("bytehound.dat"),
+            Bencher::LlvmLines => Some("llvm-lines"),
+            Bencher::MonoItems => Some("mono-items"),
+            Bencher::SelfProfile => Some("Zsp"),
+            Bencher::PerfStatSelfProfile => Some("self-profile-output"),
+        }
+    }
+}
+
 impl FromStr for PerfTool {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "perf-stat" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::PerfStat)),
             "perf-record" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::PerfRecord)),
+            "perf-stat-self-profile" => {
+                std::result::Result::Ok(PerfTool::BenchTool(Bencher::PerfStatSelfProfile))
+            }
+            "rusage" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::Rusage)),
+            "cachegrind" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::Cachegrind)),
+            "callgrind" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::Callgrind)),
+            "dhat" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::Dhat)),
+            "dhat-copy" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::DhatCopy)),
+            "massif" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::Massif)),
+            "bytehound" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::Bytehound)),
+            "llvm-lines" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::LlvmLines)),
+            "mono-items" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::MonoItems)),
+            "self-profile" => std::result::Result::Ok(PerfTool::BenchTool(Bencher::SelfProfile)),
             _ => Err(format!("Unrecognized PerfTool {}", s)),
         }
     }
@@ -458,3 +1183,38 @@ impl ResultWriter {
         Ok(self)
     }
 }
+
+#[cfg(test)]
+mod test_toolchain {
+    use std::path::{Path, PathBuf};
+
+    use super::{find_cargo, resolve_rustc};
+
+    /// Test for resolve_rustc and find_cargo
+    ///
+    /// Step1. Resolve the rustc of a sysroot, of a toolchain manifest and of a
+    /// plain path in `test/toolchain`.
+    ///
+    /// Step2. Find the cargo next to the rustc of the sysroot.
+    #[test]
+    fn test_resolve_toolchain() {
+        let dir = Path::new("test/toolchain");
+        let rustc = dir.join("sysroot/bin/rustc");
+        let cargo = dir.join("sysroot/bin/cargo");
+
+        assert_eq!(
+            resolve_rustc(&dir.join("sysroot")).unwrap(),
+            (rustc.clone(), None)
+        );
+        assert_eq!(
+            resolve_rustc(&dir.join("manifest.json")).unwrap(),
+            (rustc.clone(), Some(cargo.clone()))
+        );
+        assert_eq!(
+            resolve_rustc(&rustc).unwrap(),
+            (PathBuf::from("test/toolchain/sysroot/bin/rustc"), None)
+        );
+
+        assert_eq!(find_cargo(&rustc).unwrap(), cargo);
+    }
+}
@@ -1,13 +1,84 @@
-use std::process::{self, Command, Stdio};
+use std::{
+    process::{self, Child, Command, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
 
-pub fn run_command_with_output(cmd: &mut Command) -> anyhow::Result<process::Output> {
+/// The process group of a command was killed after running into its
+/// timeout.
+#[derive(thiserror::Error, Debug)]
+#[error("timed out after {}s", .0.as_secs())]
+pub struct TimeoutError(pub Duration);
+
+/// Whether `err` was caused by a command running into its timeout.
+pub fn is_timeout(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<TimeoutError>().is_some()
+}
+
+/// Kills the process group of a child once the timeout expires, unless it
+/// is finished first.
+struct Watchdog {
+    done: mpsc::Sender<()>,
+    handle: JoinHandle<bool>,
+}
+
+impl Watchdog {
+    fn new(child: &Child, timeout: Duration) -> Self {
+        let pid = child.id();
+        let (done, rx) = mpsc::channel();
+        let handle = thread::spawn(move || match rx.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Timeout) => {
+                kill_process_group(pid);
+                true
+            }
+            _ => false,
+        });
+        Watchdog { done, handle }
+    }
+
+    /// Stop watching the finished child and return whether it was killed.
+    fn finish(self) -> bool {
+        drop(self.done);
+        self.handle.join().unwrap_or(false)
+    }
+}
+
+#[cfg(unix)]
+fn kill_process_group(pid: u32) {
+    // The child leads its own group, see `spawn`.
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(pid: u32) {
+    let _ = Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .status();
+}
+
+/// Spawn `cmd`, in a new process group if it has a timeout so that cargo,
+/// the wrapped rustc, perf and the benchmark binaries are killed together.
+fn spawn(cmd: &mut Command, timeout: Option<Duration>) -> std::io::Result<Child> {
+    #[cfg(unix)]
+    if timeout.is_some() {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    cmd.spawn()
+}
+
+pub fn run_command_with_output(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+) -> anyhow::Result<process::Output> {
     use crate::utils::read2;
     use anyhow::Context;
-    let mut child = cmd
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
+    let mut child = spawn(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()), timeout)
         .with_context(|| format!("failed to spawn process for cmd: {:?}", cmd))?;
+    let watchdog = timeout.map(|timeout| Watchdog::new(&child, timeout));
 
     let mut stdout = Vec::new();
     let mut stderr = Vec::new();
@@ -38,6 +109,11 @@ pub fn run_command_with_output(cmd: &mut Command) -> anyhow::Result<process::Out
         .wait()
         .with_context(|| "failed to wait on child process")?;
 
+    if watchdog.is_some_and(Watchdog::finish) {
+        return Err(TimeoutError(timeout.unwrap()))
+            .with_context(|| format!("killed process for cmd: {:?}", cmd));
+    }
+
     Ok(process::Output {
         status,
         stdout,
@@ -46,7 +122,16 @@ pub fn run_command_with_output(cmd: &mut Command) -> anyhow::Result<process::Out
 }
 
 pub fn command_output(cmd: &mut Command) -> anyhow::Result<process::Output> {
-    let output = run_command_with_output(cmd)?;
+    command_output_with_timeout(cmd, None)
+}
+
+/// Like `command_output`, but kills the process group of `cmd` and returns
+/// a `TimeoutError` once `timeout` expires.
+pub fn command_output_with_timeout(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+) -> anyhow::Result<process::Output> {
+    let output = run_command_with_output(cmd, timeout)?;
 
     if !output.status.success() {
         return Err(anyhow::anyhow!(
@@ -61,10 +146,58 @@ pub fn command_output(cmd: &mut Command) -> anyhow::Result<process::Output> {
 }
 
 pub fn command_discard_output(cmd: &mut Command) -> anyhow::Result<()> {
-    cmd.stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()?
-        .wait()?;
+    command_discard_output_with_timeout(cmd, None)
+}
+
+pub fn command_discard_output_with_timeout(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+) -> anyhow::Result<()> {
+    let mut child = spawn(cmd.stdout(Stdio::null()).stderr(Stdio::null()), timeout)?;
+    let watchdog = timeout.map(|timeout| Watchdog::new(&child, timeout));
+    child.wait()?;
 
+    if watchdog.is_some_and(Watchdog::finish) {
+        return Err(TimeoutError(timeout.unwrap()).into());
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod test_command {
+    use std::{
+        process::Command,
+        time::{Duration, Instant},
+    };
+
+    use super::{command_discard_output_with_timeout, command_output_with_timeout, is_timeout};
+
+    /// A shell waiting on a child that never exits is killed together with
+    /// the child.
+    #[cfg(unix)]
+    #[test]
+    fn test_command_timeout() {
+        let start = Instant::now();
+        let err = command_output_with_timeout(
+            Command::new("sh").arg("-c").arg("sleep 60; echo done"),
+            Some(Duration::from_millis(200)),
+        )
+        .unwrap_err();
+        assert!(is_timeout(&err));
+        assert!(start.elapsed() < Duration::from_secs(30));
+
+        let err = command_discard_output_with_timeout(
+            Command::new("sh").arg("-c").arg("sleep 60"),
+            Some(Duration::from_millis(200)),
+        )
+        .unwrap_err();
+        assert!(is_timeout(&err));
+
+        let output = command_output_with_timeout(
+            Command::new("sh").arg("-c").arg("echo done"),
+            Some(Duration::from_secs(30)),
+        )
+        .unwrap();
+        assert_eq!(output.stdout, b"done\n");
+    }
+}
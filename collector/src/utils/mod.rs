@@ -1,3 +1,4 @@
 pub mod command;
 pub mod fs;
 pub mod read2;
+pub mod rng;
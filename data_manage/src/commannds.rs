@@ -1,4 +1,4 @@
-use collector::benchmark::profile::Profile;
+use collector::{benchmark::profile::Profile, statistics::significance::SignificanceTest};
 use std::path::PathBuf;
 
 #[derive(Debug, clap::Parser)]
@@ -22,7 +22,8 @@ pub enum Commands {
         #[clap(long = "profile")]
         profile: Profile,
 
-        /// Version of rustc
+        /// Label, release or commit hash of rustc, or the name of its
+        /// directory for results that do not record their toolchain
         #[clap(long = "rust-ver")]
         rustc: String,
 
@@ -38,7 +39,8 @@ pub enum Commands {
         #[clap(long = "root-dir")]
         root_dir: PathBuf,
 
-        /// Version of rustc
+        /// Label, release or commit hash of rustc, or the name of its
+        /// directory for results that do not record their toolchain
         #[clap(long = "rust-ver")]
         rustc: String,
 
@@ -64,6 +66,10 @@ pub enum Commands {
         /// The path of output file
         #[clap(long = "out-path", default_value = "results")]
         out_path: PathBuf,
+
+        /// The test telling significant changes from noise, `mann-whitney` or `welch`.
+        #[clap(long = "test", default_value = "mann-whitney")]
+        test: SignificanceTest,
     },
 
     /// Compare 2 different stats on one metric and plot their change rate on geometric mean.
@@ -83,6 +89,11 @@ pub enum Commands {
         /// The path of output file
         #[clap(long = "out-path", default_value = "results")]
         out_path: PathBuf,
+
+        /// Shift added to every geometric mean before taking ratios, so
+        /// benchmarks with a geometric mean of zero are not excluded.
+        #[clap(long = "epsilon", default_value = "0")]
+        epsilon: f64,
     },
 
     /// Compare 2 different stats on 2 metrics and plot their change rate on geometric mean.
@@ -106,6 +117,11 @@ pub enum Commands {
         /// The path of output file
         #[clap(long = "out-path", default_value = "results")]
         out_path: PathBuf,
+
+        /// Shift added to every geometric mean before taking ratios, so
+        /// benchmarks with a geometric mean of zero are not excluded.
+        #[clap(long = "epsilon", default_value = "0")]
+        epsilon: f64,
     },
 
     /// Merge several metrics into a new metric of a table data fmt file.
@@ -3,20 +3,42 @@ use std::{
     process::{Command, Stdio},
 };
 
-use super::data::{calculate_change_rate, read_data, ChangeRate};
+use collector::statistics::significance::{write_comparisons_csv, SignificanceTest};
 
+use super::data::{
+    calculate_change_rate, compare_significance, read_data, warn_different_hosts, ChangeRate,
+};
+
+/// Plot the change rate of `data_a` on `data_b` to `out_path`, and write
+/// the significance of the change of every benchmark to a csv file next
+/// to it.
 pub fn compare_data(
     data_a: &PathBuf,
     data_b: &PathBuf,
     metric: &String,
     out_path: PathBuf,
+    test: SignificanceTest,
 ) -> anyhow::Result<PathBuf> {
+    warn_different_hosts(data_a, data_b);
+
     let data_a = read_data(data_a, metric)?;
     let data_b = read_data(data_b, metric)?;
 
     // Calculate change rate of stats_a on stats_b
     let change_rate = calculate_change_rate(&data_a, &data_b);
 
+    let comparisons = compare_significance(&data_a, &data_b, test);
+    for (k, c) in &comparisons {
+        println!(
+            "{:<40} {:>8.2}% p={:<8.4} {}",
+            k, c.change, c.p_value, c.verdict
+        );
+    }
+    write_comparisons_csv(
+        comparisons.iter().map(|(k, c)| (k, c)),
+        &out_path.with_extension("csv"),
+    )?;
+
     plot_boxplot_compare(&change_rate, out_path, metric)
 }
 
@@ -57,13 +79,15 @@ mod test_compare_stat {
         path::PathBuf,
     };
 
+    use collector::statistics::significance::SignificanceTest;
+
     use super::compare_data;
 
     /// test for compare_stat
     ///
     /// Step1. compare stats of metric `instructions` in `test/compare_stat/stat`.
     ///
-    /// Step2. plot and check the compare result.
+    /// Step2. plot and check the compare result and its significance.
     ///
     /// Step3. clean up.
     #[test]
@@ -75,9 +99,22 @@ mod test_compare_stat {
 
         assert_eq!(
             out_path.clone(),
-            compare_data(&stat_1, &stat_2, &metric, out_path.clone()).unwrap()
+            compare_data(
+                &stat_1,
+                &stat_2,
+                &metric,
+                out_path.clone(),
+                SignificanceTest::default()
+            )
+            .unwrap()
         );
 
+        let significance = out_path.with_extension("csv");
+        assert!(fs::read_to_string(&significance)
+            .unwrap()
+            .starts_with("benchmark,"));
+        remove_file(significance).unwrap();
+
         fs::metadata(&out_path).unwrap();
         remove_file(out_path).unwrap();
     }
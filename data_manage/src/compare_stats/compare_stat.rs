@@ -4,21 +4,28 @@ use std::{
     process::{Command, Stdio},
 };
 
-use super::stats::{calculate_change_rate, read_stats};
+use super::{
+    data::warn_different_hosts,
+    stats::{aggregate, read_stats},
+};
 
 pub fn compare_stat(
     stats_a: &PathBuf,
     stats_b: &PathBuf,
     metric: &String,
     out_path: PathBuf,
+    epsilon: f64,
 ) -> anyhow::Result<PathBuf> {
+    warn_different_hosts(stats_a, stats_b);
+
     let stats_a = read_stats(stats_a, metric)?;
     let stats_b = read_stats(stats_b, metric)?;
 
     // Calculate change rate of stats_a on stats_b
-    let change_rate = calculate_change_rate(&stats_a, &stats_b);
+    let aggregate = aggregate(&stats_a, &stats_b, epsilon);
+    println!("{}", aggregate);
 
-    plot_compare(&change_rate, out_path, metric)
+    plot_compare(&aggregate.change_rates(), out_path, metric)
 }
 
 fn plot_compare(
@@ -67,7 +74,7 @@ mod test_compare_stat {
 
         assert_eq!(
             out_path.clone(),
-            compare_stat(&stat_1, &stat_2, &metric, out_path.clone()).unwrap()
+            compare_stat(&stat_1, &stat_2, &metric, out_path.clone(), 0.0).unwrap()
         );
 
         fs::metadata(&out_path).unwrap();
@@ -3,7 +3,10 @@ use std::{
     process::{Command, Stdio},
 };
 
-use super::stats::{calculate_change_rate, read_stats, ChangeRate};
+use super::{
+    data::warn_different_hosts,
+    stats::{aggregate, read_stats, ChangeRate},
+};
 
 pub fn compare_stat_2d(
     stats_a: &PathBuf,
@@ -11,16 +14,31 @@ pub fn compare_stat_2d(
     metric_1: &String,
     metric_2: &String,
     out_path: PathBuf,
+    epsilon: f64,
 ) -> anyhow::Result<PathBuf> {
+    warn_different_hosts(stats_a, stats_b);
+
     // Calculate change rate of stats_a on stats_b
-    let change_rate_1 = calculate_change_rate(
+    let aggregate_1 = aggregate(
         &read_stats(stats_a, metric_1)?,
         &read_stats(stats_b, metric_1)?,
+        epsilon,
     );
-    let change_rate_2 = calculate_change_rate(
+    let aggregate_2 = aggregate(
         &read_stats(stats_a, metric_2)?,
         &read_stats(stats_b, metric_2)?,
+        epsilon,
     );
+    println!(
+        "{}:\n{}\n{}:\n{}",
+        metric_1, aggregate_1, metric_2, aggregate_2
+    );
+
+    // Only benchmarks with both change rates can be plotted.
+    let mut change_rate_1 = aggregate_1.change_rates();
+    let mut change_rate_2 = aggregate_2.change_rates();
+    change_rate_1.retain(|k, _| change_rate_2.contains_key(k));
+    change_rate_2.retain(|k, _| change_rate_1.contains_key(k));
 
     plot_compare(&change_rate_1, &change_rate_2, out_path, metric_1, metric_2)
 }
@@ -83,7 +101,15 @@ mod test_compare_stat_2d {
 
         assert_eq!(
             out_path.clone(),
-            compare_stat_2d(&stat_1, &stat_2, &metric_1, &metric_2, out_path.clone()).unwrap()
+            compare_stat_2d(
+                &stat_1,
+                &stat_2,
+                &metric_1,
+                &metric_2,
+                out_path.clone(),
+                0.0
+            )
+            .unwrap()
         );
 
         fs::metadata(&out_path).unwrap();
@@ -1,8 +1,19 @@
-use std::{collections::HashMap, fs::File, io::BufReader, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
 
 use anyhow::bail;
-use collector::statistics::{
-    compile_time_stat::CompileTimeResultSet, runtime_stat::RuntimeResultVec,
+use collector::{
+    benchmark::scenario::Scenario,
+    statistics::{
+        compile_time_stat::CompileTimeResultSet,
+        metadata::Metadata,
+        runtime_stat::RuntimeResultVec,
+        significance::{Comparison, SignificanceTest},
+    },
 };
 
 pub type LabeledData = HashMap<String, Vec<f64>>;
@@ -23,6 +34,39 @@ pub fn read_data(data_file: &PathBuf, metric: &String) -> anyhow::Result<Labeled
     }
 }
 
+/// Metadata of a results file, or of the results file next to a statistics
+/// file, e.g. `compile_time_results.json` for `compile_time_statistics.json`.
+fn read_metadata(file: &Path) -> Option<Metadata> {
+    let name = file.file_name()?.to_str()?;
+    let content =
+        fs::read_to_string(file.with_file_name(name.replace("statistics", "results"))).ok()?;
+
+    match serde_json::from_str::<CompileTimeResultSet>(&content) {
+        Ok(s) => s.metadata,
+        Err(_) => {
+            serde_json::from_str::<RuntimeResultVec>(&content)
+                .ok()?
+                .metadata
+        }
+    }
+}
+
+/// Warn if `file_a` and `file_b` were measured on different hosts, which
+/// makes their comparison meaningless.
+pub fn warn_different_hosts(file_a: &Path, file_b: &Path) {
+    if let (Some(a), Some(b)) = (read_metadata(file_a), read_metadata(file_b)) {
+        let differences = a.host_differences(&b);
+        if !differences.is_empty() {
+            eprintln!(
+                "Warning: {} and {} came from different hosts:\n  {}",
+                file_a.display(),
+                file_b.display(),
+                differences.join("\n  ")
+            );
+        }
+    }
+}
+
 pub fn calculate_change_rate(data_a: &LabeledData, data_b: &LabeledData) -> ChangeRate {
     data_a
         .into_iter()
@@ -47,36 +91,85 @@ pub fn calculate_change_rate(data_a: &LabeledData, data_b: &LabeledData) -> Chan
         .collect()
 }
 
+/// Test whether the samples of every benchmark in `data_a` differ
+/// significantly from the ones in `data_b`, the baseline. Benchmarks with
+/// too few samples on either side are left out.
+pub fn compare_significance(
+    data_a: &LabeledData,
+    data_b: &LabeledData,
+    test: SignificanceTest,
+) -> Vec<(String, Comparison)> {
+    let mut comparisons: Vec<(String, Comparison)> = data_a
+        .iter()
+        .filter_map(|(k, new)| {
+            let old = data_b.get(k)?;
+            Comparison::new(old, new, test).map(|c| (k.clone(), c))
+        })
+        .collect();
+    comparisons.sort_by(|a, b| a.0.cmp(&b.0));
+    comparisons
+}
+
+/// Label of the measurements of `benchmark` in `scenario`, after `patch`,
+/// built with `build_config`. Cold builds without a build config keep the
+/// benchmark name, so runs without incremental scenarios are labeled as
+/// before.
+pub(super) fn label(
+    benchmark: &str,
+    build_config: Option<&str>,
+    scenario: &Scenario,
+    patch: &Option<String>,
+) -> String {
+    let mut label = benchmark.to_string();
+    if let Some(build_config) = build_config {
+        label = label + "-" + build_config;
+    }
+    if *scenario != Scenario::Full {
+        label = label + "-" + &scenario.to_string();
+    }
+    if let Some(patch) = patch {
+        label = label + "-" + patch;
+    }
+    label
+}
+
 fn reshape_data(data: CompileTimeResultSet, metric: &String) -> LabeledData {
+    let mut labeled_data = LabeledData::new();
     data.results
         .into_iter()
-        .map(|s| {
-            (
-                s.benchmark,
-                s.result_vec
-                    .into_iter()
-                    .map(|d| {
-                        d.stats
-                            .stats
-                            .into_iter()
-                            .find(|(m, _)| m == metric)
-                            .unwrap()
-                    })
-                    .map(|x| x.1)
-                    .collect(),
-            )
-        })
-        .collect()
+        .flat_map(|s| s.result_vec)
+        // Timed-out iterations record no values.
+        .filter(|d| !d.warmup && !d.stats.timed_out)
+        .for_each(|d| {
+            labeled_data
+                .entry(label(
+                    &d.benchmark,
+                    d.build_config.as_deref(),
+                    &d.scenario,
+                    &d.patch,
+                ))
+                .or_default()
+                .push(
+                    d.stats
+                        .stats
+                        .into_iter()
+                        .find(|(m, _)| m == metric)
+                        .unwrap()
+                        .1,
+                )
+        });
+    labeled_data
 }
 
 fn reshape_runtime_data(data: RuntimeResultVec, metric: &String) -> LabeledData {
-    data.0
+    data.results
         .into_iter()
         .map(|s| {
             (
-                s.name,
+                label(&s.name, s.build_config.as_deref(), &Scenario::Full, &None),
                 s.stats
                     .into_iter()
+                    .filter(|stats| !stats.timed_out)
                     .map(|stats| stats.stats.into_iter().find(|(m, _)| m == metric).unwrap())
                     .map(|x| x.1)
                     .collect(),
@@ -84,3 +177,42 @@ fn reshape_runtime_data(data: RuntimeResultVec, metric: &String) -> LabeledData
         })
         .collect()
 }
+
+#[cfg(test)]
+mod test_data {
+    use std::path::PathBuf;
+
+    use super::read_data;
+
+    /// Test for read_data
+    ///
+    /// Step1. Read compile-time results with a timed-out iteration and check
+    /// it is left out.
+    ///
+    /// Step2. Do the same for runtime results.
+    ///
+    /// Step3. Read results of several scenarios and build configs and check
+    /// they are labeled apart.
+    #[test]
+    fn test_read_data() {
+        let dir = PathBuf::from("test/compare_data/timed_out");
+        let metric = String::from("wall-time");
+
+        let data = read_data(&dir.join("compile_time_results.json"), &metric).unwrap();
+        assert_eq!(data["plus"], vec![0.07, 0.08]);
+
+        let data = read_data(&dir.join("runtime_results.json"), &metric).unwrap();
+        assert_eq!(data["plus"], vec![0.005, 0.006]);
+
+        let data = read_data(
+            &PathBuf::from("test/compare_data/labels/compile_time_results.json"),
+            &metric,
+        )
+        .unwrap();
+        assert_eq!(data.len(), 4);
+        assert_eq!(data["plus"], vec![0.07, 0.08]);
+        assert_eq!(data["plus-incr-full"], vec![0.09, 0.10]);
+        assert_eq!(data["plus-incr-patched-println"], vec![0.03, 0.04]);
+        assert_eq!(data["plus-fat-lto"], vec![0.11, 0.12]);
+    }
+}
@@ -1,11 +1,16 @@
 use std::{collections::HashMap, fs::File, io::BufReader, path::PathBuf};
 
 use anyhow::bail;
-use collector::statistics::{
-    compile_time_stat::CompileTimeStatistics, runtime_stat::RuntimeStatistics,
-    statistic::Statistics,
+use collector::{
+    benchmark::scenario::Scenario,
+    statistics::{
+        aggregate::Aggregate, compile_time_stat::CompileTimeStatistics,
+        runtime_stat::RuntimeStatistics, statistic::Statistics,
+    },
 };
 
+use super::data::label;
+
 pub type LabeledStats = HashMap<String, Statistics>;
 pub type ChangeRate = HashMap<String, f64>;
 
@@ -25,16 +30,20 @@ pub fn read_stats(stats_file: &PathBuf, metric: &String) -> anyhow::Result<Label
     }
 }
 
-pub fn calculate_change_rate(stats_a: &LabeledStats, stats_b: &LabeledStats) -> ChangeRate {
-    stats_a
-        .into_iter()
-        .map(|(k, v)| {
-            let geom_a = v.geometric_mean;
-            let geom_b = stats_b.get(k).unwrap().geometric_mean;
-
-            (k.clone(), (geom_a - geom_b) / geom_b * 100.)
-        })
-        .collect()
+/// Aggregate the geometric means of `stats_a` on the ones of `stats_b`,
+/// shifted by `epsilon`.
+pub fn aggregate(stats_a: &LabeledStats, stats_b: &LabeledStats, epsilon: f64) -> Aggregate {
+    let geometric_means = |stats: &LabeledStats| {
+        stats
+            .iter()
+            .map(|(k, v)| (k.clone(), v.geometric_mean))
+            .collect()
+    };
+    Aggregate::new(
+        &geometric_means(stats_a),
+        &geometric_means(stats_b),
+        epsilon,
+    )
 }
 
 fn reshape_stat(stats: CompileTimeStatistics, metric: &String) -> HashMap<String, Statistics> {
@@ -42,7 +51,7 @@ fn reshape_stat(stats: CompileTimeStatistics, metric: &String) -> HashMap<String
         .into_iter()
         .map(|s| {
             (
-                s.name,
+                label(&s.name, s.build_config.as_deref(), &s.scenario, &s.patch),
                 s.statistic_vec
                     .into_iter()
                     .find(|(m, _)| m == metric)
@@ -58,7 +67,7 @@ fn reshape_runtime_stat(stats: RuntimeStatistics, metric: &String) -> HashMap<St
         .into_iter()
         .map(|s| {
             (
-                s.name,
+                label(&s.name, s.build_config.as_deref(), &Scenario::Full, &None),
                 s.statistic_vec
                     .into_iter()
                     .find(|(m, _)| m == metric)
@@ -68,3 +77,28 @@ fn reshape_runtime_stat(stats: RuntimeStatistics, metric: &String) -> HashMap<St
         })
         .collect()
 }
+
+#[cfg(test)]
+mod test_stats {
+    use std::path::PathBuf;
+
+    use super::read_stats;
+
+    /// Test for read_stats
+    ///
+    /// Step1. Read statistics of several scenarios and build configs and check
+    /// they are labeled apart instead of overwriting each other.
+    #[test]
+    fn test_read_stats_labels() {
+        let stats = read_stats(
+            &PathBuf::from("test/compare_stat/labels/compile_time_statistics.json"),
+            &String::from("wall-time"),
+        )
+        .unwrap();
+        assert_eq!(stats.len(), 4);
+        assert_eq!(stats["plus"].max, 0.08);
+        assert_eq!(stats["plus-incr-full"].max, 0.10);
+        assert_eq!(stats["plus-incr-patched-println"].min, 0.03);
+        assert_eq!(stats["plus-fat-lto"].max, 0.12);
+    }
+}
@@ -39,7 +39,8 @@ fn main() {
             stats_b,
             out_path,
             metric,
-        } => match compare_stat(&stats_a, &stats_b, &metric, out_path) {
+            epsilon,
+        } => match compare_stat(&stats_a, &stats_b, &metric, out_path, epsilon) {
             Ok(p) => println!("Plot change rate of stats to {}", p.to_str().unwrap()),
             Err(e) => eprintln!("{}", e),
         },
@@ -49,7 +50,8 @@ fn main() {
             metric_a,
             metric_b,
             out_path,
-        } => match compare_stat_2d(&stats_a, &stats_b, &metric_a, &metric_b, out_path) {
+            epsilon,
+        } => match compare_stat_2d(&stats_a, &stats_b, &metric_a, &metric_b, out_path, epsilon) {
             Ok(p) => println!("Plot change rate of stats to {}", p.to_str().unwrap()),
             Err(e) => eprintln!("{}", e),
         },
@@ -123,7 +125,8 @@ fn main() {
             data_b,
             metric,
             out_path,
-        } => match compare_data(&data_a, &data_b, &metric, out_path) {
+            test,
+        } => match compare_data(&data_a, &data_b, &metric, out_path, test) {
             Ok(p) => println!("Plot change rate of data to {}", p.to_str().unwrap()),
             Err(e) => eprintln!("{}", e),
         },
@@ -6,13 +6,15 @@ use std::{
 
 use collector::statistics::runtime_stat::{RuntimeResultVec, RuntimeStatistics};
 
+use super::measured_by;
+
 pub fn merge_runtime_stats(
     root_dir: &PathBuf,
     rustc: String,
     out_dir: PathBuf,
 ) -> anyhow::Result<PathBuf> {
     let mut merged_stats = RuntimeStatistics::new();
-    let mut merged_data = RuntimeResultVec { 0: vec![] };
+    let mut merged_data = RuntimeResultVec::default();
 
     // Iterate each benchmark group under root dir.
     for bench_group in read_dir(root_dir)? {
@@ -21,19 +23,21 @@ pub fn merge_runtime_stats(
         if bench_group.metadata()?.is_dir() {
             for rustc_dir in read_dir(bench_group.path())? {
                 let rustc_dir = rustc_dir?;
-                // Find wanted rustc version.
-                if rustc_dir.metadata()?.is_dir()
-                    && rustc_dir.file_name().to_str().unwrap().to_string() == rustc
-                {
+                if rustc_dir.metadata()?.is_dir() {
                     // Find statistics file
                     for f in read_dir(rustc_dir.path())? {
                         let f = f?;
                         if f.file_name().to_str().unwrap().contains("results.json") {
                             let mut data: RuntimeResultVec =
                                 serde_json::from_reader(BufReader::new(File::open(f.path())?))?;
+                            // Find wanted rustc version.
+                            if !measured_by(data.metadata.as_ref(), &rustc_dir.file_name(), &rustc)
+                            {
+                                continue;
+                            }
 
                             merged_stats.append(&mut data.calculate_statistics());
-                            merged_data.0.append(&mut data.0);
+                            merged_data.results.append(&mut data.results);
                         }
                     }
                 }
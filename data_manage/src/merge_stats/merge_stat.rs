@@ -9,6 +9,8 @@ use collector::{
     statistics::compile_time_stat::{CompileTimeResultSet, CompileTimeStatistics},
 };
 
+use super::measured_by;
+
 pub fn merge_compile_time_stats(
     root_dir: &PathBuf,
     profile: Profile,
@@ -25,16 +27,18 @@ pub fn merge_compile_time_stats(
         if bench_group.metadata()?.is_dir() {
             for rustc_dir in read_dir(bench_group.path())? {
                 let rustc_dir = rustc_dir?;
-                // Find wanted rustc version.
-                if rustc_dir.metadata()?.is_dir()
-                    && rustc_dir.file_name().to_str().unwrap().to_string() == rustc
-                {
+                if rustc_dir.metadata()?.is_dir() {
                     // Find statistics file
                     for f in read_dir(rustc_dir.path())? {
                         let f = f?;
                         if f.file_name().to_str().unwrap().contains("results.json") {
                             let mut data: CompileTimeResultSet =
                                 serde_json::from_reader(BufReader::new(File::open(f.path())?))?;
+                            // Find wanted rustc version.
+                            if !measured_by(data.metadata.as_ref(), &rustc_dir.file_name(), &rustc)
+                            {
+                                continue;
+                            }
 
                             merged_stats.append(
                                 &mut data
@@ -1,2 +1,41 @@
+use std::ffi::OsStr;
+
+use collector::statistics::metadata::Metadata;
+
 pub mod merge_runtime_stat;
 pub mod merge_stat;
+
+/// Whether the results in directory `dir_name` were measured by `rustc`,
+/// either by the toolchain recorded in their `metadata` or by the name of
+/// their directory.
+fn measured_by(metadata: Option<&Metadata>, dir_name: &OsStr, rustc: &str) -> bool {
+    metadata.is_some_and(|m| m.toolchain.matches(rustc)) || dir_name == rustc
+}
+
+#[cfg(test)]
+mod test_merge_stats {
+    use std::ffi::OsStr;
+
+    use collector::statistics::metadata::{Metadata, ToolchainIdentity};
+
+    use super::measured_by;
+
+    /// Test for measured_by
+    ///
+    /// Step1. Match labeled results by their label and by their directory.
+    ///
+    /// Step2. Match results without metadata by their directory only.
+    #[test]
+    fn test_measured_by() {
+        let metadata = Metadata {
+            toolchain: ToolchainIdentity::default().with_label(Some("stable".to_string())),
+            ..Default::default()
+        };
+        assert!(measured_by(Some(&metadata), OsStr::new("a"), "stable"));
+        assert!(measured_by(Some(&metadata), OsStr::new("old"), "old"));
+        assert!(!measured_by(Some(&metadata), OsStr::new("a"), "old"));
+
+        assert!(measured_by(None, OsStr::new("old"), "old"));
+        assert!(!measured_by(None, OsStr::new("a"), "stable"));
+    }
+}
@@ -25,6 +25,7 @@ pub fn normalize_compile_time_stat(
     normalize_metric: &String,
 ) -> CompileTimeStatistics {
     let mut normalized_stat = stat.clone();
+    let mut undefined = vec![];
     normalized_stat.iter_mut().for_each(|stat| {
         let n = stat
             .statistic_vec
@@ -33,15 +34,29 @@ pub fn normalize_compile_time_stat(
             .collect::<HashMap<&String, &Statistics>>()
             .get(normalize_metric)
             .unwrap()
-            .geometric_mean;
+            .geometric_mean
+            .filter(|n| *n != 0.0);
+        if n.is_none() {
+            undefined.push(stat.name.clone());
+        }
 
         stat.statistic_vec.iter_mut().for_each(|(m, s)| {
             if m != normalize_metric {
-                s.geometric_mean /= n;
+                s.geometric_mean = s.geometric_mean.zip(n).map(|(g, n)| g / n);
             }
         });
     });
 
+    if !undefined.is_empty() {
+        eprintln!(
+            "Geometric mean of {} is undefined or zero for {} benchmarks, \
+             their normalized geometric means are undefined: {}",
+            normalize_metric,
+            undefined.len(),
+            undefined.join(", ")
+        );
+    }
+
     normalized_stat
 }
 
@@ -113,7 +113,15 @@ fn merge_metrics_from_compile_time_stats(
             m.clone(),
             stats
                 .iter()
-                .map(|(b, stats_map)| (b.clone(), stats_map.get(&m).unwrap().geometric_mean))
+                .filter_map(
+                    |(b, stats_map)| match stats_map.get(&m).unwrap().geometric_mean {
+                        Some(g) => Some((b.clone(), g)),
+                        None => {
+                            eprintln!("Geometric mean of {} is undefined for {}, skipped.", m, b);
+                            None
+                        }
+                    },
+                )
                 .collect(),
         );
     });
@@ -144,7 +152,15 @@ fn merge_metrics_from_runtime_stats(
             m.clone(),
             stats
                 .iter()
-                .map(|(b, stats_map)| (b.clone(), stats_map.get(&m).unwrap().geometric_mean))
+                .filter_map(
+                    |(b, stats_map)| match stats_map.get(&m).unwrap().geometric_mean {
+                        Some(g) => Some((b.clone(), g)),
+                        None => {
+                            eprintln!("Geometric mean of {} is undefined for {}, skipped.", m, b);
+                            None
+                        }
+                    },
+                )
                 .collect(),
         );
     });